@@ -0,0 +1,204 @@
+//! Derive and procedural macros for [edo](https://docs.rs/edo).
+//!
+//! # Examples
+//!
+//! ### Compile time checked templates
+//!
+//! `edo!` parses a template while your crate is compiled, so syntax errors are reported by `cargo build` instead of `Edo::new` at runtime. It expands to an `Edo` built from the already parsed template.
+//!
+//! ```
+//! #[macro_use]
+//! extern crate edo_derive;
+//! extern crate edo;
+//!
+//! use edo::Edo;
+//!
+//! # fn main() {
+//! let mut template: Edo<()> = edo!("Hello {name}");
+//! template.register_static("name", "World!");
+//! assert_eq!(template.render(&()), "Hello World!");
+//! # }
+//! ```
+//!
+//! ```compile_fail
+//! # #[macro_use] extern crate edo_derive;
+//! # extern crate edo;
+//! # fn main() {
+//! let template: edo::Edo<()> = edo!("Hello {name");
+//! # }
+//! ```
+//!
+//! ### Struct contexts
+//!
+//! `#[derive(EdoContext)]` implements `edo::context::Lookup` for a struct, exposing each field as a placeholder value. Fields are converted with `ToString`.
+//!
+//! ```
+//! #[macro_use]
+//! extern crate edo_derive;
+//! extern crate edo;
+//!
+//! use edo::Edo;
+//!
+//! #[derive(EdoContext)]
+//! struct User {
+//!     username: String,
+//!     age: u32,
+//!     #[edo(rename = "email")]
+//!     email_address: String,
+//!     #[edo(skip)]
+//!     #[allow(dead_code)]
+//!     password: String,
+//! }
+//!
+//! # fn main() {
+//! let user = User {
+//!     username: "gio".to_string(),
+//!     age: 30,
+//!     email_address: "gio@example.com".to_string(),
+//!     password: "hunter2".to_string(),
+//! };
+//!
+//! let mut template = Edo::new("{username} ({age}) <{email}>").unwrap();
+//! template.use_context_lookup();
+//! let output = template.render(&user);
+//! assert_eq!(output, "gio (30) <gio@example.com>");
+//! # }
+//! ```
+//!
+//! ### Field attributes
+//!
+//! * `#[edo(rename = "name")]` - Use a different placeholder name for the field
+//! * `#[edo(skip)]` - Don't expose the field at all
+#![deny(missing_docs)]
+
+extern crate edo;
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use edo::parse::{self, Case, Expression};
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use syn::{Data, DeriveInput, Fields, LitStr};
+
+/// Parse a template at compile time, expanding to an `edo::Edo`
+///
+/// Invalid templates are reported as compile errors pointing at the template string.
+#[proc_macro]
+pub fn edo(input: TokenStream) -> TokenStream {
+    match syn::parse::<LitStr>(input).and_then(|source| edo_template(&source)) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => compile_error(error).into(),
+    }
+}
+
+fn edo_template(source: &LitStr) -> syn::Result<TokenStream2> {
+    let value = source.value();
+    let template = parse::parse(&value)
+        .map_err(|error| syn::Error::new(source.span(), format!("invalid edo template: {}", error)))?;
+
+    let expressions = template.iter().map(|expression| match *expression {
+        Expression::Literal(text) => quote! {
+            ::edo::parse::Expression::Literal(#text)
+        },
+        Expression::Function { name, ref arguments, ref argument_spans, has_parentheses, case, ref filters, format, raw, span } => {
+            let (start, end) = (span.start, span.end);
+            let case = match case {
+                Some(Case::Upper) => quote!(Some(::edo::parse::Case::Upper)),
+                Some(Case::Lower) => quote!(Some(::edo::parse::Case::Lower)),
+                Some(Case::Title) => quote!(Some(::edo::parse::Case::Title)),
+                None => quote!(None),
+            };
+            let format = match format {
+                Some(format) => quote!(Some(#format)),
+                None => quote!(None),
+            };
+            let (starts, ends) = (argument_spans.iter().map(|span| span.start), argument_spans.iter().map(|span| span.end));
+            quote! {
+                ::edo::parse::Expression::Function {
+                    name: #name,
+                    arguments: vec![#(#arguments),*],
+                    argument_spans: vec![#(::edo::parse::Span { start: #starts, end: #ends }),*],
+                    has_parentheses: #has_parentheses,
+                    case: #case,
+                    filters: vec![#(#filters),*],
+                    format: #format,
+                    raw: #raw,
+                    span: ::edo::parse::Span { start: #start, end: #end },
+                }
+            }
+        },
+    });
+    Ok(quote! {
+        ::edo::Edo::from_expressions(vec![#(#expressions),*])
+    })
+}
+
+/// `syn::Error::to_compile_error` refers to `::core`, which 2015 edition crates can't see
+fn compile_error(error: syn::Error) -> TokenStream2 {
+    let message = error.to_string();
+    quote_spanned!(error.span()=> compile_error!(#message))
+}
+
+/// Expose a struct's fields as placeholder values by implementing `edo::context::Lookup`
+#[proc_macro_derive(EdoContext, attributes(edo))]
+pub fn derive_edo_context(input: TokenStream) -> TokenStream {
+    match syn::parse::<DeriveInput>(input).and_then(|input| edo_context(&input)) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => {
+            let error = compile_error(error);
+            quote!(#error;).into()
+        },
+    }
+}
+
+fn edo_context(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(&input.ident, "EdoContext can only be derived for structs with named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(&input.ident, "EdoContext can only be derived for structs")),
+    };
+
+    let mut arms = vec![];
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named fields have identifiers");
+        let mut name = ident.to_string();
+        let mut skip = false;
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("edo")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    let rename: LitStr = meta.value()?.parse()?;
+                    name = rename.value();
+                    Ok(())
+                } else if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported edo attribute, expected `rename` or `skip`"))
+                }
+            })?;
+        }
+        if !skip {
+            arms.push(quote! {
+                #name => ::edo::__private::Option::Some(::edo::__private::Cow::Owned(::edo::__private::ToString::to_string(&self.#ident))),
+            });
+        }
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::edo::context::Lookup for #ident #ty_generics #where_clause {
+            fn get(&self, name: &str) -> ::edo::__private::Option<::edo::__private::Cow<'_, str>> {
+                match name {
+                    #(#arms)*
+                    _ => ::edo::__private::Option::None,
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,73 @@
+#[macro_use]
+extern crate edo_derive;
+extern crate edo;
+
+use std::borrow::Cow;
+use std::fmt::Display;
+
+use edo::Edo;
+use edo::context::Lookup;
+
+#[derive(EdoContext)]
+struct User {
+    username: String,
+    age: u32,
+    #[edo(rename = "mail")]
+    email: &'static str,
+    #[edo(skip)]
+    #[allow(dead_code)]
+    password: String,
+}
+
+#[derive(EdoContext)]
+struct Wrapper<T: Display> {
+    value: T,
+}
+
+fn user() -> User {
+    User {
+        username: "gio".to_string(),
+        age: 30,
+        email: "gio@example.com",
+        password: "hunter2".to_string(),
+    }
+}
+
+#[test]
+fn lookup_fields() {
+    let user = user();
+    assert_eq!(user.get("username"), Some(Cow::Borrowed("gio")));
+    assert_eq!(user.get("age"), Some(Cow::Borrowed("30")));
+}
+
+#[test]
+fn lookup_renamed_field() {
+    let user = user();
+    assert_eq!(user.get("mail"), Some(Cow::Borrowed("gio@example.com")));
+    assert_eq!(user.get("email"), None);
+}
+
+#[test]
+fn lookup_skipped_field() {
+    assert_eq!(user().get("password"), None);
+}
+
+#[test]
+fn lookup_generic_field() {
+    assert_eq!(Wrapper { value: 1.5 }.get("value"), Some(Cow::Borrowed("1.5")));
+}
+
+#[test]
+fn render_struct_context() {
+    let user = user();
+    let mut edo = match Edo::new("{greeting} {username}, you are {age}.{password}") {
+        Ok(edo) => edo,
+        Err(err) => panic!("{}", err),
+    };
+    edo.register_static("greeting", "Hello");
+    edo.use_context_lookup();
+    assert_eq!(
+        edo.render(&user),
+        "Hello gio, you are 30."
+    );
+}
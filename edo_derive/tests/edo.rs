@@ -0,0 +1,33 @@
+#[macro_use]
+extern crate edo_derive;
+extern crate edo;
+
+use edo::Edo;
+
+#[test]
+fn render_compiled_template() {
+    let mut edo: Edo<str> = edo!("Hello {name}, {greet(a, b)}!");
+    edo.register_handler("name", |_, context| Ok(context.to_string()));
+    edo.register_handler("greet", |args, _| Ok(args.join(" and ")));
+    assert_eq!(
+        edo.render("Gio"),
+        "Hello Gio, a and b!"
+    );
+}
+
+#[test]
+fn compiled_template_matches_runtime_parse() {
+    let mut compiled: Edo<()> = edo!("{a}-{b(1)}");
+    let mut parsed: Edo<()> = Edo::new("{a}-{b(1)}").unwrap();
+    for edo in [&mut compiled, &mut parsed].iter_mut() {
+        edo.register_static("a", "A");
+        edo.register_handler("b", |args, _| Ok(args[0].to_string()));
+    }
+    assert_eq!(compiled.render(&()), parsed.render(&()));
+}
+
+#[test]
+fn invalid_templates_fail_to_compile() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
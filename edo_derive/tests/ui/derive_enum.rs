@@ -0,0 +1,10 @@
+#[macro_use]
+extern crate edo_derive;
+extern crate edo;
+
+#[derive(EdoContext)]
+enum Context {
+    Empty,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[macro_use]
+extern crate edo_derive;
+extern crate edo;
+
+fn main() {
+    let _template: edo::Edo<()> = edo!(42);
+}
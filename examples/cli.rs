@@ -0,0 +1,116 @@
+//! A tiny command line renderer.
+//!
+//! The first argument is the template, every following `key=value` argument is
+//! registered as a static replacement.
+//!
+//! ```text
+//! $ cargo run --example cli -- "Hello {name}, {upper(loud)}!" name=World
+//! Hello World, LOUD!
+//! ```
+//!
+//! Errors in the template point at the placeholder that caused them.
+//!
+//! ```text
+//! $ cargo run --example cli -- "Hello {name(a}"
+//! error[E0002]: Invalid argument list, expected arguments separated by commas and a closing )
+//!  --> 1:7
+//!   |
+//! 1 | Hello {name(a}
+//!   |       ^^^^^^^^
+//! ```
+extern crate edo;
+
+use std::env;
+use std::process;
+
+use edo::diagnostic::Diagnostic;
+use edo::Edo;
+
+/// Render a template from a list of command line arguments
+fn run(args: &[String]) -> Result<String, String> {
+    let (template, values) = match args.split_first() {
+        Some((template, values)) => (template, values),
+        None => return Err("usage: cli <template> [key=value...]".to_string()),
+    };
+
+    let mut edo: Edo<()> = Edo::new(template)
+        .map_err(|err| Diagnostic::from_parse_error(template, &err).to_string())?;
+    edo.register_handler("upper", |args, _| Ok(args.join(" ").to_uppercase()));
+    for value in values {
+        let mut parts = value.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) => edo.register_static(key, value),
+            _ => return Err(format!("expected key=value, got {}", value)),
+        };
+    }
+
+    let (output, errors) = edo.render_with_errors(&());
+    match errors.into_iter().next() {
+        Some(error) => Err(match Diagnostic::from_render_error(template, &error) {
+            Some(diagnostic) => diagnostic.to_string(),
+            None => error.to_string(),
+        }),
+        None => Ok(output),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+        Ok(output) => println!("{}", output),
+        Err(error) => {
+            eprint!("{}", error);
+            if !error.ends_with('\n') {
+                eprintln!();
+            }
+            process::exit(1);
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn renders_key_value_pairs() {
+        assert_eq!(
+            run(&args(&["Hello {name}, {upper(loud)}!", "name=World"])),
+            Ok("Hello World, LOUD!".to_string())
+        );
+    }
+
+    #[test]
+    fn values_may_contain_equals_signs() {
+        assert_eq!(
+            run(&args(&["{query}", "query=a=b"])),
+            Ok("a=b".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_pairs() {
+        assert_eq!(
+            run(&args(&["{name}", "name"])),
+            Err("expected key=value, got name".to_string())
+        );
+    }
+
+    #[test]
+    fn points_at_bad_placeholders() {
+        assert_eq!(
+            run(&args(&["Hi {name|nope}", "name=Gio"])),
+            Err("error[E0103]: Unknown filter nope\n --> 1:4\n  |\n1 | Hi {name|nope}\n  |    ^^^^^^^^^^^\n".to_string())
+        );
+        assert!(run(&args(&["Hi {name"])).unwrap_err().ends_with("|    ^^^^^\n"));
+    }
+
+    #[test]
+    fn requires_a_template() {
+        assert!(run(&[]).is_err());
+    }
+}
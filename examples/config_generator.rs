@@ -0,0 +1,114 @@
+//! Generating configuration files for several environments from one template.
+//!
+//! Static values are shared by every environment, while handlers look up the
+//! environment specific settings from the context.
+//!
+//! Run with `cargo run --example config_generator`.
+extern crate edo;
+
+use edo::Edo;
+
+/// Settings that differ between deployments
+struct Environment {
+    name: &'static str,
+    domain: &'static str,
+    workers: u32,
+    services: Vec<(&'static str, u16)>,
+}
+
+const SUPERVISOR: &str = "; Generated for {env} by {generator}
+[program:api]
+command=/usr/bin/api --port {port(api)} --workers {workers}
+environment=DOMAIN=\"{domain}\"
+
+[program:admin]
+command=/usr/bin/admin --port {port(admin)}
+";
+
+fn supervisor_template() -> Edo<'static, Environment> {
+    let mut template = Edo::new(SUPERVISOR).unwrap();
+    template.register_static("generator", "edo");
+    template.register_handler("env", |_, env: &Environment| Ok(env.name.to_string()));
+    template.register_handler("domain", |_, env: &Environment| Ok(env.domain.to_string()));
+    template.register_handler("workers", |_, env: &Environment| Ok(env.workers.to_string()));
+    template.register_handler("port", |args, env: &Environment| {
+        env.services.iter()
+            .find(|&&(service, _)| service == args[0])
+            .map(|&(_, port)| port.to_string())
+            .ok_or_else(|| format!("No port configured for service {} in {}", args[0], env.name))
+    });
+    template
+}
+
+fn environments() -> Vec<Environment> {
+    vec![
+        Environment {
+            name: "staging",
+            domain: "staging.example.com",
+            workers: 1,
+            services: vec![("api", 8000), ("admin", 8001)],
+        },
+        Environment {
+            name: "production",
+            domain: "example.com",
+            workers: 8,
+            services: vec![("api", 9000), ("admin", 9001)],
+        },
+    ]
+}
+
+/// Render the config for every environment, failing on the first broken one
+fn generate(template: &mut Edo<'static, Environment>, envs: Vec<Environment>) -> Result<Vec<(String, String)>, String> {
+    let mut files = vec![];
+    for env in envs {
+        let file_name = format!("supervisord.{}.conf", env.name);
+        let (output, errors) = template.render_with_errors(&env);
+        if let Some(error) = errors.into_iter().next() {
+            return Err(error.to_string());
+        }
+        files.push((file_name, output));
+    }
+    Ok(files)
+}
+
+fn main() {
+    let mut template = supervisor_template();
+    match generate(&mut template, environments()) {
+        Ok(files) => for (name, contents) in files {
+            println!("==> {} <==\n{}", name, contents);
+        },
+        Err(error) => eprintln!("error: {}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{environments, generate, supervisor_template, Environment};
+
+    #[test]
+    fn generates_every_environment() {
+        let mut template = supervisor_template();
+        let files = generate(&mut template, environments()).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].0, "supervisord.staging.conf");
+        assert!(files[0].1.starts_with("; Generated for staging by edo\n"));
+        assert!(files[1].1.contains("command=/usr/bin/api --port 9000 --workers 8"));
+        assert!(files[1].1.contains("environment=DOMAIN=\"example.com\""));
+        assert!(files[1].1.contains("command=/usr/bin/admin --port 9001"));
+    }
+
+    #[test]
+    fn reports_missing_service() {
+        let mut template = supervisor_template();
+        let broken = Environment {
+            name: "dev",
+            domain: "localhost",
+            workers: 1,
+            services: vec![("api", 3000)],
+        };
+        assert_eq!(
+            generate(&mut template, vec![broken]),
+            Err("No port configured for service admin in dev".to_string())
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Rendering a batch of personalised emails.
+//!
+//! The subject and body are separate templates sharing the same context type.
+//! Messages with failing handlers are held back instead of being sent with
+//! holes in them.
+//!
+//! Run with `cargo run --example email_pipeline`.
+extern crate edo;
+
+use edo::Edo;
+
+struct Recipient {
+    name: String,
+    email: String,
+    plan: Option<String>,
+    days_left: u32,
+}
+
+#[derive(Debug, PartialEq)]
+struct Email {
+    to: String,
+    subject: String,
+    body: String,
+}
+
+const SUBJECT: &str = "{name}, your {plan} trial ends in {days}";
+const BODY: &str = "Hi {name},
+
+Your {plan} trial ends in {days}. Upgrade at {link(upgrade)} to keep your data.
+
+{signature}";
+
+/// Register the handlers shared by the subject and the body
+fn register_common(template: &mut Edo<'static, Recipient>) {
+    template.register_handler("name", |_, recipient: &Recipient| Ok(recipient.name.clone()));
+    template.register_handler("plan", |_, recipient: &Recipient| match recipient.plan {
+        Some(ref plan) => Ok(plan.clone()),
+        None => Err(format!("{} has no plan", recipient.email)),
+    });
+    template.register_handler("days", |_, recipient: &Recipient| {
+        Ok(match recipient.days_left {
+            1 => "1 day".to_string(),
+            days => format!("{} days", days),
+        })
+    });
+}
+
+struct Pipeline {
+    subject: Edo<'static, Recipient>,
+    body: Edo<'static, Recipient>,
+}
+
+impl Pipeline {
+    fn new() -> Pipeline {
+        let mut subject = Edo::new(SUBJECT).unwrap();
+        register_common(&mut subject);
+
+        let mut body = Edo::new(BODY).unwrap();
+        register_common(&mut body);
+        body.register_handler("link", |args, _| Ok(format!("https://example.com/{}", args[0])));
+        body.register_static("signature", "-- The Example Team");
+
+        Pipeline { subject, body }
+    }
+
+    /// Render every recipient, splitting them into sendable emails and failures
+    fn run(&mut self, recipients: Vec<Recipient>) -> (Vec<Email>, Vec<String>) {
+        let mut outbox = vec![];
+        let mut failures = vec![];
+        for recipient in recipients {
+            let (subject, mut errors) = self.subject.render_with_errors(&recipient);
+            let (body, body_errors) = self.body.render_with_errors(&recipient);
+            errors.extend(body_errors);
+
+            if errors.is_empty() {
+                outbox.push(Email { to: recipient.email, subject, body });
+            } else {
+                // Both templates use {plan}, so the same message shows up twice
+                let mut errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+                errors.dedup();
+                failures.extend(errors);
+            }
+        }
+        (outbox, failures)
+    }
+}
+
+fn recipients() -> Vec<Recipient> {
+    vec![
+        Recipient {
+            name: "Ada".to_string(),
+            email: "ada@example.com".to_string(),
+            plan: Some("Pro".to_string()),
+            days_left: 3,
+        },
+        Recipient {
+            name: "Grace".to_string(),
+            email: "grace@example.com".to_string(),
+            plan: Some("Team".to_string()),
+            days_left: 1,
+        },
+        Recipient {
+            name: "Linus".to_string(),
+            email: "linus@example.com".to_string(),
+            plan: None,
+            days_left: 7,
+        },
+    ]
+}
+
+fn main() {
+    let mut pipeline = Pipeline::new();
+    let (outbox, failures) = pipeline.run(recipients());
+    for email in outbox {
+        println!("To: {}\nSubject: {}\n\n{}\n", email.to, email.subject, email.body);
+    }
+    for failure in failures {
+        eprintln!("skipped: {}", failure);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{recipients, Pipeline};
+
+    #[test]
+    fn renders_subject_and_body() {
+        let mut pipeline = Pipeline::new();
+        let (outbox, _) = pipeline.run(recipients());
+        assert_eq!(outbox.len(), 2);
+        assert_eq!(outbox[0].to, "ada@example.com");
+        assert_eq!(outbox[0].subject, "Ada, your Pro trial ends in 3 days");
+        assert_eq!(outbox[1].subject, "Grace, your Team trial ends in 1 day");
+        assert!(outbox[0].body.contains("Upgrade at https://example.com/upgrade to keep"));
+        assert!(outbox[0].body.ends_with("-- The Example Team"));
+    }
+
+    #[test]
+    fn holds_back_broken_messages() {
+        let mut pipeline = Pipeline::new();
+        let (_, failures) = pipeline.run(recipients());
+        assert_eq!(failures, vec!["linus@example.com has no plan".to_string()]);
+    }
+}
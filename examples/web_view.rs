@@ -0,0 +1,132 @@
+//! Using edo as the view layer of a small web application.
+//!
+//! Each request is passed to the template as its context, so handlers can pull
+//! values off of the request without any global state. Values are HTML
+//! escaped, except for the links, which are marked raw with `{!link(..)}`.
+//!
+//! Run with `cargo run --example web_view`.
+extern crate edo;
+
+use std::collections::HashMap;
+
+use edo::escape::Escaping;
+use edo::Edo;
+
+/// A stripped down HTTP request, standing in for whatever your framework hands you
+struct Request {
+    path: String,
+    user: Option<String>,
+    query: HashMap<String, String>,
+}
+
+const PAGE: &str = "<html>
+<head><title>{title}</title></head>
+<body>
+<nav>{greeting} | {!link(home)} {!link(about)}</nav>
+<p>You requested {path}, page {query(page)}.</p>
+</body>
+</html>";
+
+/// Build the view for a page, registering every handler the page needs
+fn page_view() -> Edo<'static, Request> {
+    let mut view = Edo::new(PAGE).unwrap();
+    view.set_escaping(Escaping::Html);
+    view.register_static("title", "Edo Example");
+    view.register_handler("path", |_, request: &Request| Ok(request.path.clone()));
+    view.register_handler("greeting", |_, request: &Request| {
+        Ok(match request.user {
+            Some(ref user) => format!("Welcome back, {}", user),
+            None => "Hello, stranger".to_string(),
+        })
+    });
+    view.register_handler("link", |args, _| {
+        let target = args[0];
+        Ok(format!("<a href=\"/{}\">{}</a>", target, target))
+    });
+    view.register_handler("query", |args, request: &Request| {
+        match request.query.get(args[0]) {
+            Some(value) => Ok(value.clone()),
+            None => Err(format!("Missing query parameter {}", args[0])),
+        }
+    });
+    view
+}
+
+/// Handle a single request, returning the status code and body
+fn handle(view: &mut Edo<'static, Request>, request: &Request) -> (u16, String) {
+    let (body, errors) = view.render_with_errors(request);
+    if errors.is_empty() {
+        (200, body)
+    } else {
+        let messages: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+        (400, messages.join("\n"))
+    }
+}
+
+fn main() {
+    let mut view = page_view();
+
+    let mut query = HashMap::new();
+    query.insert("page".to_string(), "2".to_string());
+    let request = Request {
+        path: "/blog".to_string(),
+        user: Some("Gio".to_string()),
+        query,
+    };
+
+    let (status, body) = handle(&mut view, &request);
+    println!("HTTP {}\n\n{}", status, body);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{handle, page_view, Request};
+
+    fn request(user: Option<&str>, page: Option<&str>) -> Request {
+        let mut query = HashMap::new();
+        if let Some(page) = page {
+            query.insert("page".to_string(), page.to_string());
+        }
+        Request {
+            path: "/blog".to_string(),
+            user: user.map(|user| user.to_string()),
+            query,
+        }
+    }
+
+    #[test]
+    fn renders_logged_in_page() {
+        let mut view = page_view();
+        let (status, body) = handle(&mut view, &request(Some("Gio"), Some("2")));
+        assert_eq!(status, 200);
+        assert!(body.contains("<title>Edo Example</title>"));
+        assert!(body.contains("Welcome back, Gio"));
+        assert!(body.contains("<a href=\"/about\">about</a>"));
+        assert!(body.contains("You requested /blog, page 2."));
+    }
+
+    #[test]
+    fn renders_anonymous_page() {
+        let mut view = page_view();
+        let (_, body) = handle(&mut view, &request(None, Some("1")));
+        assert!(body.contains("Hello, stranger"));
+    }
+
+    #[test]
+    fn escapes_request_values() {
+        let mut view = page_view();
+        let (_, body) = handle(&mut view, &request(Some("<b>Gio</b>"), Some("2\"><script>")));
+        assert!(body.contains("Welcome back, &lt;b&gt;Gio&lt;/b&gt;"));
+        assert!(body.contains("page 2&quot;&gt;&lt;script&gt;."));
+    }
+
+    #[test]
+    fn missing_query_is_a_bad_request() {
+        let mut view = page_view();
+        let (status, body) = handle(&mut view, &request(None, None));
+        assert_eq!(status, 400);
+        assert_eq!(body, "Missing query parameter page");
+    }
+}
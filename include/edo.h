@@ -0,0 +1,30 @@
+/* C bindings for edo, built with `cargo rustc --release --features ffi --crate-type cdylib` */
+#ifndef EDO_H
+#define EDO_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct EdoTemplate EdoTemplate;
+
+/* Parse a template, returns NULL if it is invalid. Free with edo_free. */
+EdoTemplate *edo_new(const char *template_source);
+
+/* Register a static replacement, returns 0 on success and -1 on error. */
+int edo_register_static(EdoTemplate *tmpl, const char *name, const char *value);
+
+/* Render a template, returns NULL on error. Free with edo_string_free. */
+char *edo_render(const EdoTemplate *tmpl);
+
+/* Free a string returned by edo_render. */
+void edo_string_free(char *string);
+
+/* Free a template returned by edo_new. */
+void edo_free(EdoTemplate *tmpl);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
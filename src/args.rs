@@ -0,0 +1,338 @@
+//! The arguments handed to handlers
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::{self, Vec};
+use core::fmt;
+use core::ops::{Deref, Range, RangeFrom, RangeInclusive};
+use core::slice;
+use core::str::FromStr;
+#[cfg(feature = "std")]
+use std::error::Error;
+
+use parse;
+
+/// The arguments of a placeholder, such as `a` and `b` in `{name(a, b)}`
+///
+/// It derefs to a slice of the arguments, so `args[0]`, `args.len()` and `args.join(" ")` work as they would on a `Vec<&str>`. [`get_parsed`](#method.get_parsed) parses an argument, with an error that can be returned from the handler with `?`.
+///
+/// Arguments written `key=value`, where the key is letters, digits, `_` or `-`, are also named arguments, see [`named`](#method.named). They are still in the slice of every argument, [`positional`](#method.positional) leaves them out.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// let mut template = Edo::new("{repeat(ab, 3)} {repeat(ab, x)} {repeat(ab)} {repeat(ab, 2, sep=-)}").unwrap();
+/// template.register_handler("repeat", |args, _| {
+///     let text = args.get(0).unwrap_or("");
+///     let count: usize = args.get_parsed(1)?;
+///     Ok(vec![text; count].join(args.get_named("sep").unwrap_or("")))
+/// });
+/// let (output, errors) = template.render_with_errors(&());
+/// assert_eq!(output, "ababab   ab-ab");
+/// assert_eq!(errors[0].to_string(), "Argument 2 isn't valid: x");
+/// assert_eq!(errors[1].to_string(), "Argument 2 is missing");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Args<'a> {
+    values: Vec<&'a str>,
+    named: Vec<(&'a str, &'a str)>,
+    has_parentheses: bool,
+}
+
+impl<'a> Args<'a> {
+    /// The number of arguments
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether there are no arguments
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The argument at `index`, counting from 0
+    pub fn get(&self, index: usize) -> Option<&'a str> {
+        self.values.get(index).cloned()
+    }
+
+    /// The argument at `index`, parsed
+    pub fn get_parsed<T: FromStr>(&self, index: usize) -> Result<T, ArgError> {
+        let value = self.get(index).ok_or(ArgError::Missing { index })?;
+        value.parse().map_err(|_| ArgError::Invalid { index, value: value.to_string() })
+    }
+
+    /// The value of the first named argument called `name`
+    pub fn get_named(&self, name: &str) -> Option<&'a str> {
+        self.named.iter().find(|&&(key, _)| key == name).map(|&(_, value)| value)
+    }
+
+    /// The named arguments as key and value, in the order they are written
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new("{link(/docs, title = Docs, rel=help)}").unwrap();
+    /// template.register_handler("link", |args, _| {
+    ///     let attributes: Vec<_> = args.named().iter().map(|(key, value)| format!(" {}=\"{}\"", key, value)).collect();
+    ///     Ok(format!("<a href=\"{}\"{}>", args.positional().join(""), attributes.concat()))
+    /// });
+    /// assert_eq!(template.render(&()), "<a href=\"/docs\" title=\"Docs\" rel=\"help\">");
+    /// ```
+    pub fn named(&self) -> &[(&'a str, &'a str)] {
+        &self.named
+    }
+
+    /// The arguments that aren't named, in order
+    pub fn positional(&self) -> Vec<&'a str> {
+        self.values.iter().cloned().filter(|argument| named(argument).is_none()).collect()
+    }
+
+    /// The arguments from `index` on, empty if there are fewer
+    ///
+    /// This is the tail of a variadic handler, one registered with an arity such as `1..`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new("{join(-, a, b, c, d)} {join(-)}").unwrap();
+    /// template.register_handler_with_arity("join", 1.., |args, _| Ok(args.rest(1).join(args[0])));
+    /// assert_eq!(template.render(&()), "a-b-c-d ");
+    /// ```
+    pub fn rest(&self, index: usize) -> &[&'a str] {
+        self.values.get(index..).unwrap_or(&[])
+    }
+
+    /// The arguments as a slice
+    pub fn as_slice(&self) -> &[&'a str] {
+        &self.values
+    }
+
+    /// Whether the placeholder has an argument list, even an empty one
+    ///
+    /// `{name}` has none, `{name()}` has one without arguments and `{name(,)}` has one with an empty argument.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new("{f} {f()} {f(,)} {f(a,,)}").unwrap();
+    /// template.register_handler("f", |args, _| Ok(format!("{}:{:?}", args.has_parentheses(), args.as_slice())));
+    /// assert_eq!(template.render(&()), r#"false:[] true:[] true:[""] true:["a", ""]"#);
+    /// ```
+    pub fn has_parentheses(&self) -> bool {
+        self.has_parentheses
+    }
+}
+
+// The arguments of a placeholder with their escapes replaced, for `Args::borrow`
+pub(crate) fn unescape<'s>(arguments: &[&'s str]) -> Vec<Cow<'s, str>> {
+    arguments.iter().map(|argument| parse::unescape(argument)).collect()
+}
+
+impl<'a> Args<'a> {
+    pub(crate) fn new(values: Vec<&'a str>, has_parentheses: bool) -> Args<'a> {
+        let named = values.iter().filter_map(|argument| named(argument)).collect();
+        Args { values, named, has_parentheses }
+    }
+
+    pub(crate) fn borrow(arguments: &'a [Cow<str>], has_parentheses: bool) -> Args<'a> {
+        Args::new(arguments.iter().map(|argument| &**argument).collect(), has_parentheses)
+    }
+}
+
+// Arguments from a `Vec` have parentheses unless there are none
+impl<'a> From<Vec<&'a str>> for Args<'a> {
+    fn from(values: Vec<&'a str>) -> Args<'a> {
+        let has_parentheses = !values.is_empty();
+        Args::new(values, has_parentheses)
+    }
+}
+
+// The key and value of a `key=value` argument, but not of one like `a == b`
+fn named(argument: &str) -> Option<(&str, &str)> {
+    let index = argument.find('=')?;
+    let (key, value) = (argument[..index].trim(), &argument[index + 1..]);
+    let is_key = !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    if is_key && !value.starts_with('=') {
+        Some((key, value.trim()))
+    } else {
+        None
+    }
+}
+
+impl<'a> Deref for Args<'a> {
+    type Target = [&'a str];
+
+    fn deref(&self) -> &[&'a str] {
+        &self.values
+    }
+}
+
+impl<'a> IntoIterator for Args<'a> {
+    type Item = &'a str;
+    type IntoIter = vec::IntoIter<&'a str>;
+
+    fn into_iter(self) -> vec::IntoIter<&'a str> {
+        self.values.into_iter()
+    }
+}
+
+impl<'a, 'b> IntoIterator for &'b Args<'a> {
+    type Item = &'b &'a str;
+    type IntoIter = slice::Iter<'b, &'a str>;
+
+    fn into_iter(self) -> slice::Iter<'b, &'a str> {
+        self.values.iter()
+    }
+}
+
+/// How many arguments a handler takes, see [`Edo::register_handler_with_arity`](../struct.Edo.html#method.register_handler_with_arity)
+///
+/// It converts from a count, such as `2`, or a range, such as `1..=3`. A range without an end, such as `1..`, marks a variadic handler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arity {
+    min: usize,
+    max: Option<usize>,
+}
+
+impl Arity {
+    /// Exactly `count` arguments
+    pub fn exactly(count: usize) -> Arity {
+        Arity { min: count, max: Some(count) }
+    }
+
+    /// `min` arguments or more
+    pub fn at_least(min: usize) -> Arity {
+        Arity { min, max: None }
+    }
+
+    /// From `min` to `max` arguments, both included
+    pub fn between(min: usize, max: usize) -> Arity {
+        Arity { min, max: Some(max) }
+    }
+
+    /// The fewest arguments allowed
+    pub fn min(&self) -> usize {
+        self.min
+    }
+
+    /// The most arguments allowed, `None` for a variadic handler
+    pub fn max(&self) -> Option<usize> {
+        self.max
+    }
+
+    /// Whether a handler of this arity takes `count` arguments
+    pub fn allows(&self, count: usize) -> bool {
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
+}
+
+impl From<usize> for Arity {
+    fn from(count: usize) -> Arity {
+        Arity::exactly(count)
+    }
+}
+
+impl From<Range<usize>> for Arity {
+    fn from(range: Range<usize>) -> Arity {
+        Arity::between(range.start, range.end.saturating_sub(1))
+    }
+}
+
+impl From<RangeInclusive<usize>> for Arity {
+    fn from(range: RangeInclusive<usize>) -> Arity {
+        Arity::between(*range.start(), *range.end())
+    }
+}
+
+impl From<RangeFrom<usize>> for Arity {
+    fn from(range: RangeFrom<usize>) -> Arity {
+        Arity::at_least(range.start)
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let plural = |count: usize| if count == 1 { "argument" } else { "arguments" };
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{} {}", max, plural(max)),
+            Some(max) => write!(f, "{} to {} {}", self.min, max, plural(max)),
+            None => write!(f, "at least {} {}", self.min, plural(self.min)),
+        }
+    }
+}
+
+/// An argument that is missing or couldn't be parsed, see [`Args::get_parsed`](struct.Args.html#method.get_parsed)
+///
+/// Indexes count from 0, like [`Args::get`](struct.Args.html#method.get), but the messages count from 1 for template authors. It converts into a `String`, so handlers can return it with `?`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgError {
+    /// There is no argument at this index
+    Missing {
+        /// The index of the argument
+        index: usize,
+    },
+    /// The argument couldn't be parsed
+    Invalid {
+        /// The index of the argument
+        index: usize,
+        /// The argument as it was written
+        value: String,
+    },
+}
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ArgError::Missing { index } => write!(f, "Argument {} is missing", index + 1),
+            ArgError::Invalid { index, ref value } => write!(f, "Argument {} isn't valid: {}", index + 1, value),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for ArgError {}
+
+impl From<ArgError> for String {
+    fn from(error: ArgError) -> String {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use super::{ArgError, Args, Arity};
+
+    #[test]
+    fn read_arguments() {
+        let args = Args::from(vec!["1", "x", " width = 10"]);
+        assert_eq!((args.len(), args.get(1), args.get(3)), (3, Some("x"), None));
+        assert_eq!(args.get_parsed::<u8>(0), Ok(1));
+        assert_eq!(args.get_parsed::<u8>(1), Err(ArgError::Invalid { index: 1, value: "x".into() }));
+        assert_eq!(args.get_parsed::<u8>(5), Err(ArgError::Missing { index: 5 }));
+        assert_eq!((args.get_named("width"), args.get_named("x")), (Some("10"), None));
+        assert_eq!((args.named(), args.positional()), (&[("width", "10")][..], vec!["1", "x"]));
+        assert!(args.has_parentheses() && !Args::from(vec![]).has_parentheses());
+        let unnamed = Args::from(vec!["a == b", "=x", "a b=c", "k=", "k=v=w"]);
+        assert_eq!(unnamed.named(), &[("k", ""), ("k", "v=w")][..]);
+        assert_eq!(args.iter().rev().cloned().collect::<Vec<_>>(), vec![" width = 10", "x", "1"]);
+        assert_eq!(args[0], "1");
+        assert_eq!((args.rest(1), args.rest(3), args.rest(9)), (&["x", " width = 10"][..], &[][..], &[][..]));
+    }
+
+    #[test]
+    fn count_arities() {
+        let (one, some, many) = (Arity::from(1), Arity::from(1..=3), Arity::from(2..));
+        assert!(one.allows(1) && !one.allows(0) && !one.allows(2));
+        assert!(some.allows(3) && !some.allows(4));
+        assert!(many.allows(20) && !many.allows(1));
+        assert_eq!(Arity::from(0..2), Arity::between(0, 1));
+        assert_eq!((one.to_string(), some.to_string(), many.to_string()), (
+            "1 argument".to_string(),
+            "1 to 3 arguments".to_string(),
+            "at least 2 arguments".to_string(),
+        ));
+    }
+}
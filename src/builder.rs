@@ -0,0 +1,203 @@
+//! Configuring a template in one expression, see [`Edo::builder`](../struct.Edo.html#method.builder)
+
+use alloc::string::{String, ToString};
+
+use builtins::Builtins;
+#[cfg(feature = "locale")]
+use catalog::Catalog;
+use context::Lookup;
+use error::EdoError;
+use escape::{Encoder, Escaping};
+use observer::Observer;
+use options::RenderOptions;
+use parse::Syntax;
+use report::Severity;
+use Edo;
+
+/// A template being configured, made by [`Edo::builder`](../struct.Edo.html#method.builder)
+///
+/// Each method does what the `Edo` method of the same name, or with `set_` or `register_` in front, does. The template is only parsed by [`build`](#method.build), with the [`delimiters`](#method.delimiters) set by then, which reports a template that doesn't parse.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::escape::Escaping;
+/// use edo::report::Severity;
+///
+/// let mut template = Edo::builder("<p>{name}{missing}</p>")
+///     .escaping(Escaping::Html)
+///     .missing_producers(Severity::Error)
+///     .build()
+///     .unwrap();
+/// template.register_static("name", "<Gio>");
+/// let (output, errors) = template.render_with_errors(&());
+/// assert_eq!(output, "<p>&lt;Gio&gt;</p>");
+/// assert_eq!(errors[0].to_string(), "Nothing produces missing");
+///
+/// assert!(Edo::<()>::builder("{name").escaping(Escaping::Html).build().is_err());
+/// ```
+pub struct EdoBuilder<'a, C: ?Sized> {
+    source: &'a str,
+    syntax: Result<Syntax, EdoError>,
+    // Configured without a template, which `build` parses
+    edo: Edo<'a, C>,
+}
+
+impl<'a, C: ?Sized> EdoBuilder<'a, C> {
+    pub(crate) fn new(template_string: &'a str) -> EdoBuilder<'a, C> {
+        EdoBuilder { source: template_string, syntax: Ok(Syntax::Brace), edo: Edo::default() }
+    }
+
+    fn with<F: FnOnce(&mut Edo<'a, C>)>(mut self, change: F) -> EdoBuilder<'a, C> {
+        change(&mut self.edo);
+        self
+    }
+
+    /// How placeholders are written, see [`Edo::new_with_syntax`](../struct.Edo.html#method.new_with_syntax)
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::parse::Syntax;
+    ///
+    /// let mut template = Edo::builder("function() { return {{ value }}; }").syntax(Syntax::DoubleBrace).build().unwrap();
+    /// template.register_static("value", "42");
+    /// assert_eq!(template.render(&()), "function() { return 42; }");
+    /// ```
+    pub fn syntax(mut self, syntax: Syntax) -> EdoBuilder<'a, C> {
+        self.syntax = Ok(syntax);
+        self
+    }
+
+    /// What placeholders start and end with, `{` and `}` or `{{` and `}}`, see [`Syntax`](../parse/enum.Syntax.html)
+    ///
+    /// Like [`syntax`](#method.syntax) with the syntax that uses them. Other delimiters make [`build`](#method.build) fail with [`EdoError::UnsupportedDelimiters`](../error/enum.EdoError.html#variant.UnsupportedDelimiters).
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::builder("{ \"name\": \"{{ name }}\" }").delimiters("{{", "}}").build().unwrap();
+    /// template.register_static("name", "Gio");
+    /// assert_eq!(template.render(&()), "{ \"name\": \"Gio\" }");
+    ///
+    /// assert_eq!(Edo::<()>::builder("<% name %>").delimiters("<%", "%>").build().err().map(|error| error.code()), Some("E0010"));
+    /// ```
+    pub fn delimiters(mut self, open: &str, close: &str) -> EdoBuilder<'a, C> {
+        self.syntax = match (open, close) {
+            ("{", "}") => Ok(Syntax::Brace),
+            ("{{", "}}") => Ok(Syntax::DoubleBrace),
+            _ => Err(EdoError::UnsupportedDelimiters { open: open.to_string(), close: close.to_string() }),
+        };
+        self
+    }
+
+    /// Escape the output of every placeholder, see [`Edo::set_escaping`](../struct.Edo.html#method.set_escaping)
+    pub fn escaping(self, escaping: Escaping) -> EdoBuilder<'a, C> {
+        self.with(|edo| edo.set_escaping(escaping))
+    }
+
+    /// Encode the output of every placeholder, see [`Edo::set_encoder`](../struct.Edo.html#method.set_encoder)
+    pub fn encoder<E: 'a + Encoder>(self, encoder: E) -> EdoBuilder<'a, C> {
+        self.with(|edo| edo.set_encoder(encoder))
+    }
+
+    /// Set the options used by every render, see [`Edo::set_options`](../struct.Edo.html#method.set_options)
+    ///
+    /// This replaces the options set before, including [`missing_producers`](#method.missing_producers).
+    pub fn options(self, options: RenderOptions) -> EdoBuilder<'a, C> {
+        self.with(|edo| edo.set_options(options))
+    }
+
+    /// How placeholders that nothing resolves are reported, see [`RenderOptions::missing_producers`](../options/struct.RenderOptions.html#method.missing_producers)
+    pub fn missing_producers(self, severity: Severity) -> EdoBuilder<'a, C> {
+        self.with(|edo| edo.options = core::mem::take(&mut edo.options).missing_producers(severity))
+    }
+
+    /// Register a fallback, see [`Edo::register_fallback`](../struct.Edo.html#method.register_fallback)
+    pub fn fallback<F>(self, fallback: F) -> EdoBuilder<'a, C> where
+        F: 'a + Fn(&str, &C) -> Option<String> {
+        self.with(|edo| edo.register_fallback(fallback))
+    }
+
+    /// Look placeholders up in the render context, see [`Edo::use_context_lookup`](../struct.Edo.html#method.use_context_lookup)
+    pub fn context_lookup(self) -> EdoBuilder<'a, C> where C: 'a + Lookup {
+        self.with(|edo| edo.use_context_lookup())
+    }
+
+    /// Register ready made handlers and filters, see [`Edo::register_builtins`](../struct.Edo.html#method.register_builtins)
+    pub fn builtins(self, builtins: Builtins) -> EdoBuilder<'a, C> {
+        self.with(|edo| edo.register_builtins(builtins))
+    }
+
+    /// Translate literals with a catalog, see [`Edo::set_catalog`](../struct.Edo.html#method.set_catalog)
+    #[cfg(feature = "locale")]
+    pub fn catalog<T: 'a + Catalog>(self, catalog: T) -> EdoBuilder<'a, C> {
+        self.with(|edo| edo.set_catalog(catalog))
+    }
+
+    /// Register an observer, see [`Edo::register_observer`](../struct.Edo.html#method.register_observer)
+    pub fn observer<O: 'a + Observer>(self, observer: O) -> EdoBuilder<'a, C> {
+        self.with(|edo| edo.register_observer(observer))
+    }
+
+    /// Parse the template and return it configured, or the error parsing it
+    pub fn build(self) -> Result<Edo<'a, C>, EdoError> {
+        let mut edo = self.edo;
+        edo.syntax = self.syntax?;
+        edo.set_template(self.source)?;
+        Ok(edo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use builtins;
+    use error::EdoError;
+    use escape::Escaping;
+    use options::RenderOptions;
+    use parse::{Span, Syntax};
+    use report::Severity;
+    use Edo;
+
+    #[test]
+    fn build_template() {
+        let mut edo = Edo::builder("{b} {a|upper} {truncate(2, abc)}")
+            .missing_producers(Severity::Error)
+            .options(RenderOptions::new().max_output(10))
+            .context_lookup()
+            .fallback(|name, _: &Vec<(&str, &str)>| if name == "b" { Some("fallback".to_string()) } else { None })
+            .builtins(builtins::strings())
+            .build()
+            .unwrap();
+        assert_eq!(edo.render(&vec![("a", "x")]), "");
+        edo.set_options(RenderOptions::new());
+        assert_eq!(edo.render(&vec![("a", "x")]), "fallback X ab");
+
+        let broken = Edo::<()>::builder("{a").missing_producers(Severity::Error).build();
+        assert_eq!(broken.err(), Some(EdoError::UnterminatedPlaceholder { span: Span { start: 0, end: 2 } }));
+    }
+
+    #[test]
+    fn parse_with_delimiters_when_built() {
+        // Parsed with single braces, `{{ url }}` wouldn't be a placeholder
+        let mut edo = Edo::builder("<a href=\"{{ url }}\">{ {{ url }} }</a>")
+            .escaping(Escaping::ContextualHtml)
+            .delimiters("{{", "}}")
+            .build()
+            .unwrap();
+        edo.register_static("url", "a b");
+        assert_eq!(edo.render(&()), "<a href=\"a&#x20;b\">{ a b }</a>");
+        edo.set_template("{{ url }}{url}").unwrap();
+        assert_eq!(edo.render(&()), "a b{url}");
+
+        let single = Edo::<()>::builder("{{").delimiters("{{", "}}").delimiters("{", "}").build();
+        assert_eq!(single.err().map(|error| error.code()), Some("E0001"));
+        let double = Edo::<()>::builder("{{ a }}").delimiters("[[", "]]").syntax(Syntax::DoubleBrace).build().unwrap();
+        assert_eq!(double.signature().placeholders().keys().collect::<Vec<_>>(), vec!["a"]);
+        let unsupported = Edo::<()>::builder("[[ a ]]").delimiters("[[", "]]").build();
+        assert_eq!(unsupported.err(), Some(EdoError::UnsupportedDelimiters { open: "[[".to_string(), close: "]]".to_string() }));
+    }
+}
@@ -0,0 +1,268 @@
+//! Arithmetic
+
+use alloc::string::{String, ToString};
+use core::cell::Cell;
+
+use args::Args;
+use context::Lookup;
+use parse::MAX_NESTING;
+use super::Builtins;
+
+/// `{calc(expression)}`, the value of an arithmetic expression
+///
+/// Expressions have numbers, `+`, `-`, `*`, `/`, `%` and parentheses, with the usual precedence. Whole results are shown without a decimal point. Builtins don't see the render context, so names aren't looked up here, register [`calc_in_context`](fn.calc_in_context.html) for that.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::builtins;
+///
+/// let mut template = Edo::new("{calc(2 * (3 + 4))} {calc(7 / 2)}").unwrap();
+/// template.register_builtins(builtins::calc());
+/// assert_eq!(template.render(""), "14 3.5");
+/// ```
+pub fn calc() -> Builtins {
+    Builtins::new().handler("calc", |args| match args.as_slice() {
+        [expression] => evaluate(expression, |_| None).map(format_number),
+        _ => Err("calc takes one argument, the expression".to_string()),
+    })
+}
+
+/// `{calc(expression)}` as a handler that looks names up in the render context, see [`Lookup`](../context/trait.Lookup.html)
+///
+/// Register it with [`Edo::register_handler`](../struct.Edo.html#method.register_handler). Names that aren't in the context or aren't numbers are errors.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::builtins;
+///
+/// let mut template = Edo::new("{calc(2 * (3 + x))} {calc(y)}").unwrap();
+/// template.register_handler("calc", builtins::calc_in_context);
+/// assert_eq!(template.render(&vec![("x", "4")]), "14 ");
+/// assert_eq!(template.render_with_errors(&vec![("x", "4")]).1[0].to_string(), "Unknown name y");
+/// ```
+pub fn calc_in_context<C: Lookup + ?Sized>(args: Args, context: &C) -> Result<String, String> {
+    let expression = match args.as_slice() {
+        [expression] => expression,
+        _ => return Err("calc takes one argument, the expression".to_string()),
+    };
+    // `evaluate` only hears that a name is missing, so keep why a value wasn't a number
+    let invalid = Cell::new(None);
+    let value = evaluate(expression, |name| {
+        let value = context.get(name)?;
+        let number = value.trim().parse().ok();
+        if number.is_none() {
+            invalid.set(Some(format!("{} is not a number: {}", name, value)));
+        }
+        number
+    });
+    match (value, invalid.take()) {
+        (Err(_), Some(message)) => Err(message),
+        (value, _) => value.map(format_number),
+    }
+}
+
+/// Evaluate an arithmetic expression like [`calc`](fn.calc.html) does, looking up names with `lookup`
+///
+/// Names start with a letter or `_`, and can contain letters, digits, `_` and `.`. Returns an error message for invalid expressions, unknown names, division by zero and parentheses or signs nested more than [`MAX_NESTING`](../parse/constant.MAX_NESTING.html) deep.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::builtins::{evaluate, format_number};
+///
+/// struct Row { width: f64 }
+///
+/// let mut template = Edo::new("{calc(width + 2)}").unwrap();
+/// template.register_handler("calc", |args, row: &Row| {
+///     evaluate(args[0], |name| if name == "width" { Some(row.width) } else { None }).map(format_number)
+/// });
+/// assert_eq!(template.render(&Row { width: 10.0 }), "12");
+/// ```
+pub fn evaluate<F: Fn(&str) -> Option<f64>>(expression: &str, lookup: F) -> Result<f64, String> {
+    let mut parser = Parser { input: expression, position: 0, depth: 0, lookup };
+    let value = parser.sum()?;
+    parser.skip_spaces();
+    if parser.position != expression.len() {
+        return Err(format!("Unexpected {} in {}", &expression[parser.position..], expression));
+    }
+    if !value.is_finite() {
+        return Err(format!("{} is not a finite number", expression));
+    }
+    Ok(value)
+}
+
+/// Show a number the way [`calc`](fn.calc.html) does, whole numbers without a decimal point
+pub fn format_number(value: f64) -> String {
+    if value == (value as i64) as f64 {
+        (value as i64).to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+// A recursive descent parser that evaluates as it goes
+struct Parser<'a, F> {
+    input: &'a str,
+    position: usize,
+    // How many parentheses and signs `unary` is inside of
+    depth: usize,
+    lookup: F,
+}
+
+impl<'a, F: Fn(&str) -> Option<f64>> Parser<'a, F> {
+    fn skip_spaces(&mut self) {
+        let rest = &self.input[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    // Take the next character if it's one of `operators`
+    fn operator(&mut self, operators: &str) -> Option<char> {
+        self.skip_spaces();
+        let next = self.input[self.position..].chars().next().filter(|next| operators.contains(*next));
+        if next.is_some() {
+            self.position += 1;
+        }
+        next
+    }
+
+    // `product (+|- product)*`
+    fn sum(&mut self) -> Result<f64, String> {
+        let mut value = self.product()?;
+        while let Some(operator) = self.operator("+-") {
+            let right = self.product()?;
+            value = if operator == '+' { value + right } else { value - right };
+        }
+        Ok(value)
+    }
+
+    // `unary (*|/|% unary)*`
+    fn product(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+        while let Some(operator) = self.operator("*/%") {
+            let right = self.unary()?;
+            if operator != '*' && right == 0.0 {
+                return Err("Division by zero".to_string());
+            }
+            value = match operator {
+                '*' => value * right,
+                '/' => value / right,
+                _ => value % right,
+            };
+        }
+        Ok(value)
+    }
+
+    // `-unary`, `(sum)`, a number or a name
+    fn unary(&mut self) -> Result<f64, String> {
+        if self.depth > MAX_NESTING {
+            return Err(format!("{} is nested too deep", self.input));
+        }
+        if self.operator("-").is_some() {
+            self.depth += 1;
+            let value = self.unary();
+            self.depth -= 1;
+            return value.map(|value| -value);
+        }
+        if self.operator("(").is_some() {
+            self.depth += 1;
+            let value = self.sum();
+            self.depth -= 1;
+            let value = value?;
+            return match self.operator(")") {
+                Some(_) => Ok(value),
+                None => Err(format!("Missing ) in {}", self.input)),
+            };
+        }
+
+        let rest = &self.input[self.position..];
+        let length = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.')).unwrap_or(rest.len());
+        let token = &rest[..length];
+        self.position += length;
+        match token.chars().next() {
+            None => Err(format!("Expected a number in {}", self.input)),
+            Some(first) if first.is_ascii_digit() || first == '.' => {
+                token.parse().map_err(|_| format!("{} is not a number", token))
+            },
+            Some(_) => (self.lookup)(token).ok_or_else(|| format!("Unknown name {}", token)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use parse::MAX_NESTING;
+    use Edo;
+    use super::{calc_in_context, evaluate};
+
+    fn lookup(name: &str) -> Option<f64> {
+        match name {
+            "x" => Some(4.0),
+            "row.total" => Some(1.5),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn evaluate_with_precedence() {
+        assert_eq!(evaluate("2 * (3 + x)", lookup), Ok(14.0));
+        assert_eq!(evaluate("2 + 3 * 4", lookup), Ok(14.0));
+        assert_eq!(evaluate("1 - 2 - 3", lookup), Ok(-4.0));
+        assert_eq!(evaluate("12 / 3 / 2", lookup), Ok(2.0));
+        assert_eq!(evaluate("7 % 4 * 2", lookup), Ok(6.0));
+        assert_eq!(evaluate("-(1 + 1) * -row.total % 2", lookup), Ok(1.0));
+        assert_eq!(evaluate("--x", lookup), Ok(4.0));
+        assert_eq!(evaluate(" 10 / 4 ", lookup), Ok(2.5));
+    }
+
+    #[test]
+    fn reject_division_by_zero() {
+        assert_eq!(evaluate("1 / (x - 4)", lookup), Err("Division by zero".to_string()));
+        assert_eq!(evaluate("1 % 0", lookup), Err("Division by zero".to_string()));
+        assert_eq!(evaluate("0 * 0", lookup), Ok(0.0));
+    }
+
+    #[test]
+    fn reject_unknown_names() {
+        assert_eq!(evaluate("y + 1", lookup), Err("Unknown name y".to_string()));
+        assert_eq!(evaluate("row.count", lookup), Err("Unknown name row.count".to_string()));
+    }
+
+    #[test]
+    fn reject_malformed_expressions() {
+        assert_eq!(evaluate("(1 + 2", lookup), Err("Missing ) in (1 + 2".to_string()));
+        assert_eq!(evaluate("1 +", lookup), Err("Expected a number in 1 +".to_string()));
+        assert_eq!(evaluate("", lookup), Err("Expected a number in ".to_string()));
+        assert_eq!(evaluate("1 2", lookup), Err("Unexpected 2 in 1 2".to_string()));
+        assert_eq!(evaluate("1 + 2)", lookup), Err("Unexpected ) in 1 + 2)".to_string()));
+        assert_eq!(evaluate("1.2.3", lookup), Err("1.2.3 is not a number".to_string()));
+        assert_eq!(evaluate("1e400", lookup), Err("1e400 is not a finite number".to_string()));
+    }
+
+    #[test]
+    fn reject_deep_nesting() {
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(evaluate(&nested(MAX_NESTING), lookup), Ok(1.0));
+        assert!(evaluate(&nested(MAX_NESTING + 1), lookup).unwrap_err().ends_with("is nested too deep"));
+        assert!(evaluate(&nested(100_000), lookup).unwrap_err().ends_with("is nested too deep"));
+        assert!(evaluate(&format!("{}1", "-".repeat(100_000)), lookup).unwrap_err().ends_with("is nested too deep"));
+    }
+
+    #[test]
+    fn calc_with_context() {
+        let mut template = Edo::new("{calc(width * 2 + 1)}|{calc(width + name)}|{calc(width + height)}|{calc(1, 2)}").unwrap();
+        template.register_handler("calc", calc_in_context);
+        let (output, errors) = template.render_with_errors(&vec![("width", " 10 "), ("name", "gio")]);
+        assert_eq!(output, "21|||");
+        let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(messages, vec![
+            "name is not a number: gio",
+            "Unknown name height",
+            "calc takes one argument, the expression",
+        ]);
+    }
+}
@@ -0,0 +1,132 @@
+//! Counting
+
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use core::cell::RefCell;
+
+use observer::Observer;
+use super::Builtins;
+
+/// `{counter(name)}`, which counts up from 1 each time it is rendered and starts again every render
+///
+/// Each name counts separately, `{counter}` is a counter without a name. Use [`Counters`](struct.Counters.html) to keep counting across renders or to read the counts.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::builtins;
+///
+/// let mut template = Edo::new("{counter(step)}. Mix {counter(step)}. Bake").unwrap();
+/// template.register_builtins(builtins::counter());
+/// assert_eq!(template.render(""), "1. Mix 2. Bake");
+/// assert_eq!(template.render(""), "1. Mix 2. Bake");
+/// ```
+pub fn counter() -> Builtins {
+    Counters::new().per_render()
+}
+
+/// Counts shared between a template and your code
+///
+/// Clones share the same counts.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::builtins::Counters;
+///
+/// let counters = Counters::new();
+/// let mut template = Edo::new("Figure {counter(figure)}").unwrap();
+/// template.register_builtins(counters.builtins());
+/// assert_eq!(template.render(""), "Figure 1");
+/// assert_eq!(template.render(""), "Figure 2");
+/// assert_eq!(counters.get("figure"), 2);
+///
+/// counters.reset();
+/// assert_eq!(template.render(""), "Figure 1");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Counters {
+    counts: Rc<RefCell<BTreeMap<String, u64>>>,
+}
+
+impl Counters {
+    /// Counters that all start at 0
+    pub fn new() -> Counters {
+        Counters::default()
+    }
+
+    /// The `counter` handler, counting across every render until it is reset
+    pub fn builtins(&self) -> Builtins {
+        let counters = self.clone();
+        Builtins::new().handler("counter", move |args| {
+            let name = match args.as_slice() {
+                [] => "",
+                [name] => *name,
+                _ => return Err("counter takes one argument, the name of the counter".to_string()),
+            };
+            let mut counts = counters.counts.borrow_mut();
+            let count = counts.entry(name.to_string()).or_insert(0);
+            *count += 1;
+            Ok(count.to_string())
+        })
+    }
+
+    /// The `counter` handler, with every count reset when a render starts
+    ///
+    /// The counts of the last render can still be read afterwards.
+    pub fn per_render(&self) -> Builtins {
+        self.builtins().observer(self.clone())
+    }
+
+    /// How many times a counter has been rendered since it was last reset
+    pub fn get(&self, name: &str) -> u64 {
+        self.counts.borrow().get(name).cloned().unwrap_or(0)
+    }
+
+    /// Set a counter back to 0
+    pub fn reset_counter(&self, name: &str) {
+        self.counts.borrow_mut().remove(name);
+    }
+
+    /// Set every counter back to 0
+    pub fn reset(&self) {
+        self.counts.borrow_mut().clear();
+    }
+}
+
+// Resets the counts for `per_render`
+impl Observer for Counters {
+    fn on_render_start(&self) {
+        self.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use Edo;
+    use super::Counters;
+
+    #[test]
+    fn count_per_render_and_across_renders() {
+        let counters = Counters::new();
+        let mut edo: Edo<()> = match Edo::new("{counter(a)}{counter}{counter(a)}{counter(a, b)}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_builtins(counters.per_render());
+        assert_eq!(edo.render(&()), "112");
+        assert_eq!(edo.render(&()), "112");
+        assert_eq!((counters.get("a"), counters.get(""), counters.get("b")), (2, 1, 0));
+
+        let mut edo: Edo<()> = match Edo::new("{counter(a)}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_builtins(counters.builtins());
+        edo.render(&());
+        assert_eq!(counters.get("a"), 3);
+        counters.reset_counter("a");
+        assert_eq!(edo.render(&()), "1");
+    }
+}
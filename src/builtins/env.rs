@@ -0,0 +1,68 @@
+//! Environment variables
+
+use std::collections::BTreeSet;
+use std::env as std_env;
+use std::string::{String, ToString};
+
+use super::Builtins;
+
+/// `{env(NAME)}`, the value of an environment variable
+///
+/// Only the variables in `allowed` can be read, so a template can't leak secrets like `AWS_SECRET_ACCESS_KEY` unless you list them. Reading any other variable, or one that isn't set, is a handler error.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::builtins;
+///
+/// std::env::set_var("EDO_DOC_HOME", "/home/gio");
+/// let mut template = Edo::new("{env(EDO_DOC_HOME)} {env(EDO_DOC_SECRET)}").unwrap();
+/// template.register_builtins(builtins::env(vec!["EDO_DOC_HOME"]));
+/// let (output, errors) = template.render_with_errors("");
+/// assert_eq!(output, "/home/gio ");
+/// assert_eq!(errors[0].to_string(), "EDO_DOC_SECRET is not an allowed environment variable");
+/// ```
+pub fn env<I, S>(allowed: I) -> Builtins where
+    I: IntoIterator<Item = S>,
+    S: Into<String> {
+    let allowed: BTreeSet<String> = allowed.into_iter().map(Into::into).collect();
+    Builtins::new().handler("env", move |args| {
+        let name = match args.as_slice() {
+            [name] => *name,
+            _ => return Err("env takes one argument, the name of the variable".to_string()),
+        };
+        if !allowed.contains(name) {
+            return Err(format!("{} is not an allowed environment variable", name));
+        }
+        std_env::var(name).map_err(|_| format!("Environment variable {} is not set", name))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::string::ToString;
+    use std::vec::Vec;
+
+    use Edo;
+    use super::env as env_builtin;
+
+    #[test]
+    fn read_allowed_variables() {
+        env::set_var("EDO_TEST_ALLOWED", "yes");
+        env::remove_var("EDO_TEST_UNSET");
+        let mut edo: Edo<()> = match Edo::new("{env(EDO_TEST_ALLOWED)}|{env(EDO_TEST_UNSET)}|{env(PATH)}|{env}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_builtins(env_builtin(vec!["EDO_TEST_ALLOWED", "EDO_TEST_UNSET"]));
+        let (output, errors) = edo.render_with_errors(&());
+        assert_eq!(output, "yes|||");
+        let messages: Vec<_> = errors.iter().map(|error| error.to_string()).collect();
+        assert_eq!(messages, vec![
+            "Environment variable EDO_TEST_UNSET is not set",
+            "PATH is not an allowed environment variable",
+            "env takes one argument, the name of the variable",
+        ]);
+    }
+}
@@ -0,0 +1,137 @@
+//! The data builtins format numbers, plurals and dates with
+
+use alloc::string::{String, ToString};
+
+use super::{numbers, plural};
+
+// Month and weekday names, weekdays start on Sunday
+type Names = ([&'static str; 12], [&'static str; 7]);
+
+const ENGLISH: Names = (
+    ["January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"],
+    ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"],
+);
+const GERMAN: Names = (
+    ["Januar", "Februar", "März", "April", "Mai", "Juni", "Juli", "August", "September", "Oktober", "November", "Dezember"],
+    ["Sonntag", "Montag", "Dienstag", "Mittwoch", "Donnerstag", "Freitag", "Samstag"],
+);
+const FRENCH: Names = (
+    ["janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août", "septembre", "octobre", "novembre", "décembre"],
+    ["dimanche", "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi"],
+);
+const SPANISH: Names = (
+    ["enero", "febrero", "marzo", "abril", "mayo", "junio", "julio", "agosto", "septiembre", "octubre", "noviembre", "diciembre"],
+    ["domingo", "lunes", "martes", "miércoles", "jueves", "viernes", "sábado"],
+);
+const ITALIAN: Names = (
+    ["gennaio", "febbraio", "marzo", "aprile", "maggio", "giugno", "luglio", "agosto", "settembre", "ottobre", "novembre", "dicembre"],
+    ["domenica", "lunedì", "martedì", "mercoledì", "giovedì", "venerdì", "sabato"],
+);
+const DUTCH: Names = (
+    ["januari", "februari", "maart", "april", "mei", "juni", "juli", "augustus", "september", "oktober", "november", "december"],
+    ["zondag", "maandag", "dinsdag", "woensdag", "donderdag", "vrijdag", "zaterdag"],
+);
+const PORTUGUESE: Names = (
+    ["janeiro", "fevereiro", "março", "abril", "maio", "junho", "julho", "agosto", "setembro", "outubro", "novembro", "dezembro"],
+    ["domingo", "segunda-feira", "terça-feira", "quarta-feira", "quinta-feira", "sexta-feira", "sábado"],
+);
+
+// The names for a locale, if there are any
+fn names(locale: &str) -> Option<&'static Names> {
+    let language = locale.split(['-', '_']).next().unwrap_or("").to_ascii_lowercase();
+    Some(match language.as_str() {
+        "en" => &ENGLISH,
+        "de" => &GERMAN,
+        "fr" => &FRENCH,
+        "es" => &SPANISH,
+        "it" => &ITALIAN,
+        "nl" => &DUTCH,
+        "pt" => &PORTUGUESE,
+        _ => return None,
+    })
+}
+
+// The first three characters of a name
+fn abbreviate(name: &str) -> String {
+    name.chars().take(3).collect()
+}
+
+/// Number separators, plural rules and month and weekday names, for each locale
+///
+/// The [`numbers`](fn.numbers_with_data.html), [`plural`](fn.plural_with_data.html) and [`time`](fn.time_with_data.html) builtins can be made with data of your own, such as data from CLDR, without edo depending on it. Every method has a default that uses the small tables built into edo, listed with each builtin, so an implementation only overrides what it knows better. A method returns `None` for a locale it has no data for.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::builtins::{self, LocaleData};
+///
+/// struct Swiss;
+///
+/// impl LocaleData for Swiss {
+///     fn separators(&self, locale: &str) -> Option<(char, char)> {
+///         match locale {
+///             "fr-CH" => Some(('\u{202f}', ',')),
+///             _ => builtins::DefaultLocaleData.separators(locale),
+///         }
+///     }
+/// }
+///
+/// let mut template = Edo::new("{num(1234.5, 1, fr-CH)} {num(1234.5, 1, de-CH)}").unwrap();
+/// template.register_builtins(builtins::numbers_with_data(Swiss));
+/// assert_eq!(template.render(&()), "1\u{202f}234,5 1’234.5");
+/// ```
+pub trait LocaleData {
+    /// The thousands and decimal separators
+    fn separators(&self, locale: &str) -> Option<(char, char)> {
+        numbers::separators(locale)
+    }
+
+    /// The CLDR plural category of a whole number, one of `zero`, `one`, `two`, `few`, `many` and `other`
+    fn plural_category(&self, locale: &str, number: i64) -> Option<&'static str> {
+        plural::category(locale, number)
+    }
+
+    /// The name of a month, from 1 for January to 12
+    fn month_name(&self, locale: &str, month: u32) -> Option<String> {
+        let index = (month as usize).checked_sub(1)?;
+        names(locale).and_then(|(months, _)| months.get(index)).map(|name| name.to_string())
+    }
+
+    /// The name of a weekday, from 0 for Sunday to 6
+    fn weekday_name(&self, locale: &str, weekday: u32) -> Option<String> {
+        names(locale).and_then(|(_, weekdays)| weekdays.get(weekday as usize)).map(|name| name.to_string())
+    }
+
+    /// The short name of a month, the first three letters of its name unless this is overridden
+    fn short_month_name(&self, locale: &str, month: u32) -> Option<String> {
+        self.month_name(locale, month).map(|name| abbreviate(&name))
+    }
+
+    /// The short name of a weekday, the first three letters of its name unless this is overridden
+    fn short_weekday_name(&self, locale: &str, weekday: u32) -> Option<String> {
+        self.weekday_name(locale, weekday).map(|name| abbreviate(&name))
+    }
+}
+
+/// The tables built into edo, which the builtins use unless they are made with other data
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultLocaleData;
+
+impl LocaleData for DefaultLocaleData {}
+
+#[cfg(test)]
+mod tests {
+    use super::{DefaultLocaleData, LocaleData};
+
+    #[test]
+    fn look_up_default_data() {
+        let data = DefaultLocaleData;
+        assert_eq!(data.month_name("pt-BR", 3).as_deref(), Some("março"));
+        assert_eq!(data.short_weekday_name("de", 5).as_deref(), Some("Fre"));
+        assert_eq!(data.month_name("en", 0), None);
+        assert_eq!(data.month_name("en", 13), None);
+        assert_eq!(data.weekday_name("xx", 1), None);
+        assert_eq!(data.separators("de"), Some(('.', ',')));
+        assert_eq!(data.plural_category("ru", 3), Some("few"));
+    }
+}
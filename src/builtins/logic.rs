@@ -0,0 +1,58 @@
+//! Comparisons and defaults
+
+use alloc::string::ToString;
+
+use super::Builtins;
+
+/// `{eq(a, b, then, else)}`, `{default(value, fallback)}` and `{coalesce(a, b, c)}`
+///
+/// - `{eq(a, b, then, else)}` is `then` if `a` and `b` are the same text, and `else` otherwise. `else` can be left out, for nothing.
+/// - `{default(value, fallback)}` is `value`, or `fallback` if `value` is empty or only spaces
+/// - `{coalesce(a, b, c)}` is the first argument that isn't empty or only spaces, or nothing
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::builtins;
+///
+/// let mut template = Edo::new("{eq(prod, prod, live, test)} {eq(prod, dev, live, test)} {coalesce(a, b)}").unwrap();
+/// template.register_builtins(builtins::logic());
+/// assert_eq!(template.render(""), "live test a");
+/// ```
+pub fn logic() -> Builtins {
+    Builtins::new()
+        .handler("eq", |args| match args.as_slice() {
+            [a, b, then] => Ok(if a == b { then.to_string() } else { "".to_string() }),
+            [a, b, then, otherwise] => Ok(if a == b { *then } else { *otherwise }.to_string()),
+            _ => Err("eq takes two values to compare, the text if they are equal and the text if they aren't".to_string()),
+        })
+        .handler("default", |args| match args.as_slice() {
+            [value, fallback] => Ok(if is_blank(value) { *fallback } else { *value }.to_string()),
+            _ => Err("default takes a value and its fallback".to_string()),
+        })
+        .handler("coalesce", |args| Ok(args.into_iter().find(|value| !is_blank(value)).unwrap_or("").to_string()))
+}
+
+fn is_blank(value: &str) -> bool {
+    value.trim().is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::logic;
+
+    #[test]
+    fn compare_and_default() {
+        let builtins = logic();
+        let call = |name: &str, args| (builtins.handlers[name])(args, None);
+        assert_eq!(call("eq", vec!["a", "a", "yes"]), Ok("yes".to_string()));
+        assert_eq!(call("eq", vec!["a", "A", "yes"]), Ok("".to_string()));
+        assert!(call("eq", vec!["a", "a"]).is_err());
+        assert_eq!(call("default", vec!["value", "fallback"]), Ok("value".to_string()));
+        assert_eq!(call("default", vec![" ", "fallback"]), Ok("fallback".to_string()));
+        assert_eq!(call("coalesce", vec!["", " ", "c", "d"]), Ok("c".to_string()));
+        assert_eq!(call("coalesce", vec![]), Ok("".to_string()));
+    }
+}
@@ -0,0 +1,150 @@
+//! Ready made handlers and filters
+//!
+//! Each function in this module returns a set of [`Builtins`](struct.Builtins.html), which are registered on a template with [`Edo::register_builtins`](../struct.Edo.html#method.register_builtins). Nothing is registered unless you ask for it.
+//!
+//! - [`calc`](fn.calc.html): arithmetic, with [`calc_in_context`](fn.calc_in_context.html) and [`evaluate`](fn.evaluate.html) for expressions that use the render context
+//! - [`counter`](fn.counter.html): numbering, with [`Counters`](struct.Counters.html) to read and reset the counts
+//! - [`env`](fn.env.html): environment variables, behind the `std` feature
+//! - [`logic`](fn.logic.html): `eq`, `default` and `coalesce`
+//! - [`numbers`](fn.numbers.html): numbers with thousands separators
+//! - [`plural`](fn.plural.html): singular or plural words, with CLDR rules for other languages, and forms selected by a value
+//! - [`random`](fn.random.html): `uuid`, `rand_int` and `rand_choice`, behind the `random` feature
+//! - [`strings`](fn.strings.html): `upper`, `lower`, `trim`, `truncate`, `pad_left` and `replace`
+//! - [`time`](fn.time.html): the current time and durations, behind the `time` feature
+//!
+//! The number, plural and time builtins format for a locale with the tables built into edo, or with [`LocaleData`](trait.LocaleData.html) of your own.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use observer::Observer;
+
+mod calc;
+mod counter;
+#[cfg(feature = "std")]
+mod env;
+mod locale_data;
+mod logic;
+mod numbers;
+mod plural;
+#[cfg(feature = "random")]
+mod random;
+mod strings;
+#[cfg(feature = "time")]
+mod time;
+
+pub use self::calc::{calc, calc_in_context, evaluate, format_number};
+pub use self::counter::{counter, Counters};
+#[cfg(feature = "std")]
+pub use self::env::env;
+pub use self::locale_data::{DefaultLocaleData, LocaleData};
+pub use self::logic::logic;
+pub use self::numbers::{numbers, numbers_with_data};
+pub use self::plural::{plural, plural_with_data};
+#[cfg(feature = "random")]
+pub use self::random::{random, random_seeded, random_with};
+pub use self::strings::strings;
+#[cfg(feature = "time")]
+pub use self::time::{time, time_with, time_with_data};
+
+// Builtin handlers also get the locale of the render, if it has one
+pub(crate) type BuiltinHandler = Box<dyn Fn(Vec<&str>, Option<&str>) -> Result<String, String>>;
+pub(crate) type BuiltinFilter = Box<dyn Fn(&str) -> String>;
+
+/// A set of handlers and filters that don't need the render context
+///
+/// A set can also bring observers along, for builtins that need to know when a render starts.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::builtins::Builtins;
+///
+/// let shout = Builtins::new()
+///     .handler("shout", |args| Ok(args.join(" ").to_uppercase()))
+///     .filter("bang", |value| format!("{}!", value));
+/// let mut template = Edo::new("{shout(hi, there)|bang}").unwrap();
+/// template.register_builtins(shout);
+/// assert_eq!(template.render(""), "HI THERE!");
+/// ```
+#[derive(Default)]
+pub struct Builtins {
+    pub(crate) handlers: BTreeMap<String, BuiltinHandler>,
+    pub(crate) filters: BTreeMap<String, BuiltinFilter>,
+    pub(crate) observers: Vec<Box<dyn Observer>>,
+}
+
+impl Builtins {
+    /// An empty set
+    pub fn new() -> Builtins {
+        Builtins::default()
+    }
+
+    /// Add a handler, which gets the arguments of the placeholder
+    pub fn handler<F>(self, name: &str, handler: F) -> Builtins where
+        F: 'static + Fn(Vec<&str>) -> Result<String, String> {
+        self.with_locale(name, move |args, _| handler(args))
+    }
+
+    /// Add a handler, which gets the arguments of the placeholder and the locale of the render
+    ///
+    /// Needs the `locale` feature. The locale is set with [`RenderOptions::locale`](../options/struct.RenderOptions.html#method.locale) or [`Edo::render_in`](../struct.Edo.html#method.render_in).
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::builtins::Builtins;
+    ///
+    /// let hello = Builtins::new().localized_handler("hello", |_, locale| Ok(match locale {
+    ///     Some("fr") => "Bonjour".to_string(),
+    ///     _ => "Hello".to_string(),
+    /// }));
+    /// let mut template = Edo::new("{hello}").unwrap();
+    /// template.register_builtins(hello);
+    /// assert_eq!(template.render_in("fr", ""), "Bonjour");
+    /// ```
+    #[cfg(feature = "locale")]
+    pub fn localized_handler<F>(self, name: &str, handler: F) -> Builtins where
+        F: 'static + Fn(Vec<&str>, Option<&str>) -> Result<String, String> {
+        self.with_locale(name, handler)
+    }
+
+    pub(crate) fn with_locale<F>(mut self, name: &str, handler: F) -> Builtins where
+        F: 'static + Fn(Vec<&str>, Option<&str>) -> Result<String, String> {
+        self.handlers.insert(name.to_string(), Box::new(handler));
+        self
+    }
+
+    /// Add a filter
+    pub fn filter<F>(mut self, name: &str, filter: F) -> Builtins where
+        F: 'static + Fn(&str) -> String {
+        self.filters.insert(name.to_string(), Box::new(filter));
+        self
+    }
+
+    /// Add an observer, registered along with the handlers and filters
+    pub fn observer<O: 'static + Observer>(mut self, observer: O) -> Builtins {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Add everything from another set, replacing handlers and filters with the same name
+    pub fn merge(mut self, other: Builtins) -> Builtins {
+        self.handlers.extend(other.handlers);
+        self.filters.extend(other.filters);
+        self.observers.extend(other.observers);
+        self
+    }
+
+    /// The names of the handlers in this set
+    pub fn handler_names(&self) -> Vec<&str> {
+        self.handlers.keys().map(String::as_str).collect()
+    }
+
+    /// The names of the filters in this set
+    pub fn filter_names(&self) -> Vec<&str> {
+        self.filters.keys().map(String::as_str).collect()
+    }
+}
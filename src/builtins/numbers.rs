@@ -0,0 +1,110 @@
+//! Number formatting
+
+use alloc::string::{String, ToString};
+
+use super::{Builtins, DefaultLocaleData, LocaleData};
+
+/// `{num(number, precision, locale)}`, a number with thousands separators
+///
+/// `precision` is the number of decimal places, without it or when it's empty the number keeps the decimals it has. `locale` picks the separators, it is the locale of the render with the `locale` feature, or `en`:
+///
+/// | Locale | Example |
+/// |---|---|
+/// | `en`, `ja`, `ko`, `zh` | `1,234,567.89` |
+/// | `de`, `es`, `id`, `it`, `nl`, `pt`, `tr` | `1.234.567,89` |
+/// | `fr`, `cs`, `pl`, `ru`, `sv`, `uk` | `1 234 567,89`, with a narrow no-break space |
+/// | `de-CH` | `1’234’567.89` |
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::builtins;
+///
+/// let mut template = Edo::new("{num(1234567.891, 2)} {num(-1234.6, 0, de)} {num(0.125)}").unwrap();
+/// template.register_builtins(builtins::numbers());
+/// assert_eq!(template.render(""), "1,234,567.89 -1.235 0.125");
+/// ```
+pub fn numbers() -> Builtins {
+    numbers_with_data(DefaultLocaleData)
+}
+
+/// Like [`numbers`](fn.numbers.html), with the separators of `data`, see [`LocaleData`](trait.LocaleData.html)
+pub fn numbers_with_data<D: 'static + LocaleData>(data: D) -> Builtins {
+    Builtins::new().with_locale("num", move |args, render_locale| {
+        let (number, precision, locale) = match args.as_slice() {
+            [number] => (*number, None, None),
+            [number, precision] => (*number, Some(*precision), None),
+            [number, precision, locale] => (*number, Some(*precision), Some(*locale)),
+            _ => return Err("num takes a number, a precision and a locale".to_string()),
+        };
+        let number: f64 = number.parse().map_err(|_| format!("{} is not a number", number))?;
+        let precision = match precision {
+            Some(precision) if !precision.is_empty() => Some(precision.parse().map_err(|_| format!("{} is not a precision", precision))?),
+            _ => None,
+        };
+        let locale = locale.or(render_locale).unwrap_or("en");
+        let (group, decimal) = data.separators(locale).ok_or_else(|| format!("Unknown locale {}", locale))?;
+        Ok(group_digits(number, precision, group, decimal))
+    })
+}
+
+// The thousands and decimal separators of a locale
+pub(crate) fn separators(locale: &str) -> Option<(char, char)> {
+    let language = locale.split(['-', '_']).next().unwrap_or("");
+    let region = locale.get(language.len() + 1..).unwrap_or("");
+    match (language.to_ascii_lowercase().as_str(), region.to_ascii_uppercase().as_str()) {
+        ("de", "CH") | ("de", "LI") => Some(('’', '.')),
+        ("en", _) | ("ja", _) | ("ko", _) | ("zh", _) => Some((',', '.')),
+        ("de", _) | ("es", _) | ("id", _) | ("it", _) | ("nl", _) | ("pt", _) | ("tr", _) => Some(('.', ',')),
+        ("fr", _) | ("cs", _) | ("pl", _) | ("ru", _) | ("sv", _) | ("uk", _) => Some(('\u{202f}', ',')),
+        _ => None,
+    }
+}
+
+// Format a number with a separator between each group of three digits
+pub(crate) fn group_digits(number: f64, precision: Option<usize>, group: char, decimal: char) -> String {
+    let formatted = match precision {
+        Some(precision) => format!("{:.*}", precision, number),
+        None => number.to_string(),
+    };
+    let (sign, digits) = match formatted.strip_prefix('-') {
+        // Rounding can leave a negative zero
+        Some(digits) if digits.bytes().any(|digit| digit.is_ascii_digit() && digit != b'0') => ("-", digits),
+        Some(digits) => ("", digits),
+        None => ("", formatted.as_str()),
+    };
+    let (whole, fraction) = match digits.find('.') {
+        Some(index) => (&digits[..index], Some(&digits[index + 1..])),
+        None => (digits, None),
+    };
+
+    let mut output = sign.to_string();
+    for (index, digit) in whole.chars().enumerate() {
+        if index > 0 && (whole.len() - index) % 3 == 0 {
+            output.push(group);
+        }
+        output.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        output.push(decimal);
+        output.push_str(fraction);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{group_digits, separators};
+
+    #[test]
+    fn group_numbers() {
+        assert_eq!(group_digits(1234567.891, Some(2), ',', '.'), "1,234,567.89");
+        assert_eq!(group_digits(123.0, None, ',', '.'), "123");
+        assert_eq!(group_digits(-999999.6, Some(0), '.', ','), "-1.000.000");
+        assert_eq!(group_digits(-0.001, Some(2), ',', '.'), "0.00");
+        assert_eq!(group_digits(1000.25, None, '\u{202f}', ','), "1\u{202f}000,25");
+        assert_eq!(separators("de_ch"), Some(('’', '.')));
+        assert_eq!(separators("pt-BR"), Some(('.', ',')));
+        assert_eq!(separators("xx"), None);
+    }
+}
@@ -0,0 +1,150 @@
+//! Plurals and other forms picked by a value
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::{Builtins, DefaultLocaleData, LocaleData};
+
+/// `{plural(count, one, other)}`, `{icu_plural(count, locale, forms...)}` and `{select(value, forms...)}`
+///
+/// `plural` picks `one` when the count is 1 and `other` otherwise. A `#` in the chosen form is replaced by the count.
+///
+/// `icu_plural` is for languages with more forms. Each form is written `category: text`, where the category is `zero`, `one`, `two`, `few`, `many` or `other` as decided by the CLDR rules of `locale`, or `=N` to match an exact count. `other` is used when nothing else matches. With the `locale` feature the locale can be left out to use the locale of the render. The rules are known for `ar`, `cs`, `de`, `en`, `es`, `fr`, `it`, `ja`, `ko`, `nl`, `pl`, `pt`, `ru`, `sk`, `sv`, `uk` and `zh`.
+///
+/// `select` picks a form by a value that isn't a count, such as `{select({gender}, female: she, male: he, other: they)}`. Each form is written `value: text`, and `other` is used when no value matches.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::builtins;
+///
+/// let mut template = Edo::new("{plural(3, # file, # files)} deleted, {icu_plural(5, pl, one: # plik, few: # pliki, many: # plików, other: # pliku)}, by {select(female, female: her, male: him, other: them)}").unwrap();
+/// template.register_builtins(builtins::plural());
+/// assert_eq!(template.render(""), "3 files deleted, 5 plików, by her");
+/// ```
+pub fn plural() -> Builtins {
+    plural_with_data(DefaultLocaleData)
+}
+
+/// Like [`plural`](fn.plural.html), with the plural rules of `data` for `icu_plural`, see [`LocaleData`](trait.LocaleData.html)
+pub fn plural_with_data<D: 'static + LocaleData>(data: D) -> Builtins {
+    Builtins::new()
+        .handler("plural", |args| match args.as_slice() {
+            [count, one, other] => {
+                let number = whole_number(count)?;
+                Ok(if number == 1 { *one } else { *other }.replace('#', count))
+            },
+            _ => Err("plural takes a count, the singular and the plural".to_string()),
+        })
+        .with_locale("icu_plural", move |args, render_locale| {
+            let (count, locale, forms) = match (args.as_slice(), render_locale) {
+                ([count, form, ..], Some(locale)) if form.contains(':') => (*count, locale, &args[1..]),
+                ([count, locale, forms @ ..], _) if !forms.is_empty() => (*count, *locale, forms),
+                _ => return Err("icu_plural takes a count, a locale and the forms".to_string()),
+            };
+            let number = whole_number(count)?;
+            let category = data.plural_category(locale, number).ok_or_else(|| format!("Unknown locale {}", locale))?;
+            let forms = forms_of(forms, "plural form", "category")?;
+            let exact = format!("={}", number);
+            let find = |wanted: &str| forms.iter().find(|(name, _)| *name == wanted).map(|(_, text)| *text);
+            match find(&exact).or_else(|| find(category)).or_else(|| find("other")) {
+                Some(text) => Ok(text.replace('#', count)),
+                None => Err(format!("icu_plural has no {} form for {}", category, count)),
+            }
+        })
+        .handler("select", |args| {
+            let (value, forms) = match args.as_slice() {
+                [value, forms @ ..] if !forms.is_empty() => (*value, forms),
+                _ => return Err("select takes a value and the forms".to_string()),
+            };
+            let forms = forms_of(forms, "select form", "value")?;
+            let find = |wanted: &str| forms.iter().find(|(name, _)| *name == wanted).map(|(_, text)| text.to_string());
+            find(value).or_else(|| find("other")).ok_or_else(|| format!("select has no form for {}", value))
+        })
+}
+
+// Split forms written `key: text`, where `what` is what they are called in errors
+fn forms_of<'s>(forms: &[&'s str], what: &str, key: &str) -> Result<Vec<(&'s str, &'s str)>, String> {
+    forms.iter().map(|form| match form.find(':') {
+        Some(index) => Ok((form[..index].trim_end(), form[index + 1..].trim_start())),
+        None => Err(format!("{} is not a {}, expected {}: text", form, what, key)),
+    }).collect()
+}
+
+fn whole_number(count: &str) -> Result<i64, String> {
+    count.parse().map_err(|_| format!("{} is not a whole number", count))
+}
+
+// The CLDR plural category of a whole number, see https://www.unicode.org/cldr/charts/latest/supplemental/language_plural_rules.html
+pub(crate) fn category(locale: &str, number: i64) -> Option<&'static str> {
+    let language = locale.split(['-', '_']).next().unwrap_or("").to_ascii_lowercase();
+    let number = number.unsigned_abs();
+    let (last, last_two) = (number % 10, number % 100);
+    Some(match language.as_str() {
+        "ja" | "ko" | "zh" => "other",
+        "de" | "en" | "es" | "it" | "nl" | "sv" => if number == 1 { "one" } else { "other" },
+        "fr" | "pt" => if number <= 1 { "one" } else { "other" },
+        "cs" | "sk" => match number {
+            1 => "one",
+            2..=4 => "few",
+            _ => "other",
+        },
+        "pl" => match (number, last, last_two) {
+            (1, _, _) => "one",
+            (_, 2..=4, _) if !(12..=14).contains(&last_two) => "few",
+            _ => "many",
+        },
+        "ru" | "uk" => match (last, last_two) {
+            (1, _) if last_two != 11 => "one",
+            (2..=4, _) if !(12..=14).contains(&last_two) => "few",
+            _ => "many",
+        },
+        "ar" => match (number, last_two) {
+            (0, _) => "zero",
+            (1, _) => "one",
+            (2, _) => "two",
+            (_, 3..=10) => "few",
+            (_, 11..=99) => "many",
+            _ => "other",
+        },
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use Edo;
+    use super::{category, plural};
+
+    #[test]
+    fn pick_categories() {
+        let russian: Vec<_> = [1, 2, 5, 11, 21, 22, 112].iter().map(|&number| category("ru", number).unwrap()).collect();
+        assert_eq!(russian, vec!["one", "few", "many", "many", "one", "few", "many"]);
+        let arabic: Vec<_> = [0, 1, 2, 3, 11, 100].iter().map(|&number| category("ar-EG", number).unwrap()).collect();
+        assert_eq!(arabic, vec!["zero", "one", "two", "few", "many", "other"]);
+        assert_eq!(category("fr", 0), Some("one"));
+        assert_eq!(category("pl", 22), Some("few"));
+        assert_eq!(category("xx", 1), None);
+    }
+
+    #[test]
+    fn render_plurals() {
+        let mut edo: Edo<()> = match Edo::new("{plural(1, # file, # files)}|{icu_plural(0, en, =0: no files, one: # file, other: # files)}|{icu_plural(2, en, one: # file)}|{icu_plural(2, en, other)}|{plural(x, a, b)}|{select(b, a: 1, b: 2)}|{select(c, a: 1, other: 0)}|{select(c, a: 1)}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_builtins(plural());
+        let (output, errors) = edo.render_with_errors(&());
+        assert_eq!(output, "1 file|no files||||2|0|");
+        let messages: Vec<_> = errors.iter().map(|error| error.to_string()).collect();
+        assert_eq!(messages, vec![
+            "icu_plural has no other form for 2",
+            "other is not a plural form, expected category: text",
+            "x is not a whole number",
+            "select has no form for c",
+        ]);
+    }
+}
@@ -0,0 +1,173 @@
+//! Random numbers and UUIDs
+
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::rc::Rc;
+use std::string::{String, ToString};
+
+use super::Builtins;
+
+/// `{uuid}`, `{rand_int(low, high)}` and `{rand_choice(a, b, c)}`
+///
+/// - `{uuid}` is a random version 4 UUID, like `0f8e2c3a-5b1d-4e6f-9a7b-3c2d1e0f9a8b`
+/// - `{rand_int(low, high)}` is a whole number from `low` to `high`, including both
+/// - `{rand_choice(a, b, c)}` is one of its arguments
+///
+/// The numbers come from a generator seeded differently every time, they aren't suitable for secrets. Use [`random_seeded`](fn.random_seeded.html) for the same output on every run.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::builtins;
+///
+/// let mut template = Edo::new("{rand_int(1, 6)}").unwrap();
+/// template.register_builtins(builtins::random());
+/// let roll: u32 = template.render("").parse().unwrap();
+/// assert!(roll >= 1 && roll <= 6);
+/// ```
+pub fn random() -> Builtins {
+    random_seeded(RandomState::new().build_hasher().finish())
+}
+
+/// Like [`random`](fn.random.html), but the same seed always gives the same values
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::builtins;
+///
+/// let render = || {
+///     let mut template = Edo::new("{uuid} {rand_choice(red, green, blue)}").unwrap();
+///     template.register_builtins(builtins::random_seeded(42));
+///     template.render("")
+/// };
+/// assert_eq!(render(), render());
+/// ```
+pub fn random_seeded(seed: u64) -> Builtins {
+    let mut state = seed;
+    // SplitMix64, from https://prng.di.unimi.it/splitmix64.c
+    random_with(move || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut value = state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        value ^ (value >> 31)
+    })
+}
+
+/// Like [`random`](fn.random.html), but the values come from `source`, which returns a random `u64` each time it is called
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::builtins;
+///
+/// let mut template = Edo::new("{rand_int(10, 19)} {rand_choice(a, b, c)}").unwrap();
+/// template.register_builtins(builtins::random_with(|| 4));
+/// assert_eq!(template.render(""), "14 b");
+/// ```
+pub fn random_with<F>(source: F) -> Builtins where
+    F: 'static + FnMut() -> u64 {
+    let source = Rc::new(RefCell::new(source));
+    let (int_source, choice_source) = (source.clone(), source.clone());
+    Builtins::new()
+        .handler("uuid", move |args| {
+            if !args.is_empty() {
+                return Err("uuid doesn't take any arguments".to_string());
+            }
+            let mut source = source.borrow_mut();
+            Ok(uuid((*source)(), (*source)()))
+        })
+        .handler("rand_int", move |args| {
+            let (low, high) = match args.as_slice() {
+                [low, high] => (whole_number(low)?, whole_number(high)?),
+                _ => return Err("rand_int takes the lowest and highest number".to_string()),
+            };
+            if low > high {
+                return Err(format!("rand_int needs {} to be no more than {}", low, high));
+            }
+            let range = high.wrapping_sub(low) as u64;
+            let offset = match range.checked_add(1) {
+                Some(size) => (*int_source.borrow_mut())() % size,
+                None => (*int_source.borrow_mut())(),
+            };
+            Ok(low.wrapping_add(offset as i64).to_string())
+        })
+        .handler("rand_choice", move |args| {
+            if args.is_empty() {
+                return Err("rand_choice takes at least one choice".to_string());
+            }
+            let index = (*choice_source.borrow_mut())() % args.len() as u64;
+            Ok(args[index as usize].to_string())
+        })
+}
+
+fn whole_number(number: &str) -> Result<i64, String> {
+    number.parse().map_err(|_| format!("{} is not a whole number", number))
+}
+
+// Format random bits as a version 4 UUID
+fn uuid(high: u64, low: u64) -> String {
+    let high = (high & !0xf000) | 0x4000;
+    let low = (low & !(0b11 << 62)) | (0b10 << 62);
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xffff,
+        low >> 48,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::string::ToString;
+    use std::vec::Vec;
+
+    use Edo;
+    use super::{random_seeded, random_with, uuid};
+
+    #[test]
+    fn format_uuids() {
+        assert_eq!(uuid(0, 0), "00000000-0000-4000-8000-000000000000");
+        assert_eq!(uuid(!0, !0), "ffffffff-ffff-4fff-bfff-ffffffffffff");
+    }
+
+    #[test]
+    fn render_random_values() {
+        let mut edo: Edo<()> = match Edo::new("{rand_int(-2, 2)}|{rand_int(-9223372036854775808, 9223372036854775807)}|{rand_choice(a, b)}|{rand_int(2, 1)}|{rand_int(x, 1)}|{rand_choice}|{uuid(x)}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        let mut next = 0;
+        edo.register_builtins(random_with(move || {
+            next += 3;
+            next
+        }));
+        let (output, errors) = edo.render_with_errors(&());
+        assert_eq!(output, "1|-9223372036854775802|b||||");
+        let messages: Vec<_> = errors.iter().map(|error| error.to_string()).collect();
+        assert_eq!(messages, vec![
+            "rand_int needs 2 to be no more than 1",
+            "x is not a whole number",
+            "rand_choice takes at least one choice",
+            "uuid doesn't take any arguments",
+        ]);
+    }
+
+    #[test]
+    fn seeds_repeat() {
+        let render = |seed| {
+            let mut edo: Edo<()> = match Edo::new("{uuid} {rand_int(0, 1000000)}") {
+                Ok(edo) => edo,
+                Err(err) => panic!("{}", err),
+            };
+            edo.register_builtins(random_seeded(seed));
+            edo.render(&())
+        };
+        assert_eq!(render(7), render(7));
+        assert_ne!(render(7), render(8));
+    }
+}
@@ -0,0 +1,93 @@
+//! Common string transforms
+
+use alloc::string::{String, ToString};
+
+use super::Builtins;
+
+/// `upper`, `lower`, `trim`, `truncate`, `pad_left` and `replace`
+///
+/// `upper`, `lower` and `trim` are filters, like `{name|upper}`, and handlers, like `{upper(text)}`. Filters don't take arguments, so the others are only handlers, with the text as the last argument:
+///
+/// - `{truncate(n, text)}` keeps the first `n` characters of `text`
+/// - `{pad_left(n, ch, text)}` pads `text` with `ch` until it is `n` characters long
+/// - `{replace(from, to, text)}` replaces every `from` in `text` with `to`
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::builtins;
+///
+/// let mut template = Edo::new("{name|upper} #{pad_left(4, 0, 42)} {truncate(3, abcdef)}").unwrap();
+/// template.register_static("name", "gio");
+/// template.register_builtins(builtins::strings());
+/// assert_eq!(template.render(""), "GIO #0042 abc");
+/// ```
+pub fn strings() -> Builtins {
+    Builtins::new()
+        .filter("upper", |value| value.to_uppercase())
+        .filter("lower", |value| value.to_lowercase())
+        .filter("trim", |value| value.trim().to_string())
+        .handler("upper", |args| one_argument("upper", &args).map(str::to_uppercase))
+        .handler("lower", |args| one_argument("lower", &args).map(str::to_lowercase))
+        .handler("trim", |args| one_argument("trim", &args).map(|text| text.trim().to_string()))
+        .handler("truncate", |args| match args.as_slice() {
+            [length, text] => Ok(text.chars().take(count(length)?).collect()),
+            _ => Err("truncate takes a length and the text".to_string()),
+        })
+        .handler("pad_left", |args| match args.as_slice() {
+            [length, padding, text] => {
+                let mut characters = padding.chars();
+                let padding = match (characters.next(), characters.next()) {
+                    (Some(padding), None) => padding,
+                    _ => return Err(format!("pad_left pads with a single character, not {}", padding)),
+                };
+                let missing = count(length)?.saturating_sub(text.chars().count());
+                let mut padded: String = (0..missing).map(|_| padding).collect();
+                padded.push_str(text);
+                Ok(padded)
+            },
+            _ => Err("pad_left takes a length, a character and the text".to_string()),
+        })
+        .handler("replace", |args| match args.as_slice() {
+            [from, to, text] => Ok(text.replace(from, to)),
+            _ => Err("replace takes the text to replace, its replacement and the text".to_string()),
+        })
+}
+
+fn one_argument<'a>(name: &str, args: &[&'a str]) -> Result<&'a str, String> {
+    match args {
+        [text] => Ok(text),
+        _ => Err(format!("{} takes one argument, the text", name)),
+    }
+}
+
+fn count(length: &str) -> Result<usize, String> {
+    length.parse().map_err(|_| format!("{} is not a length", length))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use Edo;
+    use super::strings;
+
+    #[test]
+    fn transform_strings() {
+        let mut edo: Edo<()> = match Edo::new("{name|trim|lower}|{upper(ß)}|{truncate(9, héllo)}|{truncate(2, héllo)}|{pad_left(2, -, abc)}|{replace(l, L, hello)}|{truncate(x, a)}|{pad_left(3, ab, c)}|{upper}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("name", "  GIO  ");
+        edo.register_builtins(strings());
+        let (output, errors) = edo.render_with_errors(&());
+        assert_eq!(output, "gio|SS|héllo|hé|abc|heLLo|||");
+        let messages: Vec<_> = errors.iter().map(|error| error.to_string()).collect();
+        assert_eq!(messages, vec![
+            "x is not a length",
+            "pad_left pads with a single character, not ab",
+            "upper takes one argument, the text",
+        ]);
+    }
+}
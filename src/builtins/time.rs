@@ -0,0 +1,232 @@
+//! Dates, times and durations
+
+use std::rc::Rc;
+use std::string::{String, ToString};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{Builtins, DefaultLocaleData, LocaleData};
+
+const DEFAULT_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%z";
+
+/// `{now}`, `{now_utc}` and `{duration(seconds)}`
+///
+/// - `{now(format)}` is the current time, formatted with a subset of `strftime`: `%Y %y %m %d %e %H %M %S %j %b %B %a %A %s %F %T %z %%`. Without a format it is ISO 8601, like `2001-09-09T01:46:40+0000`.
+/// - `{now_utc(format)}` is the same, always in UTC.
+/// - `{duration(seconds)}` is a number of seconds like `1h 2m 5s`, or `01:02:05` with `{duration(seconds, clock)}`.
+///
+/// Month and weekday names follow the locale of the render with the `locale` feature, for `de`, `es`, `fr`, `it`, `nl` and `pt`, and are otherwise in English. `%a` and `%b` are the first three letters of the name. Use [`time_with_data`](fn.time_with_data.html) for names of your own. There is no time zone database, so `now` is also in UTC. Use [`time_with`](fn.time_with.html) to give it an offset.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::builtins;
+///
+/// let mut template = Edo::new("{now_utc(%Y)} took {duration(3725)}").unwrap();
+/// template.register_builtins(builtins::time());
+/// assert!(template.render("").ends_with(" took 1h 2m 5s"));
+/// ```
+pub fn time() -> Builtins {
+    time_with(SystemTime::now, 0)
+}
+
+/// Like [`time`](fn.time.html), but with a clock to read and the offset of `now` from UTC in seconds
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use edo::builtins;
+///
+/// let mut template = Edo::new("{now(%F %T %z)} / {now_utc(%H:%M)}").unwrap();
+/// template.register_builtins(builtins::time_with(|| UNIX_EPOCH + Duration::from_secs(1_000_000_000), 2 * 60 * 60));
+/// assert_eq!(template.render(""), "2001-09-09 03:46:40 +0200 / 01:46");
+/// ```
+pub fn time_with<F>(clock: F, utc_offset: i32) -> Builtins where
+    F: 'static + Fn() -> SystemTime {
+    time_with_data(clock, utc_offset, DefaultLocaleData)
+}
+
+/// Like [`time_with`](fn.time_with.html), with the month and weekday names of `data`, see [`LocaleData`](trait.LocaleData.html)
+pub fn time_with_data<F, D>(clock: F, utc_offset: i32, data: D) -> Builtins where
+    F: 'static + Fn() -> SystemTime,
+    D: 'static + LocaleData {
+    let clock = Rc::new(clock);
+    let utc_clock = clock.clone();
+    let data = Rc::new(data);
+    let utc_data = data.clone();
+    Builtins::new()
+        .with_locale("now", move |args, locale| {
+            let format = match args.as_slice() {
+                [] => DEFAULT_FORMAT,
+                [format] => *format,
+                _ => return Err("now takes one argument, the format".to_string()),
+            };
+            format_time(seconds_since_epoch(clock()), utc_offset, format, locale, &*data)
+        })
+        .with_locale("now_utc", move |args, locale| {
+            let format = match args.as_slice() {
+                [] => DEFAULT_FORMAT,
+                [format] => *format,
+                _ => return Err("now_utc takes one argument, the format".to_string()),
+            };
+            format_time(seconds_since_epoch(utc_clock()), 0, format, locale, &*utc_data)
+        })
+        .handler("duration", |args| {
+            let (seconds, style) = match args.as_slice() {
+                [seconds] => (*seconds, "short"),
+                [seconds, style] => (*seconds, *style),
+                _ => return Err("duration takes a number of seconds and an optional style".to_string()),
+            };
+            let seconds: u64 = seconds.parse().map_err(|_| format!("{} is not a number of seconds", seconds))?;
+            format_duration(seconds, style)
+        })
+}
+
+// Whole seconds since the epoch, negative before it
+fn seconds_since_epoch(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(error) => -(error.duration().as_secs() as i64),
+    }
+}
+
+// The year, month and day of a number of days since the epoch, from http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = if days >= 0 { days } else { days - 146_096 } / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+// Format a time with a subset of strftime
+fn format_time(timestamp: i64, utc_offset: i32, format: &str, locale: Option<&str>, data: &dyn LocaleData) -> Result<String, String> {
+    let local = timestamp + i64::from(utc_offset);
+    let days = local.div_euclid(86_400);
+    let seconds_of_day = local.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    let (hour, minute, second) = (seconds_of_day / 3600, seconds_of_day / 60 % 60, seconds_of_day % 60);
+    let weekday = (days + 4).rem_euclid(7) as u32;
+    // Names for the locale, English when the data has none
+    let name = |named: &dyn Fn(&dyn LocaleData, &str) -> Option<String>| locale
+        .and_then(|locale| named(data, locale))
+        .or_else(|| named(&DefaultLocaleData, "en"))
+        .unwrap_or_default();
+    let day_of_year = (0..month - 1)
+        .map(|index| [31, if is_leap_year(year) { 29 } else { 28 }, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31][index as usize])
+        .sum::<u32>() + day;
+
+    let mut output = String::new();
+    let mut characters = format.chars();
+    while let Some(character) = characters.next() {
+        if character != '%' {
+            output.push(character);
+            continue;
+        }
+        match characters.next() {
+            Some('Y') => output += &year.to_string(),
+            Some('y') => output += &format!("{:02}", year.rem_euclid(100)),
+            Some('m') => output += &format!("{:02}", month),
+            Some('d') => output += &format!("{:02}", day),
+            Some('e') => output += &format!("{:2}", day),
+            Some('H') => output += &format!("{:02}", hour),
+            Some('M') => output += &format!("{:02}", minute),
+            Some('S') => output += &format!("{:02}", second),
+            Some('j') => output += &format!("{:03}", day_of_year),
+            Some('b') => output += &name(&|data, locale| data.short_month_name(locale, month)),
+            Some('B') => output += &name(&|data, locale| data.month_name(locale, month)),
+            Some('a') => output += &name(&|data, locale| data.short_weekday_name(locale, weekday)),
+            Some('A') => output += &name(&|data, locale| data.weekday_name(locale, weekday)),
+            Some('s') => output += &timestamp.to_string(),
+            Some('F') => output += &format!("{}-{:02}-{:02}", year, month, day),
+            Some('T') => output += &format!("{:02}:{:02}:{:02}", hour, minute, second),
+            Some('z') => {
+                let sign = if utc_offset < 0 { '-' } else { '+' };
+                let offset = utc_offset.unsigned_abs() / 60;
+                output += &format!("{}{:02}{:02}", sign, offset / 60, offset % 60);
+            },
+            Some('%') => output.push('%'),
+            Some(other) => return Err(format!("Unknown time format %{}", other)),
+            None => return Err("Time format ends with a lone %".to_string()),
+        }
+    }
+    Ok(output)
+}
+
+// Format a number of seconds as `1h 2m 5s` or `01:02:05`
+fn format_duration(seconds: u64, style: &str) -> Result<String, String> {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    match style {
+        "short" => {
+            let mut parts = vec![];
+            if hours > 0 {
+                parts.push(format!("{}h", hours));
+            }
+            if minutes > 0 {
+                parts.push(format!("{}m", minutes));
+            }
+            if seconds > 0 || parts.is_empty() {
+                parts.push(format!("{}s", seconds));
+            }
+            Ok(parts.join(" "))
+        },
+        "clock" => Ok(format!("{:02}:{:02}:{:02}", hours, minutes, seconds)),
+        _ => Err(format!("Unknown duration style {}, expected short or clock", style)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::string::{String, ToString};
+    use std::time::{Duration, UNIX_EPOCH};
+    use std::vec::Vec;
+
+    use Edo;
+    use builtins::{DefaultLocaleData, LocaleData};
+    use super::{civil_from_days, format_time, time_with};
+
+    #[test]
+    fn convert_days_to_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(20_819), (2027, 1, 1));
+    }
+
+    #[test]
+    fn name_months_and_weekdays() {
+        assert_eq!(format_time(1_000_000_000, 0, "%a %A %b %B", None, &DefaultLocaleData), Ok("Sun Sunday Sep September".to_string()));
+        assert_eq!(format_time(5_000_000, 0, "%a %A %b %B", Some("de-AT"), &DefaultLocaleData), Ok("Fre Freitag Feb Februar".to_string()));
+        assert_eq!(format_time(7_000_000, 0, "%b %B", Some("fr"), &DefaultLocaleData), Ok("mar mars".to_string()));
+
+        struct Polish;
+        impl LocaleData for Polish {
+            fn month_name(&self, locale: &str, month: u32) -> Option<String> {
+                if locale == "pl" && month == 9 { Some("wrzesień".to_string()) } else { None }
+            }
+        }
+        assert_eq!(format_time(1_000_000_000, 0, "%b %B %A", Some("pl"), &Polish), Ok("wrz wrzesień Sunday".to_string()));
+    }
+
+    #[test]
+    fn format_times_and_durations() {
+        let mut edo: Edo<()> = match Edo::new("{now_utc(%a %e %b %y day %j %s %%)}|{now(%H:%M %z)}|{now_utc}|{duration(0)}|{duration(3600)}|{duration(61, clock)}|{now(%Q)}|{duration(-1)}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_builtins(time_with(|| UNIX_EPOCH + Duration::from_secs(1_000_000_000), -(5 * 60 + 30) * 60));
+        let (output, errors) = edo.render_with_errors(&());
+        assert_eq!(output, "Sun  9 Sep 01 day 252 1000000000 %|20:16 -0530|2001-09-09T01:46:40+0000|0s|1h|00:01:01||");
+        let messages: Vec<_> = errors.iter().map(|error| error.to_string()).collect();
+        assert_eq!(messages, vec!["Unknown time format %Q", "-1 is not a number of seconds"]);
+    }
+}
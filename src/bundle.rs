@@ -0,0 +1,248 @@
+// The bundle file format, see `Engine::save_bundle`
+//
+// Everything is little endian, numbers are u64 and strings are a length followed by UTF-8:
+//
+//   "EDOBUNDL" version:u8 count
+//   count times: name source expressions expression...
+//
+// An expression is a tag, 0 for a literal and 1 for a placeholder, followed by slices of its template's source,
+// written as offset and length, which are also the spans of the arguments:
+//
+//   0 text
+//   1 name arguments argument... filters filter... format case:u8 raw:u8 has_parentheses:u8 span_start span_end
+//
+// A format is 0 when there is none and 1 followed by a slice when there is. A case is 0 when there is none, then
+// 1 for upper, 2 for lower and 3 for title case
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use engine::Compiled;
+use parse::{Case, Expression, Span};
+
+const MAGIC: &[u8] = b"EDOBUNDL";
+const VERSION: u8 = 4;
+
+const LITERAL: u8 = 0;
+const FUNCTION: u8 = 1;
+
+pub(crate) fn encode<'t, I>(templates: I) -> Result<Vec<u8>, String> where
+    I: ExactSizeIterator<Item = (&'t str, &'t Compiled)> {
+    let mut out = MAGIC.to_vec();
+    out.push(VERSION);
+    number(&mut out, templates.len());
+    for (name, compiled) in templates {
+        let source = compiled.source();
+        text(&mut out, name);
+        text(&mut out, source);
+        let expressions = compiled.expressions();
+        number(&mut out, expressions.len());
+        for expression in &expressions {
+            let slice = |out: &mut Vec<u8>, piece: &str| slice(out, source, piece)
+                .ok_or_else(|| format!("Template {} can't be precompiled", name));
+            match *expression {
+                Expression::Literal(literal) => {
+                    out.push(LITERAL);
+                    slice(&mut out, literal)?;
+                },
+                Expression::Function { name: function, ref arguments, ref filters, format, case, raw, has_parentheses, span, .. } => {
+                    out.push(FUNCTION);
+                    slice(&mut out, function)?;
+                    for list in &[arguments, filters] {
+                        number(&mut out, list.len());
+                        for piece in list.iter() {
+                            slice(&mut out, piece)?;
+                        }
+                    }
+                    match format {
+                        Some(format) => {
+                            out.push(1);
+                            slice(&mut out, format)?;
+                        },
+                        None => out.push(0),
+                    }
+                    out.push(match case {
+                        None => 0,
+                        Some(Case::Upper) => 1,
+                        Some(Case::Lower) => 2,
+                        Some(Case::Title) => 3,
+                    });
+                    out.push(raw as u8);
+                    out.push(has_parentheses as u8);
+                    number(&mut out, span.start);
+                    number(&mut out, span.end);
+                },
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn number(out: &mut Vec<u8>, number: usize) {
+    out.extend_from_slice(&(number as u64).to_le_bytes());
+}
+
+fn text(out: &mut Vec<u8>, text: &str) {
+    number(out, text.len());
+    out.extend_from_slice(text.as_bytes());
+}
+
+// Write `piece` as its place in `source`, if it is part of it
+fn slice(out: &mut Vec<u8>, source: &str, piece: &str) -> Option<()> {
+    let offset = (piece.as_ptr() as usize).checked_sub(source.as_ptr() as usize)?;
+    if offset + piece.len() > source.len() {
+        return None;
+    }
+    number(out, offset);
+    number(out, piece.len());
+    Some(())
+}
+
+pub(crate) fn decode(bytes: &[u8]) -> Result<Vec<(String, Compiled)>, String> {
+    let mut reader = Reader { bytes };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err("Not a template bundle".to_string());
+    }
+    match reader.byte()? {
+        VERSION => {},
+        version => return Err(format!("Unsupported bundle version {}", version)),
+    }
+
+    let count = reader.number()?;
+    let mut templates = Vec::new();
+    for _ in 0..count {
+        let name = reader.text()?.to_string();
+        let source = reader.text()?.to_string();
+        let compiled = Compiled::build(source, |source| {
+            let count = reader.number()?;
+            let mut expressions = Vec::new();
+            for _ in 0..count {
+                expressions.push(match reader.byte()? {
+                    LITERAL => Expression::Literal(reader.slice(source)?),
+                    FUNCTION => {
+                        let name = reader.slice(source)?;
+                        let mut lists = [vec![], vec![]];
+                        for list in &mut lists {
+                            for _ in 0..reader.number()? {
+                                list.push(reader.slice(source)?);
+                            }
+                        }
+                        let [arguments, filters] = lists;
+                        let argument_spans = arguments.iter().map(|argument: &&str| {
+                            let start = argument.as_ptr() as usize - source.as_ptr() as usize;
+                            Span { start, end: start + argument.len() }
+                        }).collect();
+                        let format = match reader.byte()? {
+                            0 => None,
+                            _ => Some(reader.slice(source)?),
+                        };
+                        let case = match reader.byte()? {
+                            0 => None,
+                            1 => Some(Case::Upper),
+                            2 => Some(Case::Lower),
+                            3 => Some(Case::Title),
+                            case => return Err(format!("Unknown case {} in template {}", case, name)),
+                        };
+                        let raw = reader.byte()? != 0;
+                        let has_parentheses = reader.byte()? != 0;
+                        let span = Span { start: reader.number()?, end: reader.number()? };
+                        if span.start > span.end || span.end > source.len() {
+                            return Err("The bundle has a span outside of a template".to_string());
+                        }
+                        Expression::Function { name, arguments, argument_spans, has_parentheses, case, filters, format, raw, span }
+                    },
+                    tag => return Err(format!("Unknown expression {} in template {}", tag, name)),
+                });
+            }
+            Ok(expressions)
+        })?;
+        templates.push((name, compiled));
+    }
+    if !reader.bytes.is_empty() {
+        return Err("Unexpected data after the templates".to_string());
+    }
+    Ok(templates)
+}
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+}
+
+impl<'b> Reader<'b> {
+    fn take(&mut self, length: usize) -> Result<&'b [u8], String> {
+        if length > self.bytes.len() {
+            return Err("The bundle is cut short".to_string());
+        }
+        let (taken, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn number(&mut self) -> Result<usize, String> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        usize::try_from(u64::from_le_bytes(bytes)).map_err(|_| "The bundle is too large".to_string())
+    }
+
+    fn text(&mut self) -> Result<&'b str, String> {
+        let length = self.number()?;
+        core::str::from_utf8(self.take(length)?).map_err(|_| "The bundle has text that isn't UTF-8".to_string())
+    }
+
+    fn slice<'s>(&mut self, source: &'s str) -> Result<&'s str, String> {
+        let offset = self.number()?;
+        let length = self.number()?;
+        offset.checked_add(length)
+            .and_then(|end| source.get(offset..end))
+            .ok_or_else(|| "The bundle points outside of a template".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use engine::Compiled;
+    use parse;
+    use super::{decode, encode};
+
+    #[test]
+    fn round_trip() {
+        let sources = ["Hello {name(a, b)|upper|raw}!", "", "{!x}{y()}{z(,)!title:>3}"];
+        let compiled: Vec<_> = sources.iter().map(|source| Compiled::build(source.to_string(), parse::parse).unwrap()).collect();
+        let names = ["hello", "empty", "pair"];
+        let bytes = encode(names.iter().cloned().zip(compiled.iter())).unwrap();
+
+        let decoded = decode(&bytes).unwrap();
+        assert_eq!(decoded.len(), 3);
+        for ((name, template), (expected_name, expected)) in decoded.iter().zip(names.iter().zip(compiled.iter())) {
+            assert_eq!(name, expected_name);
+            assert_eq!(template.source(), expected.source());
+            assert_eq!(template.expressions(), expected.expressions());
+        }
+
+        assert_eq!(decode(b"nope").err(), Some("The bundle is cut short".to_string()));
+        assert_eq!(decode(b"EDOBUNDL\x03").err(), Some("Unsupported bundle version 3".to_string()));
+        assert_eq!(decode(&bytes[..bytes.len() - 1]).err(), Some("The bundle is cut short".to_string()));
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(decode(&trailing).err(), Some("Unexpected data after the templates".to_string()));
+
+        // The span of `{a}` is the last two numbers
+        let bytes = encode(core::iter::once(("a", &Compiled::new("{a}".to_string()).unwrap()))).unwrap();
+        let span = bytes.len() - 16;
+        for &(start, end) in &[(2u64, 1u64), (0, 4), (4, 4)] {
+            let mut malformed = bytes[..span].to_vec();
+            malformed.extend_from_slice(&start.to_le_bytes());
+            malformed.extend_from_slice(&end.to_le_bytes());
+            assert_eq!(decode(&malformed).err(), Some("The bundle has a span outside of a template".to_string()));
+        }
+        assert!(decode(&bytes).is_ok());
+    }
+}
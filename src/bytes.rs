@@ -0,0 +1,181 @@
+//! Templates over raw bytes
+//!
+//! A [`ByteTemplate`](struct.ByteTemplate.html) works like [`Edo`](../struct.Edo.html), but its literals can be any bytes and its producers return `Vec<u8>`. Use it for binary protocols or files that aren't valid UTF-8. Placeholder names and arguments must still be UTF-8, and there is no escaping or filters.
+//!
+//! ```
+//! use edo::bytes::ByteTemplate;
+//!
+//! let mut template = ByteTemplate::new(b"\x02{length}\x00{payload}\x03").unwrap();
+//! template.register_static("payload", &b"\xde\xad"[..]);
+//! template.register_handler("length", |_, payload: &[u8]| Ok(vec![payload.len() as u8]));
+//! assert_eq!(template.render(b"\xde\xad"), b"\x02\x02\x00\xde\xad\x03");
+//! ```
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use args::{self, Args};
+use error::{EdoError, RenderError};
+use options::RenderOptions;
+use parse::{self, ByteExpression};
+use report::{PlaceholderOutcome, RenderReport, Resolution, Severity, Timer};
+
+type ByteHandler<C> = Box<dyn Fn(Args, &C) -> Result<Vec<u8>, String>>;
+
+enum ByteProducer<C: ?Sized> {
+    Handler(ByteHandler<C>),
+    Static(Vec<u8>),
+}
+
+/// A template whose literals and output are bytes
+pub struct ByteTemplate<'a, C: ?Sized> {
+    value_producers: BTreeMap<String, ByteProducer<C>>,
+    options: RenderOptions,
+    template: Vec<ByteExpression<'a>>,
+}
+
+impl<'a, C: ?Sized> ByteTemplate<'a, C> {
+    /// Creates a new byte template
+    pub fn new(template: &'a [u8]) -> Result<ByteTemplate<'a, C>, EdoError> {
+        Ok(ByteTemplate {
+            value_producers: BTreeMap::new(),
+            options: RenderOptions::new(),
+            template: parse::parse_bytes(template)?,
+        })
+    }
+
+    /// Register a new function handler
+    pub fn register_handler<F>(&mut self, name: &str, handler: F) where
+        F: 'static + Fn(Args, &C) -> Result<Vec<u8>, String> {
+        self.value_producers.insert(name.to_string(), ByteProducer::Handler(Box::new(handler)));
+    }
+
+    /// Register a static replacement
+    pub fn register_static<B: Into<Vec<u8>>>(&mut self, name: &str, input: B) {
+        self.value_producers.insert(name.to_string(), ByteProducer::Static(input.into()));
+    }
+
+    /// Set the options used by every render
+    ///
+    /// The output limit, allow-list, severities and fail fast mode apply. The other options only affect text templates.
+    pub fn set_options(&mut self, options: RenderOptions) {
+        self.options = options;
+    }
+
+    /// Render the template into bytes
+    ///
+    /// Returns nothing if the render is aborted, see [`try_render`](#method.try_render).
+    pub fn render(&self, context: &C) -> Vec<u8> {
+        self.render_with_errors(context).0
+    }
+
+    /// Render the template into bytes and recieve a vector of errors
+    ///
+    /// If the render is aborted the output is empty and the reason is the last error. Warnings are left out.
+    pub fn render_with_errors(&self, context: &C) -> (Vec<u8>, Vec<RenderError>) {
+        match self.try_render(context) {
+            Ok(report) => (report.output, report.errors),
+            Err(error) => (vec![], vec![error]),
+        }
+    }
+
+    /// Render the template into a report of the bytes, warnings and errors, or the error that aborted the render
+    pub fn try_render(&self, context: &C) -> Result<RenderReport<Vec<u8>>, RenderError> {
+        let mut report: RenderReport<Vec<u8>> = RenderReport::default();
+        for expression in &self.template {
+            let piece = match *expression {
+                ByteExpression::Literal(bytes) => bytes.to_vec(),
+                ByteExpression::Function { name, span, .. } if !self.options.is_allowed(name) => {
+                    report.report(Severity::Error, RenderError::NotAllowed { name: name.to_string(), span });
+                    report.placeholders.push(PlaceholderOutcome { name: name.to_string(), span, resolution: Resolution::Skipped, duration: None });
+                    vec![]
+                },
+                ByteExpression::Function { name, ref arguments, has_parentheses, span } => {
+                    let timer = Timer::start();
+                    let (resolution, bytes) = match self.value_producers.get(name) {
+                        None => {
+                            report.report(self.options.get_missing_producers(), RenderError::MissingProducer { name: name.to_string(), span });
+                            (Resolution::Skipped, vec![])
+                        },
+                        Some(ByteProducer::Handler(handler)) => match handler(Args::borrow(&args::unescape(arguments), has_parentheses), context) {
+                            Ok(bytes) => (Resolution::Handler, bytes),
+                            Err(message) => {
+                                report.report(self.options.get_handler_failures(), RenderError::HandlerFailed {
+                                    name: name.to_string(),
+                                    args: arguments.iter().map(|argument| argument.to_string()).collect(),
+                                    message,
+                                    span,
+                                });
+                                (Resolution::Failed, vec![])
+                            },
+                        },
+                        Some(ByteProducer::Static(bytes)) => (Resolution::Static, bytes.clone()),
+                    };
+                    report.placeholders.push(PlaceholderOutcome { name: name.to_string(), span, resolution, duration: timer.elapsed() });
+                    bytes
+                },
+            };
+
+            if self.options.get_fail_fast() && !report.errors.is_empty() {
+                return Err(report.errors.remove(0));
+            }
+            if let Some(limit) = self.options.get_max_output() {
+                if report.output.len() + piece.len() > limit {
+                    return Err(RenderError::OutputTooLarge { limit });
+                }
+            }
+            report.output.extend_from_slice(&piece);
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use error::RenderError;
+    use options::RenderOptions;
+    use parse::Span;
+    use super::ByteTemplate;
+
+    #[test]
+    fn render_bytes() {
+        let mut template: ByteTemplate<()> = match ByteTemplate::new(b"\xff{a}{b(x)}{c}{missing}") {
+            Ok(template) => template,
+            Err(err) => panic!("{}", err),
+        };
+        template.register_static("a", vec![0u8, 1]);
+        template.register_handler("b", |args, _| Ok(args[0].as_bytes().to_vec()));
+        template.register_handler("c", |_, _| Err("broken".to_string()));
+        assert_eq!(
+            template.render_with_errors(&()),
+            (b"\xff\x00\x01x".to_vec(), vec![RenderError::HandlerFailed {
+                name: "c".to_string(),
+                args: vec![],
+                message: "broken".to_string(),
+                span: Span { start: 10, end: 13 },
+            }])
+        );
+    }
+
+    #[test]
+    fn render_bytes_with_options() {
+        let mut template: ByteTemplate<()> = match ByteTemplate::new(b"{a}{b}") {
+            Ok(template) => template,
+            Err(err) => panic!("{}", err),
+        };
+        template.register_static("a", "aa");
+        template.register_static("b", "bb");
+        template.set_options(RenderOptions::new().allow_only(vec!["a"]));
+        assert_eq!(
+            template.render_with_errors(&()),
+            (b"aa".to_vec(), vec![RenderError::NotAllowed { name: "b".to_string(), span: Span { start: 3, end: 6 } }])
+        );
+
+        template.set_options(RenderOptions::new().max_output(3));
+        assert_eq!(template.try_render(&()), Err(RenderError::OutputTooLarge { limit: 3 }));
+    }
+}
@@ -0,0 +1,284 @@
+//! Translating the literal text of templates, see [`Edo::set_catalog`](../struct.Edo.html#method.set_catalog)
+//!
+//! A render in a locale looks up each literal of the template in the catalog and uses the translation in its place, while the placeholders are rendered as usual. The same template then renders in every language the catalog has. Rendering with a catalog needs the `locale` feature, and [`Edo::extract_messages`](../struct.Edo.html#method.extract_messages) lists the messages to translate.
+
+#[cfg(feature = "locale")]
+use alloc::collections::BTreeMap;
+#[cfg(feature = "locale")]
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use parse::{Expression, Span};
+
+/// Translations of literal text, by locale
+///
+/// Needs the `locale` feature.
+///
+/// A literal is looked up without the whitespace around it, which is kept as it is, so in `Hello {name}, welcome` the messages are `Hello` and `, welcome`.
+#[cfg(feature = "locale")]
+pub trait Catalog {
+    /// The translation of `message` in `locale`, or `None` to keep the message as it is
+    fn translate(&self, locale: &str, message: &str) -> Option<String>;
+}
+
+#[cfg(feature = "locale")]
+impl<F: Fn(&str, &str) -> Option<String>> Catalog for F {
+    fn translate(&self, locale: &str, message: &str) -> Option<String> {
+        self(locale, message)
+    }
+}
+
+/// A catalog kept in memory
+///
+/// Needs the `locale` feature.
+/// Translations are keyed by the message itself, or by an id given to the message with [`id`](#method.id), so a message that is reworded in the template keeps its translations.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::catalog::Messages;
+///
+/// let mut template = Edo::new("Hello {name}, welcome back").unwrap();
+/// template.register_static("name", "Gio");
+/// template.set_catalog(Messages::new()
+///     .id(", welcome back", "welcome")
+///     .translation("es", "Hello", "Hola")
+///     .translation("es", "welcome", ", bienvenido de nuevo"));
+/// assert_eq!(template.render_in("es", ""), "Hola Gio, bienvenido de nuevo");
+/// assert_eq!(template.render_in("fr", ""), "Hello Gio, welcome back");
+/// assert_eq!(template.render(""), "Hello Gio, welcome back");
+/// ```
+#[cfg(feature = "locale")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Messages {
+    // The id of a message, for messages that aren't their own key
+    ids: BTreeMap<String, String>,
+    // Translations by locale, then by message or id
+    translations: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+#[cfg(feature = "locale")]
+impl Messages {
+    /// An empty catalog
+    pub fn new() -> Messages {
+        Messages::default()
+    }
+
+    /// Look `message` up by `id` instead of by its text
+    pub fn id<M: Into<String>, I: Into<String>>(mut self, message: M, id: I) -> Messages {
+        self.ids.insert(message.into(), id.into());
+        self
+    }
+
+    /// Translate the message written `key`, or the one with that id, as `text` in `locale`
+    pub fn translation<S: Into<String>>(mut self, locale: &str, key: &str, text: S) -> Messages {
+        self.translations.entry(locale.to_string()).or_default().insert(key.to_string(), text.into());
+        self
+    }
+}
+
+#[cfg(feature = "locale")]
+impl Catalog for Messages {
+    fn translate(&self, locale: &str, message: &str) -> Option<String> {
+        let key = self.ids.get(message).map_or(message, String::as_str);
+        self.translations.get(locale)?.get(key).cloned()
+    }
+}
+
+/// The locales to look in, in turn, for something that a locale doesn't have, like `pt-BR`, then `pt`, then `en`
+///
+/// Needs the `locale` feature. Set with [`RenderOptions::locale_chain`](../options/struct.RenderOptions.html#method.locale_chain), it is used for the translations of a [`Catalog`](trait.Catalog.html) and for the per-locale templates of [`Engine::render_localized`](../engine/struct.Engine.html#method.render_localized). A locale falls back to the one set with [`fallback`](#method.fallback), or else to itself without its last subtag, and the chain ends with the [`default_locale`](#method.default_locale).
+///
+/// # Examples
+/// ```
+/// use edo::catalog::LocaleChain;
+///
+/// let chain = LocaleChain::new().fallback("pt-BR", "pt-PT").default_locale("en");
+/// assert_eq!(chain.resolve("pt-BR"), vec!["pt-BR", "pt-PT", "pt", "en"]);
+/// assert_eq!(chain.resolve("de-CH-1996"), vec!["de-CH-1996", "de-CH", "de", "en"]);
+/// assert_eq!(LocaleChain::new().resolve("fr_CA"), vec!["fr_CA", "fr"]);
+/// ```
+#[cfg(feature = "locale")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocaleChain {
+    fallbacks: BTreeMap<String, String>,
+    default_locale: Option<String>,
+}
+
+#[cfg(feature = "locale")]
+impl LocaleChain {
+    /// A chain that only drops subtags
+    pub fn new() -> LocaleChain {
+        LocaleChain::default()
+    }
+
+    /// Look in `fallback` after `locale`, instead of in `locale` without its last subtag
+    pub fn fallback(mut self, locale: &str, fallback: &str) -> LocaleChain {
+        self.fallbacks.insert(locale.to_string(), fallback.to_string());
+        self
+    }
+
+    /// Look in `locale` last, after every other locale in the chain
+    pub fn default_locale(mut self, locale: &str) -> LocaleChain {
+        self.default_locale = Some(locale.to_string());
+        self
+    }
+
+    /// The locales to look in for `locale`, starting with itself
+    pub fn resolve(&self, locale: &str) -> Vec<String> {
+        let mut chain: Vec<String> = vec![];
+        let mut next = Some(locale.to_string());
+        while let Some(locale) = next.take() {
+            if chain.contains(&locale) {
+                break;
+            }
+            next = match self.fallbacks.get(&locale) {
+                Some(fallback) => Some(fallback.clone()),
+                None => locale.rfind(['-', '_']).map(|end| locale[..end].to_string()),
+            };
+            chain.push(locale);
+        }
+        if let Some(ref default_locale) = self.default_locale {
+            if !chain.contains(default_locale) {
+                chain.push(default_locale.clone());
+            }
+        }
+        chain
+    }
+}
+
+/// A message to translate, as listed by [`Edo::extract_messages`](../struct.Edo.html#method.extract_messages)
+///
+/// With the `serde` feature it serializes as a struct of `text`, `start`, `end`, `before` and `after`, for turning into PO or JSON files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Message<'a> {
+    /// The literal without the whitespace around it, which is what the catalog is asked for
+    pub text: &'a str,
+    /// Where the text is in the template
+    pub span: Span,
+    /// The name of the placeholder right before the text, if there is one
+    pub before: Option<&'a str>,
+    /// The name of the placeholder right after the text, if there is one
+    pub after: Option<&'a str>,
+}
+
+#[cfg(feature = "serde")]
+impl<'a> serde::Serialize for Message<'a> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut message = serializer.serialize_struct("Message", 5)?;
+        message.serialize_field("text", self.text)?;
+        message.serialize_field("start", &self.span.start)?;
+        message.serialize_field("end", &self.span.end)?;
+        message.serialize_field("before", &self.before)?;
+        message.serialize_field("after", &self.after)?;
+        message.end()
+    }
+}
+
+// The messages in a template, in order
+pub(crate) fn messages<'a>(template: &[Expression<'a>]) -> Vec<Message<'a>> {
+    let name = |index: Option<usize>| match index.and_then(|index| template.get(index)) {
+        Some(&Expression::Function { name, .. }) => Some(name),
+        _ => None,
+    };
+    let mut messages = vec![];
+    // Literals don't have spans, but they start where the placeholder before them ends
+    let mut offset = 0;
+    for (index, expression) in template.iter().enumerate() {
+        match *expression {
+            Expression::Literal(literal) => {
+                let text = literal.trim();
+                if !text.is_empty() {
+                    let start = offset + literal.len() - literal.trim_start().len();
+                    messages.push(Message {
+                        text,
+                        span: Span { start, end: start + text.len() },
+                        before: name(index.checked_sub(1)),
+                        after: name(Some(index + 1)),
+                    });
+                }
+                offset += literal.len();
+            },
+            Expression::Function { span, .. } => offset = span.end,
+        }
+    }
+    messages
+}
+
+// `literal` translated by `catalog` in the first locale of `chain` that has it, keeping the whitespace around it
+#[cfg(feature = "locale")]
+pub(crate) fn translate(catalog: &dyn Catalog, chain: &LocaleChain, locale: &str, literal: &str) -> Option<String> {
+    let message = literal.trim();
+    if message.is_empty() {
+        return None;
+    }
+    let translation = chain.resolve(locale).iter().find_map(|locale| catalog.translate(locale, message))?;
+    let start = literal.len() - literal.trim_start().len();
+    let end = start + message.len();
+    Some(format!("{}{}{}", &literal[..start], translation, &literal[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "locale")]
+    use alloc::string::ToString;
+
+    use super::Message;
+    #[cfg(feature = "locale")]
+    use super::{translate, LocaleChain, Messages};
+    #[cfg(feature = "locale")]
+    use options::RenderOptions;
+    use parse::Span;
+    use Edo;
+
+    #[test]
+    fn extract_messages() {
+        let source = "Hello {name}, welcome\n {count(1)}  {a}{b} files ";
+        let edo: Edo<()> = Edo::new(source).unwrap();
+        assert_eq!(edo.extract_messages(), vec![
+            Message { text: "Hello", span: Span { start: 0, end: 5 }, before: None, after: Some("name") },
+            Message { text: ", welcome", span: Span { start: 12, end: 21 }, before: Some("name"), after: Some("count") },
+            Message { text: "files", span: Span { start: 42, end: 47 }, before: Some("b"), after: None },
+        ]);
+        assert_eq!(&source[42..47], "files");
+        assert!(Edo::<()>::new("{a} {b}").unwrap().extract_messages().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serialize_messages() {
+        let edo: Edo<()> = Edo::new("Hi {name}").unwrap();
+        assert_eq!(
+            ::serde_json::to_string(&edo.extract_messages()).unwrap(),
+            r#"[{"text":"Hi","start":0,"end":2,"before":null,"after":"name"}]"#
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "locale")]
+    fn translate_literals() {
+        let messages = Messages::new().translation("de", "files", "Dateien").id("Bye", "bye").translation("de", "bye", "Tschüss");
+        let chain = LocaleChain::new();
+        assert_eq!(translate(&messages, &chain, "de", "  files\n"), Some("  Dateien\n".to_string()));
+        assert_eq!(translate(&messages, &chain, "de", "Bye"), Some("Tschüss".to_string()));
+        assert_eq!(translate(&messages, &chain, "de", "bye"), Some("Tschüss".to_string()));
+        assert_eq!(translate(&messages, &chain, "de", "  "), None);
+        assert_eq!(translate(&messages, &chain, "fr", "files"), None);
+        assert_eq!(translate(&messages, &chain, "de-AT", "files"), Some("Dateien".to_string()));
+        assert_eq!(translate(&messages, &chain.clone().default_locale("de"), "fr", "files"), Some("Dateien".to_string()));
+        assert_eq!(translate(&messages, &chain.fallback("de-AT", "fr"), "de-AT", "files"), None);
+        assert_eq!(LocaleChain::new().fallback("a", "b").fallback("b", "a").resolve("a"), vec!["a", "b"]);
+
+        let mut edo = Edo::new("{count} files").unwrap();
+        edo.register_static("count", "3");
+        edo.set_catalog(|locale: &str, message: &str| if locale == "pl" { Some(message.to_uppercase()) } else { None });
+        assert_eq!(edo.render_in("pl", &()), "3 FILES");
+        assert_eq!(edo.render_in("de", &()), "3 files");
+        edo.set_catalog(messages);
+        assert_eq!(edo.render_in("de", &()), "3 Dateien");
+        edo.set_options(RenderOptions::new().locale_chain(LocaleChain::new().default_locale("de")));
+        assert_eq!(edo.render_in("es", &()), "3 Dateien");
+    }
+}
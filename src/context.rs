@@ -0,0 +1,492 @@
+//! Render contexts that placeholders can be looked up in
+
+use alloc::borrow::{Borrow, Cow};
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::any::Any;
+use core::cell::RefCell;
+use core::fmt;
+#[cfg(feature = "std")]
+use core::hash::{BuildHasher, Hash};
+use core::str::FromStr;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::error::Error;
+
+/// A context that can supply values for placeholders by name
+///
+/// Call [`Edo::use_context_lookup`](../struct.Edo.html#method.use_context_lookup) and any placeholder without a handler or static replacement is looked up in the render context.
+///
+/// # Examples
+/// ```
+/// # use std::collections::BTreeMap;
+/// # use edo::Edo;
+/// let mut template = Edo::new("Hello {name}").unwrap();
+/// template.use_context_lookup();
+///
+/// let mut context = BTreeMap::new();
+/// context.insert("name", "World!");
+/// assert_eq!(template.render(&context), "Hello World!");
+/// ```
+///
+/// Handlers can read typed values from a context with [`get_parsed`](#method.get_parsed).
+///
+/// ```
+/// # use edo::Edo;
+/// use edo::context::Lookup;
+///
+/// let mut template = Edo::new("{stars}").unwrap();
+/// template.register_handler("stars", |_, context: &Vec<(&str, &str)>| Ok("*".repeat(context.get_parsed("rating")?)));
+/// assert_eq!(template.render(&vec![("rating", "3")]), "***");
+/// ```
+pub trait Lookup {
+    /// Get the value for a placeholder, or `None` if the context doesn't have one
+    fn get(&self, name: &str) -> Option<Cow<'_, str>>;
+
+    /// The value for `name`, parsed
+    fn get_parsed<T: FromStr>(&self, name: &str) -> Result<T, LookupError> where Self: Sized {
+        let value = self.get(name).ok_or_else(|| LookupError::Missing { name: name.to_string() })?;
+        value.parse().map_err(|_| LookupError::Invalid { name: name.to_string(), value: value.into_owned() })
+    }
+}
+
+/// A context value that is missing or couldn't be parsed, see [`Lookup::get_parsed`](trait.Lookup.html#method.get_parsed)
+///
+/// It converts into a `String`, so handlers can return it with `?`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LookupError {
+    /// The context has no value with this name
+    Missing {
+        /// The name that was looked up
+        name: String,
+    },
+    /// The value couldn't be parsed
+    Invalid {
+        /// The name that was looked up
+        name: String,
+        /// The value in the context
+        value: String,
+    },
+}
+
+impl fmt::Display for LookupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LookupError::Missing { ref name } => write!(f, "The context has no {}", name),
+            LookupError::Invalid { ref name, ref value } => write!(f, "The context's {} isn't valid: {}", name, value),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for LookupError {}
+
+impl From<LookupError> for String {
+    fn from(error: LookupError) -> String {
+        error.to_string()
+    }
+}
+
+impl<L: Lookup + ?Sized> Lookup for &L {
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        (**self).get(name)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V, S> Lookup for HashMap<K, V, S> where
+    K: Borrow<str> + Hash + Eq,
+    V: AsRef<str>,
+    S: BuildHasher {
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        HashMap::get(self, name).map(|value| Cow::Borrowed(value.as_ref()))
+    }
+}
+
+impl<K, V> Lookup for BTreeMap<K, V> where
+    K: Borrow<str> + Ord,
+    V: AsRef<str> {
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        BTreeMap::get(self, name).map(|value| Cow::Borrowed(value.as_ref()))
+    }
+}
+
+/// Pairs are searched in order, so the first matching key wins
+impl<K, V> Lookup for Vec<(K, V)> where
+    K: AsRef<str>,
+    V: AsRef<str> {
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        self.iter()
+            .find(|pair| pair.0.as_ref() == name)
+            .map(|pair| Cow::Borrowed(pair.1.as_ref()))
+    }
+}
+
+/// Values by name, ready to render with
+///
+/// For quick scripts and tests, where picking between a map, a struct or handlers isn't worth it yet. Values are converted with `ToString`.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::context::Context;
+///
+/// let mut template = Edo::new("Hello {name}, you have {count} messages").unwrap();
+/// template.use_context_lookup();
+/// let context = Context::new().set("name", "World").set("count", 3);
+/// assert_eq!(template.render(&context), "Hello World, you have 3 messages");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Context {
+    values: BTreeMap<String, String>,
+}
+
+impl Context {
+    /// A context without values
+    pub fn new() -> Context {
+        Context::default()
+    }
+
+    /// Set `name` to `value`, replacing the value it had
+    pub fn set<V: ToString>(mut self, name: &str, value: V) -> Context {
+        self.values.insert(name.to_string(), value.to_string());
+        self
+    }
+}
+
+impl Lookup for Context {
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        self.values.get(name).map(|value| Cow::Borrowed(value.as_str()))
+    }
+}
+
+/// A stack of contexts where later layers shadow earlier ones
+///
+/// Push a layer for each scope, like the per-request values on top of the ones shared by every request, and handlers get a single context to look names up in.
+///
+/// # Examples
+/// ```
+/// # use std::collections::BTreeMap;
+/// # use edo::Edo;
+/// use edo::context::{Layers, Lookup};
+///
+/// let mut base = BTreeMap::new();
+/// base.insert("site", "Edo");
+/// base.insert("user", "guest");
+/// let mut request = BTreeMap::new();
+/// request.insert("user", "Gio");
+///
+/// let mut template = Edo::new("{site}: {user}").unwrap();
+/// template.use_context_lookup();
+/// let mut layers = Layers::new();
+/// layers.push(&base);
+/// assert_eq!(template.render(&layers), "Edo: guest");
+/// layers.push(&request);
+/// assert_eq!(template.render(&layers), "Edo: Gio");
+/// assert_eq!(layers.pop(), Some(&request));
+/// assert_eq!(layers.get("user").as_deref(), Some("guest"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layers<L> {
+    layers: Vec<L>,
+}
+
+impl<L> Layers<L> {
+    /// An empty stack, where nothing can be looked up
+    pub fn new() -> Layers<L> {
+        Layers { layers: vec![] }
+    }
+
+    /// Add a layer on top, shadowing the ones below it
+    pub fn push(&mut self, layer: L) {
+        self.layers.push(layer);
+    }
+
+    /// Remove the top layer
+    pub fn pop(&mut self) -> Option<L> {
+        self.layers.pop()
+    }
+
+    /// The layers, from the bottom up
+    pub fn layers(&self) -> &[L] {
+        &self.layers
+    }
+}
+
+impl<L> Default for Layers<L> {
+    fn default() -> Layers<L> {
+        Layers::new()
+    }
+}
+
+/// The layers are pushed in order, so the last one is on top
+impl<L> From<Vec<L>> for Layers<L> {
+    fn from(layers: Vec<L>) -> Layers<L> {
+        Layers { layers }
+    }
+}
+
+impl<L: Lookup> Lookup for Layers<L> {
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        self.layers.iter().rev().find_map(|layer| layer.get(name))
+    }
+}
+
+/// A context that handlers can change, for renders that collect something as a side effect
+///
+/// Render with [`Edo::render_mut`](../struct.Edo.html#method.render_mut), and every handler gets the same value, which ends up back in the caller's variable when the render is done.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::context::Mut;
+///
+/// let mut template = Edo::new("<img src=\"{asset(logo.png)}\">").unwrap();
+/// template.register_handler("asset", |args, assets: &Mut<Vec<String>>| {
+///     assets.with(|assets| assets.push(args[0].to_string()));
+///     Ok(format!("/static/{}", &args[0]))
+/// });
+///
+/// let mut assets = vec![];
+/// assert_eq!(template.render_mut(&mut assets), "<img src=\"/static/logo.png\">");
+/// assert_eq!(assets, vec!["logo.png".to_string()]);
+/// ```
+#[derive(Debug, Default)]
+pub struct Mut<T> {
+    value: RefCell<T>,
+}
+
+impl<T> Mut<T> {
+    /// Share `value` with the handlers of a render
+    pub fn new(value: T) -> Mut<T> {
+        Mut { value: RefCell::new(value) }
+    }
+
+    /// Call `change` with the value
+    ///
+    /// # Panics
+    /// When `change` calls `with` on the same value.
+    pub fn with<R, F: FnOnce(&mut T) -> R>(&self, change: F) -> R {
+        change(&mut self.value.borrow_mut())
+    }
+
+    // The value, once the render is done
+    pub(crate) fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+/// Looked up values are copied, since the value can change between lookups
+impl<T: Lookup> Lookup for Mut<T> {
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        self.value.borrow().get(name).map(|value| Cow::Owned(value.into_owned()))
+    }
+}
+
+/// Values of any type by name, for handlers that each need different services
+///
+/// Get a value back with its type, as in `context.get::<Database>("db")`. Cloning the bag shares the values instead of copying them. As a [`Lookup`](trait.Lookup.html), the values that are a `String` or a `&'static str` are looked up.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::context::ContextBag;
+///
+/// struct Prices { tax: f64 }
+///
+/// let mut template = Edo::new("{site}: {price(10)}").unwrap();
+/// template.use_context_lookup();
+/// template.register_handler("price", |args, context: &ContextBag| {
+///     let prices = context.get::<Prices>("prices").ok_or("No prices")?;
+///     Ok(format!("{:.2}", args.get_parsed::<f64>(0)? * (1.0 + prices.tax)))
+/// });
+///
+/// let mut context = ContextBag::new();
+/// context.insert("prices", Prices { tax: 0.2 });
+/// context.insert("site", "Shop");
+/// assert_eq!(template.render(&context), "Shop: 12.00");
+/// ```
+#[derive(Clone, Default)]
+pub struct ContextBag {
+    values: BTreeMap<String, Rc<dyn Any>>,
+}
+
+impl ContextBag {
+    /// An empty bag
+    pub fn new() -> ContextBag {
+        ContextBag::default()
+    }
+
+    /// Add `value` as `name`, replacing the value that had that name
+    pub fn insert<T: Any>(&mut self, name: &str, value: T) {
+        self.values.insert(name.to_string(), Rc::new(value));
+    }
+
+    /// The value called `name`, if there is one of type `T`
+    pub fn get<T: Any>(&self, name: &str) -> Option<&T> {
+        self.values.get(name).and_then(|value| value.downcast_ref())
+    }
+}
+
+impl fmt::Debug for ContextBag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.values.keys()).finish()
+    }
+}
+
+impl Lookup for ContextBag {
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        let value = self.values.get(name)?;
+        value.downcast_ref::<String>().map(String::as_str)
+            .or_else(|| value.downcast_ref::<&'static str>().cloned())
+            .map(Cow::Borrowed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::borrow::Cow;
+    use alloc::collections::BTreeMap;
+    use alloc::string::{String, ToString};
+    #[cfg(feature = "std")]
+    use std::collections::HashMap;
+
+    use super::{Context, ContextBag, Layers, Lookup, LookupError, Mut};
+    use Edo;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn lookup_hash_map() {
+        let mut map = HashMap::new();
+        map.insert("name".to_string(), "Gio".to_string());
+        assert_eq!(Lookup::get(&map, "name"), Some(Cow::Borrowed("Gio")));
+        assert_eq!(Lookup::get(&map, "age"), None);
+    }
+
+    #[test]
+    fn lookup_btree_map() {
+        let mut map = BTreeMap::new();
+        map.insert("name", "Gio");
+        assert_eq!(Lookup::get(&map, "name"), Some(Cow::Borrowed("Gio")));
+        assert_eq!(Lookup::get(&map, "age"), None);
+    }
+
+    #[test]
+    fn lookup_pairs() {
+        let pairs = vec![("name", "Gio"), ("name", "Someone else")];
+        assert_eq!(Lookup::get(&pairs, "name"), Some(Cow::Borrowed("Gio")));
+        assert_eq!(Lookup::get(&pairs, "age"), None);
+    }
+
+    #[test]
+    fn lookup_parsed() {
+        let pairs = vec![("age", "30"), ("name", "Gio")];
+        assert_eq!(pairs.get_parsed::<u8>("age"), Ok(30));
+        assert_eq!(pairs.get_parsed::<u8>("name"), Err(LookupError::Invalid { name: "name".to_string(), value: "Gio".to_string() }));
+        assert_eq!(pairs.get_parsed::<u8>("height"), Err(LookupError::Missing { name: "height".to_string() }));
+        assert_eq!(String::from(pairs.get_parsed::<u8>("name").unwrap_err()), "The context's name isn't valid: Gio");
+    }
+
+    #[test]
+    fn render_with_context_lookup() {
+        let mut context = BTreeMap::new();
+        context.insert("greeting".to_string(), "Ignored".to_string());
+        context.insert("name".to_string(), "Gio".to_string());
+
+        let mut edo = match Edo::new("{greeting} {name}{missing}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("greeting", "Hello");
+        edo.use_context_lookup();
+        assert_eq!(
+            edo.render(&context),
+            "Hello Gio"
+        );
+    }
+
+    #[test]
+    fn lookup_context() {
+        let context = Context::new().set("a", 1.5).set("b", 'x').set("a", "replaced");
+        assert_eq!(context.get("a"), Some(Cow::Borrowed("replaced")));
+        assert_eq!(context.get("b"), Some(Cow::Borrowed("x")));
+        assert_eq!(context.get("c"), None);
+        assert_eq!(Context::new(), Context::default());
+    }
+
+    #[test]
+    fn lookup_layers() {
+        let base = vec![("a", "base"), ("b", "base")];
+        let scope = vec![("b", "scope")];
+        let mut layers = Layers::from(vec![&base, &scope]);
+        assert_eq!(layers.get("a"), Some(Cow::Borrowed("base")));
+        assert_eq!(layers.get("b"), Some(Cow::Borrowed("scope")));
+        assert_eq!(layers.get("c"), None);
+        layers.pop();
+        assert_eq!(layers.get("b"), Some(Cow::Borrowed("base")));
+        assert_eq!(Layers::<BTreeMap<&str, &str>>::new().get("a"), None);
+    }
+
+    #[test]
+    fn render_with_borrowed_context() {
+        // Not `Clone`, and dropped after each render while the template lives on
+        struct Site {
+            name: String,
+        }
+
+        let mut edo = match Edo::new("{name}-{name}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_handler("name", |_, site: &Site| Ok(site.name.clone()));
+        for name in &["Blog", "Shop"] {
+            let site = Site { name: name.to_string() };
+            assert_eq!(edo.render(&site), format!("{}-{}", name, name));
+        }
+    }
+
+    #[test]
+    fn render_with_mutable_context() {
+        let mut edo = match Edo::new("{count}{count}{name}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_handler("count", |_, counts: &Mut<BTreeMap<String, String>>| {
+            counts.with(|counts| {
+                let count = counts.get("count").map_or(0, |count| count.parse::<u32>().unwrap()) + 1;
+                counts.insert("count".to_string(), count.to_string());
+                Ok(count.to_string())
+            })
+        });
+        edo.use_context_lookup();
+
+        let mut counts = BTreeMap::new();
+        counts.insert("name".to_string(), "!".to_string());
+        assert_eq!(edo.render_mut(&mut counts), "12!");
+        assert_eq!(counts["count"], "2");
+        assert_eq!(edo.try_render_mut(&mut counts).map(|report| report.output), Ok("34!".to_string()));
+        assert_eq!(counts["count"], "4");
+    }
+
+    #[test]
+    fn lookup_context_bag() {
+        let mut bag = ContextBag::new();
+        bag.insert("a", 1u8);
+        bag.insert("b", "two");
+        bag.insert("c", "three".to_string());
+        assert_eq!(bag.get::<u8>("a"), Some(&1));
+        assert_eq!(bag.get::<u16>("a"), None);
+        assert_eq!(bag.get::<u8>("d"), None);
+        assert_eq!(Lookup::get(&bag, "a"), None);
+        assert_eq!(Lookup::get(&bag, "b"), Some(Cow::Borrowed("two")));
+        assert_eq!(Lookup::get(&bag, "c"), Some(Cow::Borrowed("three")));
+
+        let copy = bag.clone();
+        bag.insert("a", 2u8);
+        assert_eq!(copy.get::<u8>("a"), Some(&1));
+        assert_eq!(format!("{:?}", bag), r#"{"a", "b", "c"}"#);
+    }
+}
@@ -0,0 +1,412 @@
+//! Expanding templates while deserializing
+//!
+//! Wrapping a deserializer renders every string value it produces through an `Edo`'s producers before it reaches the type being deserialized. This works with any serde format, so values in YAML, TOML or JSON config files can use placeholders. Struct field names, map keys and enum variant names are left alone.
+//!
+//! # Examples
+//! ```
+//! # extern crate edo;
+//! # extern crate serde;
+//! # extern crate serde_json;
+//! # use serde::Deserialize;
+//! use edo::Edo;
+//!
+//! #[derive(Deserialize)]
+//! struct Config {
+//!     data_dir: String,
+//!     log_file: String,
+//! }
+//!
+//! # fn main() {
+//! let mut edo: Edo<()> = Edo::new("").unwrap();
+//! edo.register_static("home", "/home/gio");
+//! edo.register_handler("join", |args, _| Ok(args.join("/")));
+//!
+//! let mut json = serde_json::Deserializer::from_str(r#"{
+//!     "data_dir": "{home}/data",
+//!     "log_file": "{home}/{join(logs, edo)}.log"
+//! }"#);
+//! let config: Config = edo::de::deserialize(&mut json, &edo, &()).unwrap();
+//! assert_eq!(config.data_dir, "/home/gio/data");
+//! assert_eq!(config.log_file, "/home/gio/logs/edo.log");
+//! # }
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::de::{self, Deserialize, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
+
+use escape;
+use parse;
+use Edo;
+
+/// Deserialize a value, rendering every string in it as a template
+///
+/// Fails if a string is not a valid template or one of its handlers returns an error.
+pub fn deserialize<'de, 'e, 'a, T, D, C>(deserializer: D, edo: &'e Edo<'a, C>, context: &'e C) -> Result<T, D::Error> where
+    T: Deserialize<'de>,
+    D: de::Deserializer<'de>,
+    C: ?Sized {
+    T::deserialize(Deserializer::new(deserializer, edo, context))
+}
+
+/// Renders strings using a template's producers and a context
+struct Expander<'e, 'a: 'e, C: 'e + ?Sized> {
+    edo: &'e Edo<'a, C>,
+    context: &'e C,
+}
+
+impl<'e, 'a, C: ?Sized> Clone for Expander<'e, 'a, C> {
+    fn clone(&self) -> Self {
+        Expander { edo: self.edo, context: self.context }
+    }
+}
+
+impl<'e, 'a, C: ?Sized> Expander<'e, 'a, C> {
+    fn expand<E: de::Error>(&self, source: &str) -> Result<String, E> {
+        let template = parse::parse(source)
+            .map_err(|error| E::custom(format_args!("invalid template {:?}: {}", source, error)))?;
+        let report = self.edo.render_parsed(&template, &escape::html_contexts(&template), self.context)
+            .map_err(|error| E::custom(format_args!("failed to render {:?}: {}", source, error)))?;
+        match report.errors.into_iter().next() {
+            Some(error) => Err(E::custom(format_args!("failed to render {:?}: {}", source, error))),
+            None => Ok(report.output),
+        }
+    }
+}
+
+/// A deserializer adapter that renders every string value as a template
+pub struct Deserializer<'e, 'a: 'e, D, C: 'e + ?Sized> {
+    inner: D,
+    expander: Expander<'e, 'a, C>,
+}
+
+impl<'e, 'a, D, C: ?Sized> Deserializer<'e, 'a, D, C> {
+    /// Wrap a deserializer, rendering strings with the producers registered on `edo`
+    pub fn new(inner: D, edo: &'e Edo<'a, C>, context: &'e C) -> Self {
+        Deserializer {
+            inner,
+            expander: Expander { edo, context },
+        }
+    }
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, D::Error> {
+                self.inner.$method($($arg,)* Expand { inner: visitor, expander: self.expander })
+            }
+        )*
+    };
+}
+
+impl<'de, 'e, 'a, D, C> de::Deserializer<'de> for Deserializer<'e, 'a, D, C> where
+    D: de::Deserializer<'de>,
+    C: ?Sized {
+    type Error = D::Error;
+
+    forward_deserialize! {
+        deserialize_any();
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_i128();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_u128();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+    }
+
+    // Identifiers name fields and variants, they are never templates
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+        self.inner.deserialize_identifier(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+        self.inner.deserialize_ignored_any(visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+/// Wraps visitors, seeds and accessors so nested values are expanded too
+struct Expand<'e, 'a: 'e, T, C: 'e + ?Sized> {
+    inner: T,
+    expander: Expander<'e, 'a, C>,
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty);)*) => {
+        $(
+            fn $method<E: de::Error>(self, value: $ty) -> Result<V::Value, E> {
+                self.inner.$method(value)
+            }
+        )*
+    };
+}
+
+impl<'de, 'e, 'a, V, C> Visitor<'de> for Expand<'e, 'a, V, C> where
+    V: Visitor<'de>,
+    C: ?Sized {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.expecting(formatter)
+    }
+
+    forward_visit! {
+        visit_bool(bool);
+        visit_i8(i8);
+        visit_i16(i16);
+        visit_i32(i32);
+        visit_i64(i64);
+        visit_i128(i128);
+        visit_u8(u8);
+        visit_u16(u16);
+        visit_u32(u32);
+        visit_u64(u64);
+        visit_u128(u128);
+        visit_f32(f32);
+        visit_f64(f64);
+        visit_char(char);
+        visit_bytes(&[u8]);
+        visit_borrowed_bytes(&'de [u8]);
+        visit_byte_buf(Vec<u8>);
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<V::Value, E> {
+        let expanded = self.expander.expand(value)?;
+        self.inner.visit_string(expanded)
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, value: &'de str) -> Result<V::Value, E> {
+        let expanded = self.expander.expand(value)?;
+        // Keep borrowing from the input when there was nothing to expand
+        if expanded == value {
+            self.inner.visit_borrowed_str(value)
+        } else {
+            self.inner.visit_string(expanded)
+        }
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<V::Value, E> {
+        let expanded = self.expander.expand(&value)?;
+        self.inner.visit_string(expanded)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<V::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<V::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<V::Value, D::Error> {
+        self.inner.visit_some(Deserializer { inner: deserializer, expander: self.expander })
+    }
+
+    fn visit_newtype_struct<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<V::Value, D::Error> {
+        self.inner.visit_newtype_struct(Deserializer { inner: deserializer, expander: self.expander })
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<V::Value, A::Error> {
+        self.inner.visit_seq(Expand { inner: seq, expander: self.expander })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<V::Value, A::Error> {
+        self.inner.visit_map(Expand { inner: map, expander: self.expander })
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<V::Value, A::Error> {
+        self.inner.visit_enum(Expand { inner: data, expander: self.expander })
+    }
+}
+
+impl<'de, 'e, 'a, S, C> DeserializeSeed<'de> for Expand<'e, 'a, S, C> where
+    S: DeserializeSeed<'de>,
+    C: ?Sized {
+    type Value = S::Value;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<S::Value, D::Error> {
+        self.inner.deserialize(Deserializer { inner: deserializer, expander: self.expander })
+    }
+}
+
+impl<'de, 'e, 'a, A, C> SeqAccess<'de> for Expand<'e, 'a, A, C> where
+    A: SeqAccess<'de>,
+    C: ?Sized {
+    type Error = A::Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<Option<S::Value>, A::Error> {
+        self.inner.next_element_seed(Expand { inner: seed, expander: self.expander.clone() })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, 'e, 'a, A, C> MapAccess<'de> for Expand<'e, 'a, A, C> where
+    A: MapAccess<'de>,
+    C: ?Sized {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, A::Error> {
+        self.inner.next_key_seed(seed)
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, A::Error> {
+        self.inner.next_value_seed(Expand { inner: seed, expander: self.expander.clone() })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, 'e, 'a, A, C> EnumAccess<'de> for Expand<'e, 'a, A, C> where
+    A: EnumAccess<'de>,
+    C: ?Sized {
+    type Error = A::Error;
+    type Variant = Expand<'e, 'a, A::Variant, C>;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<(S::Value, Self::Variant), A::Error> {
+        let expander = self.expander;
+        let (value, variant) = self.inner.variant_seed(seed)?;
+        Ok((value, Expand { inner: variant, expander }))
+    }
+}
+
+impl<'de, 'e, 'a, A, C> VariantAccess<'de> for Expand<'e, 'a, A, C> where
+    A: VariantAccess<'de>,
+    C: ?Sized {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), A::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, A::Error> {
+        self.inner.newtype_variant_seed(Expand { inner: seed, expander: self.expander })
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, A::Error> {
+        self.inner.tuple_variant(len, Expand { inner: visitor, expander: self.expander })
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, A::Error> {
+        self.inner.struct_variant(fields, Expand { inner: visitor, expander: self.expander })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::BTreeMap;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+
+    use serde::Deserialize;
+    use serde_json;
+
+    use super::deserialize;
+    use Edo;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Backend {
+        Disk(String),
+        Memory,
+        Remote { url: String },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        name: String,
+        port: u16,
+        paths: Vec<String>,
+        backup: Option<String>,
+        labels: BTreeMap<String, String>,
+        backends: Vec<Backend>,
+    }
+
+    fn edo() -> Edo<'static, str> {
+        let mut edo = match Edo::new("") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("home", "/home/gio");
+        edo.register_handler("env", |_, context: &str| Ok(context.to_string()));
+        edo.register_handler("fail", |_, _| Err("BORK".to_string()));
+        edo
+    }
+
+    fn from_json<'de, T: Deserialize<'de>>(json: &'de str) -> Result<T, serde_json::Error> {
+        deserialize(&mut serde_json::Deserializer::from_str(json), &edo(), "prod")
+    }
+
+    #[test]
+    fn expand_nested_strings() {
+        let config: Config = from_json(r#"{
+            "name": "app-{env}",
+            "port": 8080,
+            "paths": ["{home}/bin", "/usr/bin"],
+            "backup": "{home}/backup",
+            "labels": { "{home}": "{env}" },
+            "backends": [{ "Disk": "{home}/data" }, "Memory", { "Remote": { "url": "https://{env}.example.com" } }]
+        }"#).unwrap();
+        assert_eq!(config, Config {
+            name: "app-prod".to_string(),
+            port: 8080,
+            paths: vec!["/home/gio/bin".to_string(), "/usr/bin".to_string()],
+            backup: Some("/home/gio/backup".to_string()),
+            labels: vec![("{home}".to_string(), "prod".to_string())].into_iter().collect(),
+            backends: vec![
+                Backend::Disk("/home/gio/data".to_string()),
+                Backend::Memory,
+                Backend::Remote { url: "https://prod.example.com".to_string() },
+            ],
+        });
+    }
+
+    #[test]
+    fn borrowed_strings_without_placeholders() {
+        let paths: Vec<&str> = from_json(r#"["/usr/bin", "/bin"]"#).unwrap();
+        assert_eq!(paths, vec!["/usr/bin", "/bin"]);
+    }
+
+    #[test]
+    fn invalid_template_is_an_error() {
+        let error = from_json::<String>(r#""{home""#).unwrap_err();
+        assert!(error.to_string().starts_with("invalid template \"{home\": Unterminated placeholder"));
+    }
+
+    #[test]
+    fn handler_error_is_an_error() {
+        let error = from_json::<Vec<String>>(r#"["{home}", "{fail}"]"#).unwrap_err();
+        assert!(error.to_string().starts_with("failed to render \"{fail}\": BORK"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Readable error messages that point at the template
+//!
+//! A [`Diagnostic`](struct.Diagnostic.html) prints the line of the template an error comes from, with carets under the placeholder:
+//!
+//! ```text
+//! error[E0103]: Unknown filter nope
+//!  --> 1:7
+//!   |
+//! 1 | Hello {name|nope}
+//!   |       ^^^^^^^^^^^
+//! ```
+
+use alloc::string::{String, ToString};
+use core::fmt;
+
+use error::{EdoError, RenderError};
+use parse::Span;
+
+/// An error message tied to a span of a template's source
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::diagnostic::Diagnostic;
+///
+/// let source = "Hello {name(a}";
+/// let error = Edo::<()>::new(source).err().unwrap();
+/// let diagnostic = Diagnostic::from_parse_error(source, &error);
+/// assert_eq!(diagnostic.to_string(), "\
+/// error[E0002]: Invalid argument list, expected arguments separated by commas and a closing )
+///  --> 1:7
+///   |
+/// 1 | Hello {name(a}
+///   |       ^^^^^^^^
+/// ");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic<'a> {
+    source: &'a str,
+    span: Span,
+    code: Option<&'static str>,
+    message: String,
+}
+
+impl<'a> Diagnostic<'a> {
+    /// Point at `span` of `source` with a message
+    pub fn new<M: Into<String>>(source: &'a str, span: Span, message: M) -> Diagnostic<'a> {
+        Diagnostic { source, span, code: None, message: message.into() }
+    }
+
+    /// Show an error code next to the message
+    pub fn with_code(mut self, code: &'static str) -> Diagnostic<'a> {
+        self.code = Some(code);
+        self
+    }
+
+    /// Point at the placeholder in `source` that couldn't be parsed, `source` must be the template that `error` came from
+    pub fn from_parse_error(source: &'a str, error: &EdoError) -> Diagnostic<'a> {
+        Diagnostic::new(source, error.span().unwrap_or_default(), error.to_string()).with_code(error.code())
+    }
+
+    /// Point at the placeholder in `source` that caused a render error
+    ///
+    /// Returns `None` for errors that aren't about a single placeholder, such as [`RenderError::OutputTooLarge`](../error/enum.RenderError.html#variant.OutputTooLarge). Errors from inside a nested template have spans into that template, so pass its source instead.
+    pub fn from_render_error(source: &'a str, error: &RenderError) -> Option<Diagnostic<'a>> {
+        error.span().map(|span| Diagnostic::new(source, span, error.to_string()).with_code(error.code()))
+    }
+
+    /// The span being pointed at
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The error code, if there is one
+    pub fn code(&self) -> Option<&'static str> {
+        self.code
+    }
+
+    /// The error message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl<'a> fmt::Display for Diagnostic<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Clamp the span to the source, on character boundaries
+        let mut start = self.span.start.min(self.source.len());
+        while !self.source.is_char_boundary(start) {
+            start -= 1;
+        }
+        let line_start = self.source[..start].rfind('\n').map_or(0, |index| index + 1);
+        let line_end = self.source[start..].find('\n').map_or(self.source.len(), |index| start + index);
+        let line = &self.source[line_start..line_end];
+        let line_number = self.source[..start].matches('\n').count() + 1;
+
+        // Columns and carets count characters, so multibyte text lines up
+        let column = self.source[line_start..start].chars().count();
+        let end = self.span.end.max(start).min(line_end);
+        let carets = self.source.get(start..end).map_or(0, |text| text.chars().count()).max(1);
+
+        let width = line_number.to_string().len();
+        match self.code {
+            Some(code) => writeln!(f, "error[{}]: {}", code, self.message)?,
+            None => writeln!(f, "error: {}", self.message)?,
+        }
+        writeln!(f, "{:width$}--> {}:{}", "", line_number, column + 1, width = width)?;
+        writeln!(f, "{:width$} |", "", width = width)?;
+        writeln!(f, "{} | {}", line_number, line)?;
+        writeln!(f, "{:width$} | {:column$}{}", "", "", "^".repeat(carets), width = width, column = column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use error::RenderError;
+    use parse::Span;
+    use super::Diagnostic;
+
+    #[test]
+    fn point_at_a_later_line() {
+        let source = "first\nsecond {name|nope} line\nthird";
+        let error = RenderError::UnknownFilter { name: "nope".to_string(), span: Span { start: 13, end: 24 } };
+        let diagnostic = Diagnostic::from_render_error(source, &error).unwrap();
+        assert_eq!(
+            diagnostic.to_string(),
+            "error[E0103]: Unknown filter nope\n --> 2:8\n  |\n2 | second {name|nope} line\n  |        ^^^^^^^^^^^\n"
+        );
+    }
+
+    #[test]
+    fn point_at_multibyte_text() {
+        let diagnostic = Diagnostic::new("héllo {x}", Span { start: 7, end: 10 }, "oops");
+        assert_eq!(diagnostic.to_string(), "error: oops\n --> 1:7\n  |\n1 | héllo {x}\n  |       ^^^\n");
+    }
+
+    #[test]
+    fn point_past_the_end() {
+        let diagnostic = Diagnostic::new("abc", Span { start: 3, end: 3 }, "oops");
+        assert_eq!(diagnostic.to_string(), "error: oops\n --> 1:4\n  |\n1 | abc\n  |    ^\n");
+        assert!(Diagnostic::from_render_error("abc", &RenderError::OutputTooLarge { limit: 1 }).is_none());
+    }
+}
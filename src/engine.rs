@@ -0,0 +1,777 @@
+//! Render templates by name
+//!
+//! An [`Engine`](struct.Engine.html) loads templates from a [`Loader`](../loader/trait.Loader.html) the first time they are rendered, and keeps them parsed for the next render. A template can render another one from the loader in its place with `{include(name)}`. It can also render template strings directly, which are kept parsed too. Parsed templates are kept in a bounded cache, dropping the least recently used ones first. Every template is rendered with the same handlers, statics, filters and options, registered once on the engine's [`registry`](struct.Engine.html#method.registry).
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use args::Args;
+#[cfg(feature = "std")]
+use bundle;
+use error::{EdoError, EngineError};
+use loader::Loader;
+#[cfg(feature = "std")]
+use loader::MemoryLoader;
+use parse::{self, Case, Expression, Span};
+use report::RenderReport;
+use {Edo, Partials, ValueProducer};
+#[cfg(feature = "std")]
+use std::{fs, path::Path};
+
+// A parsed template that owns its source
+//
+// The expressions are kept as ranges of the source and made again on each access, so the template doesn't borrow from itself
+pub(crate) struct Compiled {
+    source: Box<str>,
+    pieces: Vec<Piece>,
+}
+
+// An expression whose text is kept as ranges of its template's source
+enum Piece {
+    Literal(Range<usize>),
+    Function {
+        name: Range<usize>,
+        arguments: Vec<Range<usize>>,
+        argument_spans: Vec<Span>,
+        has_parentheses: bool,
+        case: Option<Case>,
+        filters: Vec<Range<usize>>,
+        format: Option<Range<usize>>,
+        raw: bool,
+        span: Span,
+    },
+}
+
+impl Compiled {
+    pub(crate) fn new(source: String) -> Result<Compiled, EdoError> {
+        Compiled::build(source, parse::parse)
+    }
+
+    // Parse `source` with `parse`, which can only borrow the expressions from the text it's given
+    pub(crate) fn build<E, F>(mut source: String, parse: F) -> Result<Compiled, E> where
+        F: for<'s> FnOnce(&'s str) -> Result<Vec<Expression<'s>>, E> {
+        let (pieces, copied) = pieces(&source, &parse(&source)?);
+        source.push_str(&copied);
+        Ok(Compiled { source: source.into(), pieces })
+    }
+
+    // Copy `expressions` and the text they borrow, so the copy borrows from nothing else
+    pub(crate) fn copy(expressions: &[Expression]) -> Compiled {
+        let (pieces, source) = pieces("", expressions);
+        Compiled { source: source.into(), pieces }
+    }
+
+    pub(crate) fn source(&self) -> &str {
+        &self.source
+    }
+
+    // The expressions, borrowing their text from the source
+    pub(crate) fn expressions(&self) -> Vec<Expression<'_>> {
+        let text = |range: &Range<usize>| &self.source[range.clone()];
+        self.pieces.iter().map(|piece| match *piece {
+            Piece::Literal(ref range) => Expression::Literal(text(range)),
+            Piece::Function { ref name, ref arguments, ref argument_spans, has_parentheses, case, ref filters, ref format, raw, span } => Expression::Function {
+                name: text(name),
+                arguments: arguments.iter().map(text).collect(),
+                argument_spans: argument_spans.clone(),
+                has_parentheses,
+                case,
+                filters: filters.iter().map(text).collect(),
+                format: format.as_ref().map(text),
+                raw,
+                span,
+            },
+        }).collect()
+    }
+}
+
+// `expressions` as ranges of `source`, and the text of theirs that isn't part of it, which goes after its end
+fn pieces(source: &str, expressions: &[Expression]) -> (Vec<Piece>, String) {
+    let mut copied = String::new();
+    let mut range = |text: &str| {
+        let offset = (text.as_ptr() as usize).wrapping_sub(source.as_ptr() as usize);
+        if offset <= source.len() && text.len() <= source.len() - offset {
+            return offset..offset + text.len();
+        }
+        let start = source.len() + copied.len();
+        copied.push_str(text);
+        start..start + text.len()
+    };
+    let pieces = expressions.iter().map(|expression| match *expression {
+        Expression::Literal(text) => Piece::Literal(range(text)),
+        Expression::Function { name, ref arguments, ref argument_spans, has_parentheses, case, ref filters, format, raw, span } => Piece::Function {
+            name: range(name),
+            arguments: arguments.iter().map(|argument| range(argument)).collect(),
+            argument_spans: argument_spans.clone(),
+            has_parentheses,
+            case,
+            filters: filters.iter().map(|filter| range(filter)).collect(),
+            format: format.map(&mut range),
+            raw,
+            span,
+        },
+    }).collect();
+    (pieces, copied)
+}
+
+// What a parsed template is cached under
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Key {
+    Name(String),
+    // The hash of a template string, which is checked against the source on a hit
+    Source(u64),
+}
+
+struct Entry {
+    // Shared with the renders using it, so it can be evicted while an include is loaded
+    compiled: Rc<Compiled>,
+    used: u64,
+}
+
+/// How well the cache of parsed templates is doing, see [`Engine::cache_stats`](struct.Engine.html#method.cache_stats)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    /// Templates, rendered or included, that were found already parsed
+    pub hits: u64,
+    /// Templates that had to be loaded or parsed
+    pub misses: u64,
+    /// Templates dropped to stay within the capacity
+    pub evictions: u64,
+    /// How many templates are parsed right now
+    pub len: usize,
+    /// The most templates that are kept parsed
+    pub capacity: usize,
+}
+
+// A least recently used cache of parsed templates
+struct Cache {
+    entries: BTreeMap<Key, Entry>,
+    // Keys by when they were last used, oldest first
+    recency: BTreeMap<u64, Key>,
+    clock: u64,
+    stats: CacheStats,
+}
+
+impl Cache {
+    fn new(capacity: usize) -> Cache {
+        Cache {
+            entries: BTreeMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+            stats: CacheStats { capacity, ..CacheStats::default() },
+        }
+    }
+
+    // The template cached under `key`, or the one `load` returns, which is then cached
+    fn get_or_load<F>(&mut self, key: Key, source: Option<&str>, load: F) -> Result<Rc<Compiled>, EngineError> where
+        F: FnOnce() -> Result<Compiled, EngineError> {
+        let hit = self.entries.get(&key).is_some_and(|entry| source.is_none_or(|source| *entry.compiled.source == *source));
+        if !hit {
+            self.stats.misses += 1;
+            let compiled = Rc::new(load()?);
+            self.insert(key, compiled.clone());
+            return Ok(compiled);
+        }
+
+        self.stats.hits += 1;
+        self.clock += 1;
+        let entry = self.entries.get_mut(&key).expect("the entry was just found");
+        self.recency.remove(&entry.used);
+        entry.used = self.clock;
+        self.recency.insert(self.clock, key);
+        Ok(entry.compiled.clone())
+    }
+
+    fn insert(&mut self, key: Key, compiled: Rc<Compiled>) {
+        self.clock += 1;
+        if let Some(old) = self.entries.insert(key.clone(), Entry { compiled, used: self.clock }) {
+            self.recency.remove(&old.used);
+        }
+        self.recency.insert(self.clock, key);
+        self.shrink();
+    }
+
+    // Drop the least recently used templates until the cache fits, always keeping the newest one
+    fn shrink(&mut self) {
+        while self.entries.len() > self.stats.capacity.max(1) {
+            let oldest = match self.recency.keys().next() {
+                Some(&oldest) => oldest,
+                None => break,
+            };
+            if let Some(key) = self.recency.remove(&oldest) {
+                self.entries.remove(&key);
+                self.stats.evictions += 1;
+            }
+        }
+        self.stats.len = self.entries.len();
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+        self.stats.len = 0;
+    }
+
+    // Forget the templates that came from the loader
+    fn clear_named(&mut self) {
+        let entries = &mut self.entries;
+        self.recency.retain(|_, key| match *key {
+            Key::Name(_) => {
+                entries.remove(key);
+                false
+            },
+            Key::Source(_) => true,
+        });
+        self.stats.len = self.entries.len();
+    }
+}
+
+// The names of the templates `template` includes
+fn includes<'t>(template: &'t [Expression<'t>]) -> impl Iterator<Item = &'t str> {
+    template.iter().filter_map(|expression| match *expression {
+        Expression::Function { name: "include", ref arguments, .. } if arguments.len() == 1 => Some(arguments[0]),
+        _ => None,
+    })
+}
+
+// FNV-1a, which is enough to tell template strings apart since hits are checked against the source
+fn hash(source: &str) -> u64 {
+    source.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Producers for a single [`Engine::render_with`](struct.Engine.html#method.render_with), such as the values of one request
+///
+/// They are looked up before the engine's registry, so they can also replace its producers for that render.
+pub struct Producers<C: ?Sized> {
+    producers: BTreeMap<String, ValueProducer<'static, C>>,
+}
+
+impl<C: ?Sized> Producers<C> {
+    /// An empty set
+    pub fn new() -> Producers<C> {
+        Producers { producers: BTreeMap::new() }
+    }
+
+    /// Add a handler, which gets the arguments of the placeholder and the context
+    pub fn handler<F>(mut self, name: &str, handler: F) -> Producers<C> where
+        F: 'static + Fn(Args, &C) -> Result<String, String> {
+        self.producers.insert(name.to_string(), ValueProducer::Handler(Box::new(handler), None));
+        self
+    }
+
+    /// Add a static replacement
+    pub fn value<S: Into<String>>(mut self, name: &str, value: S) -> Producers<C> {
+        self.producers.insert(name.to_string(), ValueProducer::Static(value.into()));
+        self
+    }
+}
+
+impl<C: ?Sized> Default for Producers<C> {
+    fn default() -> Producers<C> {
+        Producers::new()
+    }
+}
+
+/// Named templates sharing one set of producers
+///
+/// # Examples
+/// ```
+/// use edo::engine::Engine;
+/// use edo::loader::Loader;
+///
+/// struct Greetings;
+///
+/// impl Loader for Greetings {
+///     fn load(&self, name: &str) -> Result<Option<String>, String> {
+///         Ok(match name {
+///             "hello" => Some("Hello {name}".to_string()),
+///             "bye" => Some("Bye {name}".to_string()),
+///             _ => None,
+///         })
+///     }
+/// }
+///
+/// let mut engine = Engine::new(Greetings);
+/// engine.registry().register_handler("name", |_, name: &str| Ok(name.to_string()));
+/// assert_eq!(engine.render("hello", "Gio").unwrap().output, "Hello Gio");
+/// assert_eq!(engine.render("bye", "Gio").unwrap().output, "Bye Gio");
+/// assert_eq!(engine.render("nope", "Gio").unwrap_err().to_string(), "No template named nope");
+/// ```
+pub struct Engine<C: ?Sized> {
+    registry: Edo<'static, C>,
+    loader: Box<dyn Loader>,
+    cache: Cache,
+    generation: u64,
+}
+
+impl<C: ?Sized> Engine<C> {
+    /// An engine with no producers, loading templates from `loader` and keeping up to 256 of them parsed
+    pub fn new<L: 'static + Loader>(loader: L) -> Engine<C> {
+        Engine {
+            registry: Edo::from_expressions(vec![]),
+            loader: Box::new(loader),
+            cache: Cache::new(256),
+            generation: 0,
+        }
+    }
+
+    /// The template whose producers, filters, escaping and options every template is rendered with
+    ///
+    /// Its own template is empty, only register things on it.
+    pub fn registry(&mut self) -> &mut Edo<'static, C> {
+        &mut self.registry
+    }
+
+    /// Render the template called `name`, loading it if it hasn't been yet
+    ///
+    /// `{include(other)}` renders the template called `other` in its place, unescaped. Included templates are loaded before the render starts, so a missing one fails the render with [`EngineError::NotFound`](../error/enum.EngineError.html#variant.NotFound). A template including itself, directly or through others, fails with [`RenderError::IncludeCycle`](../error/enum.RenderError.html#variant.IncludeCycle).
+    ///
+    /// # Examples
+    /// ```
+    /// use edo::engine::Engine;
+    /// use edo::loader::MemoryLoader;
+    ///
+    /// let loader = MemoryLoader::new()
+    ///     .template("page", "{include(header)} {body}")
+    ///     .template("header", "# {title}");
+    /// let mut engine: Engine<()> = Engine::new(loader);
+    /// engine.registry().register_static("title", "Edo");
+    /// engine.registry().register_static("body", "Templates");
+    /// assert_eq!(engine.render("page", &()).unwrap().output, "# Edo Templates");
+    /// ```
+    pub fn render(&mut self, name: &str, context: &C) -> Result<RenderReport, EngineError> {
+        self.render_named(name, None, None, context)
+    }
+
+    /// Render the template called `name` like [`render`](#method.render), with `producers` added for this render only
+    ///
+    /// # Examples
+    /// ```
+    /// use edo::engine::{Engine, Producers};
+    /// use edo::loader::MemoryLoader;
+    ///
+    /// let mut engine: Engine<()> = Engine::new(MemoryLoader::new().template("invoice", "{total} for {customer}, {company}"));
+    /// engine.registry().register_static("company", "Edo Inc.");
+    /// engine.registry().register_static("customer", "nobody");
+    ///
+    /// let request = Producers::new()
+    ///     .value("customer", "Gio")
+    ///     .handler("total", |_, _| Ok("$12".to_string()));
+    /// assert_eq!(engine.render_with("invoice", &(), &request).unwrap().output, "$12 for Gio, Edo Inc.");
+    /// ```
+    pub fn render_with(&mut self, name: &str, context: &C, producers: &Producers<C>) -> Result<RenderReport, EngineError> {
+        self.render_named(name, Some(producers), None, context)
+    }
+
+    /// Render the template called `name` like [`render`](#method.render), with builtins and localized handlers using `locale`
+    ///
+    /// Needs the `locale` feature. This overrides the locale in the registry's options, so one registry serves every locale, see [`Edo::render_in`](../struct.Edo.html#method.render_in).
+    ///
+    /// # Examples
+    /// ```
+    /// use edo::builtins;
+    /// use edo::engine::Engine;
+    /// use edo::loader::MemoryLoader;
+    ///
+    /// let mut engine: Engine<()> = Engine::new(MemoryLoader::new().template("total", "{num(1234.5, 2)}"));
+    /// engine.registry().register_builtins(builtins::numbers());
+    /// assert_eq!(engine.render_in("de", "total", &()).unwrap().output, "1.234,50");
+    /// assert_eq!(engine.render("total", &()).unwrap().output, "1,234.50");
+    /// ```
+    #[cfg(feature = "locale")]
+    pub fn render_in(&mut self, locale: &str, name: &str, context: &C) -> Result<RenderReport, EngineError> {
+        self.render_named(name, None, Some(locale), context)
+    }
+
+    /// Render the best variant of the template called `name` for `locale`, like [`render_in`](#method.render_in)
+    ///
+    /// Needs the `locale` feature. Variants are templates named after the locale, like `welcome.de` or `welcome.pt-BR`. The locales of the registry's [`LocaleChain`](../catalog/struct.LocaleChain.html) are tried in turn, and the template called `name` is rendered when none of them has a variant.
+    ///
+    /// # Examples
+    /// ```
+    /// use edo::engine::Engine;
+    /// use edo::loader::MemoryLoader;
+    ///
+    /// let loader = MemoryLoader::new()
+    ///     .template("welcome", "Welcome {name}")
+    ///     .template("welcome.pt", "Bem-vindo {name}");
+    /// let mut engine: Engine<()> = Engine::new(loader);
+    /// engine.registry().register_static("name", "Gio");
+    /// assert_eq!(engine.render_localized("welcome", "pt-BR", &()).unwrap().output, "Bem-vindo Gio");
+    /// assert_eq!(engine.render_localized("welcome", "de", &()).unwrap().output, "Welcome Gio");
+    /// ```
+    #[cfg(feature = "locale")]
+    pub fn render_localized(&mut self, name: &str, locale: &str, context: &C) -> Result<RenderReport, EngineError> {
+        for variant in self.registry.options.get_locale_chain().resolve(locale) {
+            let variant = format!("{}.{}", name, variant);
+            match self.render_named(&variant, None, Some(locale), context) {
+                Err(EngineError::NotFound { name: ref missing }) if *missing == variant => continue,
+                result => return result,
+            }
+        }
+        self.render_named(name, None, Some(locale), context)
+    }
+
+    fn render_named(&mut self, name: &str, producers: Option<&Producers<C>>, locale: Option<&str>, context: &C) -> Result<RenderReport, EngineError> {
+        self.check_generation();
+        let compiled = self.load(name)?;
+        self.render_compiled(Some(name), &compiled, producers, locale, context)
+    }
+
+    /// Render a template string, which is kept parsed for the next time it's rendered
+    ///
+    /// Use this for templates that don't come from the loader, like ones supplied by users. Parse errors have an empty name.
+    ///
+    /// # Examples
+    /// ```
+    /// use edo::engine::Engine;
+    /// use edo::loader::MemoryLoader;
+    ///
+    /// let mut engine: Engine<()> = Engine::new(MemoryLoader::new());
+    /// engine.registry().register_static("name", "Gio");
+    /// engine.set_cache_capacity(1);
+    /// assert_eq!(engine.render_source("Hi {name}", &()).unwrap().output, "Hi Gio");
+    /// assert_eq!(engine.render_source("Hi {name}", &()).unwrap().output, "Hi Gio");
+    /// assert_eq!(engine.render_source("Bye {name}", &()).unwrap().output, "Bye Gio");
+    ///
+    /// let stats = engine.cache_stats();
+    /// assert_eq!((stats.hits, stats.misses, stats.evictions, stats.len), (1, 2, 1, 1));
+    /// ```
+    pub fn render_source(&mut self, source: &str, context: &C) -> Result<RenderReport, EngineError> {
+        let compiled = self.cache.get_or_load(Key::Source(hash(source)), Some(source), || {
+            Compiled::new(source.to_string()).map_err(|error| EngineError::Parse { name: String::new(), error })
+        })?;
+        self.render_compiled(None, &compiled, None, None, context)
+    }
+
+    // Drop the loaded templates if the loader's have changed since
+    fn check_generation(&mut self) {
+        let generation = self.loader.generation();
+        if generation != self.generation {
+            self.cache.clear_named();
+            self.generation = generation;
+        }
+    }
+
+    // The template called `name`, parsed
+    fn load(&mut self, name: &str) -> Result<Rc<Compiled>, EngineError> {
+        let loader = &self.loader;
+        self.cache.get_or_load(Key::Name(name.to_string()), None, || {
+            let source = match loader.load(name) {
+                Ok(Some(source)) => source,
+                Ok(None) => return Err(EngineError::NotFound { name: name.to_string() }),
+                Err(message) => return Err(EngineError::LoadFailed { name: name.to_string(), message }),
+            };
+            Compiled::new(source).map_err(|error| EngineError::Parse { name: name.to_string(), error })
+        })
+    }
+
+    // Load every template `compiled` includes, directly or not, then render it
+    fn render_compiled(&mut self, name: Option<&str>, compiled: &Compiled, producers: Option<&Producers<C>>, locale: Option<&str>, context: &C) -> Result<RenderReport, EngineError> {
+        let template = compiled.expressions();
+        let mut loaded = BTreeMap::new();
+        let mut pending: Vec<String> = includes(&template).map(str::to_string).collect();
+        while let Some(included) = pending.pop() {
+            if Some(included.as_str()) == name || loaded.contains_key(&included) {
+                continue;
+            }
+            let partial = self.load(&included)?;
+            pending.extend(includes(&partial.expressions()).map(str::to_string));
+            loaded.insert(included, partial);
+        }
+
+        let expressions: BTreeMap<&str, Vec<Expression>> = loaded.iter().map(|(included, partial)| (included.as_str(), partial.expressions())).collect();
+        let mut partials: Partials = expressions.iter().map(|(included, partial)| (included.to_string(), &partial[..])).collect();
+        if let Some(name) = name {
+            partials.insert(name.to_string(), &template);
+        }
+        let producers = producers.map(|producers| &producers.producers);
+        Ok(self.registry.render_with_partials(name, &template, &partials, producers, locale, context)?)
+    }
+
+    /// The loader's generation when templates were last loaded, which goes up each time they are reloaded
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Forget the parsed templates, so they are parsed again on their next render
+    pub fn reload(&mut self) {
+        self.cache.clear();
+    }
+
+    /// Keep at most `capacity` templates parsed, dropping the least recently used ones first
+    ///
+    /// The template being rendered is always kept, so a capacity of 0 acts like 1.
+    pub fn set_cache_capacity(&mut self, capacity: usize) {
+        self.cache.stats.capacity = capacity;
+        self.cache.shrink();
+    }
+
+    /// Hit, miss and eviction counts of the cache of parsed templates
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.stats
+    }
+
+    /// Write the templates called `names` to a single bundle file, along with the templates they include
+    ///
+    /// Templates are loaded from the loader, or taken from the cache when they are already parsed. A template that can't be loaded or parsed fails the whole bundle, nothing is written then. The bundle has each template's source and its parsed form, so [`load_bundle`](#method.load_bundle) doesn't parse them again. Needs the `std` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// # #[cfg(feature = "std")] {
+    /// use edo::engine::Engine;
+    /// use edo::loader::MemoryLoader;
+    ///
+    /// let path = std::env::temp_dir().join(format!("edo-doc-{}.bundle", std::process::id()));
+    /// let loader = MemoryLoader::new()
+    ///     .template("hello", "{include(greeting)} {name}")
+    ///     .template("greeting", "Hello");
+    /// let mut engine: Engine<()> = Engine::new(loader);
+    /// engine.save_bundle(&path, &["hello"]).unwrap();
+    /// assert!(engine.save_bundle(&path, &["hello", "bye"]).is_err());
+    ///
+    /// let mut shipped: Engine<()> = Engine::load_bundle(&path).unwrap();
+    /// shipped.registry().register_static("name", "Gio");
+    /// assert_eq!(shipped.render("hello", &()).unwrap().output, "Hello Gio");
+    /// # std::fs::remove_file(&path).unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn save_bundle<P: AsRef<Path>, N: AsRef<str>>(&mut self, path: P, names: &[N]) -> Result<(), EngineError> {
+        let path = path.as_ref();
+        let failed = |message: String| EngineError::BundleFailed { path: path.display().to_string(), message };
+        self.check_generation();
+        let mut templates = BTreeMap::new();
+        let mut pending: Vec<String> = names.iter().map(|name| name.as_ref().to_string()).collect();
+        while let Some(name) = pending.pop() {
+            if templates.contains_key(&name) {
+                continue;
+            }
+            let compiled = self.load(&name)?;
+            pending.extend(includes(&compiled.expressions()).map(str::to_string));
+            templates.insert(name, compiled);
+        }
+        let bytes = bundle::encode(templates.iter().map(|(name, compiled)| (name.as_str(), &**compiled))).map_err(failed)?;
+        fs::write(path, bytes).map_err(|error| failed(error.to_string()))
+    }
+
+    /// An engine rendering the templates of a bundle written by [`save_bundle`](#method.save_bundle), with no producers
+    ///
+    /// The templates are already parsed, and are kept in memory to be parsed again if they are dropped from the cache. Needs the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn load_bundle<P: AsRef<Path>>(path: P) -> Result<Engine<C>, EngineError> {
+        let path = path.as_ref();
+        let failed = |message: String| EngineError::BundleFailed { path: path.display().to_string(), message };
+        let bytes = fs::read(path).map_err(|error| failed(error.to_string()))?;
+        let templates = bundle::decode(&bytes).map_err(failed)?;
+
+        let loader: MemoryLoader = templates.iter().map(|(name, compiled)| (name.clone(), compiled.source().to_string())).collect();
+        let mut engine = Engine::new(loader);
+        for (name, compiled) in templates {
+            engine.cache.insert(Key::Name(name), Rc::new(compiled));
+        }
+        Ok(engine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+    use alloc::string::{String, ToString};
+    use core::cell::Cell;
+
+    use error::{EdoError, EngineError, RenderError};
+    use escape::Escaping;
+    use loader::{Loader, MemoryLoader};
+    use parse::{self, Expression, Span};
+    use super::{CacheStats, Compiled, Engine, Producers};
+
+    #[derive(Clone, Default)]
+    struct Counting {
+        loads: Rc<Cell<u32>>,
+        generation: Rc<Cell<u64>>,
+    }
+
+    impl Loader for Counting {
+        fn load(&self, name: &str) -> Result<Option<String>, String> {
+            self.loads.set(self.loads.get() + 1);
+            match name {
+                "broken" => Ok(Some("{oops".to_string())),
+                "unreadable" => Err("Permission denied".to_string()),
+                _ => Ok(Some(format!("{} {{value}}", name))),
+            }
+        }
+
+        fn generation(&self) -> u64 {
+            self.generation.get()
+        }
+    }
+
+    #[test]
+    fn keep_expressions_as_ranges_of_the_source() {
+        let compiled = Compiled::build("Hi {name(a, b)|upper:>4}".to_string(), |text| parse::parse(text).map(|mut expressions| {
+            expressions.push(Expression::Literal("!"));
+            expressions
+        })).unwrap();
+        let moved = Rc::new(compiled);
+        assert_eq!(moved.source(), "Hi {name(a, b)|upper:>4}!");
+        let mut expected = parse::parse("Hi {name(a, b)|upper:>4}").unwrap();
+        expected.push(Expression::Literal("!"));
+        assert_eq!(moved.expressions(), expected);
+
+        let name = "name".to_string();
+        let copied = Compiled::copy(&[Expression::Literal("Hi "), Expression::Function { name: &name, arguments: vec!["a"], argument_spans: vec![], has_parentheses: true, case: None, filters: vec![], format: None, raw: false, span: Span { start: 3, end: 10 } }]);
+        drop(name);
+        assert_eq!(copied.source(), "Hi namea");
+        assert_eq!(copied.expressions()[1], Expression::Function { name: "name", arguments: vec!["a"], argument_spans: vec![], has_parentheses: true, case: None, filters: vec![], format: None, raw: false, span: Span { start: 3, end: 10 } });
+    }
+
+    #[test]
+    fn load_templates_once_per_generation() {
+        let loader = Counting::default();
+        let mut engine = Engine::new(loader.clone());
+        engine.registry().register_static("value", "1");
+        assert_eq!(engine.render("a", &()).unwrap().output, "a 1");
+        assert_eq!(engine.render("a", &()).unwrap().output, "a 1");
+        assert_eq!(loader.loads.get(), 1);
+
+        loader.generation.set(3);
+        assert_eq!(engine.render("a", &()).unwrap().output, "a 1");
+        assert_eq!((loader.loads.get(), engine.generation()), (2, 3));
+
+        assert_eq!(engine.render("broken", &()), Err(EngineError::Parse {
+            name: "broken".to_string(),
+            error: EdoError::UnterminatedPlaceholder { span: Span { start: 0, end: 5 } },
+        }));
+        assert_eq!(engine.render("unreadable", &()).unwrap_err().to_string(), "Couldn't load template unreadable: Permission denied");
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let loader = Counting::default();
+        let mut engine = Engine::new(loader.clone());
+        engine.set_cache_capacity(2);
+        engine.render("a", &()).unwrap();
+        engine.render("b", &()).unwrap();
+        engine.render("a", &()).unwrap();
+        engine.render_source("{value}", &()).unwrap();
+        engine.render("a", &()).unwrap();
+        engine.render("b", &()).unwrap();
+        assert_eq!(loader.loads.get(), 3);
+        assert_eq!(engine.cache_stats(), CacheStats { hits: 2, misses: 4, evictions: 2, len: 2, capacity: 2 });
+
+        // Template strings survive a reload of the loader's templates
+        engine.render_source("{value}", &()).unwrap();
+        loader.generation.set(1);
+        engine.render("c", &()).unwrap();
+        engine.render_source("{value}", &()).unwrap();
+        assert_eq!(engine.cache_stats(), CacheStats { hits: 3, misses: 6, evictions: 3, len: 2, capacity: 2 });
+    }
+
+    #[test]
+    fn include_templates() {
+        let loader = MemoryLoader::new()
+            .template("a", "a[{include(b)}]")
+            .template("b", "b[{include(c)}{include(c)}]")
+            .template("c", "c")
+            .template("loop", "{include(again)}")
+            .template("again", "{include(loop)}")
+            .template("self", "{include(self)}")
+            .template("dangling", "{include(nope)}");
+        let mut engine: Engine<()> = Engine::new(loader);
+        assert_eq!(engine.render("a", &()).unwrap().output, "a[b[cc]]");
+        assert_eq!(engine.render_source("{include(c)}{include(a)}", &()).unwrap().output, "ca[b[cc]]");
+
+        let cycle = |names: &[&str]| EngineError::Render(RenderError::IncludeCycle { cycle: names.iter().map(|name| name.to_string()).collect() });
+        assert_eq!(engine.render("loop", &()), Err(cycle(&["loop", "again", "loop"])));
+        assert_eq!(engine.render("self", &()), Err(cycle(&["self", "self"])));
+        assert_eq!(engine.render_source("{include(again)}", &()), Err(cycle(&["again", "loop", "again"])));
+        assert_eq!(engine.render("dangling", &()), Err(EngineError::NotFound { name: "nope".to_string() }));
+    }
+
+    #[test]
+    fn escape_includes_for_their_context() {
+        let loader = MemoryLoader::new()
+            .template("link", "<a href=\"{include(target)}\" title=\"{include(target)}\">{include(target)}</a>")
+            .template("target", "{url}");
+        let mut engine: Engine<()> = Engine::new(loader);
+        engine.registry().set_escaping(Escaping::ContextualHtml);
+        engine.registry().register_static("url", "javascript:alert('x')");
+        assert_eq!(
+            engine.render("link", &()).unwrap().output,
+            "<a href=\"#\" title=\"javascript:alert(&#x27;x&#x27;)\">javascript:alert(&#x27;x&#x27;)</a>"
+        );
+    }
+
+    #[test]
+    fn overlay_producers_for_one_render() {
+        let loader = MemoryLoader::new()
+            .template("page", "{user}: {include(footer)}")
+            .template("footer", "{user} at {site}");
+        let mut engine = Engine::new(loader);
+        engine.registry().register_static("site", "edo.rs");
+        engine.registry().register_static("user", "guest");
+
+        let request = Producers::new().handler("user", |_, id: &u32| Ok(format!("user {}", id)));
+        assert_eq!(engine.render_with("page", &7, &request).unwrap().output, "user 7: user 7 at edo.rs");
+        assert_eq!(engine.render("page", &7).unwrap().output, "guest: guest at edo.rs");
+    }
+
+    #[test]
+    #[cfg(feature = "locale")]
+    fn render_locale_variants() {
+        use catalog::LocaleChain;
+        use options::RenderOptions;
+
+        let loader = MemoryLoader::new()
+            .template("welcome", "Welcome{include(footer)}")
+            .template("welcome.de", "Willkommen {lang}{include(footer)}")
+            .template("welcome.pt-PT", "Bem-vindo")
+            .template("footer", "!")
+            .template("broken.fr", "{oops");
+        let mut engine: Engine<()> = Engine::new(loader);
+        engine.registry().register_localized_handler("lang", |_, _, locale| Ok(locale.unwrap_or("none").to_string()));
+        assert_eq!(engine.render_localized("welcome", "de-AT", &()).unwrap().output, "Willkommen de-AT!");
+        assert_eq!(engine.render_localized("welcome", "pt-BR", &()).unwrap().output, "Welcome!");
+        assert_eq!(engine.render_localized("welcome", "fr", &()).unwrap().output, "Welcome!");
+
+        engine.registry().set_options(RenderOptions::new().locale_chain(LocaleChain::new().fallback("pt-BR", "pt-PT").default_locale("de")));
+        assert_eq!(engine.render_localized("welcome", "pt-BR", &()).unwrap().output, "Bem-vindo");
+        assert_eq!(engine.render_localized("welcome", "fr", &()).unwrap().output, "Willkommen fr!");
+        assert!(matches!(engine.render_localized("broken", "fr", &()), Err(EngineError::Parse { .. })));
+        assert_eq!(engine.render_localized("missing", "fr", &()), Err(EngineError::NotFound { name: "missing".to_string() }));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn bundle_named_templates() {
+        let path = std::env::temp_dir().join(format!("edo-test-{}.bundle", std::process::id()));
+        let loader = MemoryLoader::new()
+            .template("a", "a[{include(b)}]")
+            .template("b", "b {value}")
+            .template("c", "c {value}")
+            .template("dangling", "{include(nope)}")
+            .template("broken", "{oops");
+        let mut engine: Engine<()> = Engine::new(loader);
+        engine.set_cache_capacity(1);
+        engine.render("c", &()).unwrap();
+        engine.save_bundle(&path, &["a", "c"]).unwrap();
+
+        let mut shipped: Engine<()> = Engine::load_bundle(&path).unwrap();
+        shipped.registry().register_static("value", "1");
+        assert_eq!(shipped.render("a", &()).unwrap().output, "a[b 1]");
+        assert_eq!(shipped.render("b", &()).unwrap().output, "b 1");
+        assert_eq!(shipped.render("c", &()).unwrap().output, "c 1");
+
+        assert_eq!(engine.save_bundle(&path, &["a", "missing"]), Err(EngineError::NotFound { name: "missing".to_string() }));
+        assert_eq!(engine.save_bundle(&path, &["dangling"]), Err(EngineError::NotFound { name: "nope".to_string() }));
+        assert!(matches!(engine.save_bundle(&path, &["broken"]), Err(EngineError::Parse { .. })));
+        assert!(matches!(Engine::<()>::load_bundle(&path).unwrap().render("dangling", &()), Err(EngineError::NotFound { .. })));
+        assert!(matches!(Engine::<()>::new(Counting::default()).save_bundle(&path, &["unreadable"]), Err(EngineError::LoadFailed { .. })));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
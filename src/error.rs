@@ -1,35 +1,418 @@
 //! Errors
+//!
+//! Every error has a stable code, returned by `code()`, for tooling that maps errors to documentation or filters them:
+//!
+//! | Code    | Error                                   |
+//! |---------|-----------------------------------------|
+//! | `E0001` | `EdoError::UnterminatedPlaceholder`     |
+//! | `E0002` | `EdoError::BadArguments`                |
+//! | `E0003` | `EdoError::BadFilter`                   |
+//! | `E0004` | `EdoError::UnsupportedInBytes`          |
+//! | `E0005` | `EdoError::InvalidUtf8`                 |
+//! | `E0006` | `EdoError::BadFormat`                   |
+//! | `E0007` | `EdoError::UnmatchedSection`            |
+//! | `E0008` | `EdoError::UnsupportedTag`              |
+//! | `E0009` | `EdoError::NestedTooDeep`               |
+//! | `E0010` | `EdoError::UnsupportedDelimiters`       |
+//! | `E0101` | `RenderError::HandlerFailed`            |
+//! | `E0102` | `RenderError::MissingProducer`          |
+//! | `E0103` | `RenderError::UnknownFilter`            |
+//! | `E0104` | `RenderError::NotAllowed`               |
+//! | `E0105` | `RenderError::WrongArity`               |
+//! | `E0201` | `RenderError::OutputTooLarge`           |
+//! | `E0202` | `RenderError::DepthLimitExceeded`       |
+//! | `E0203` | `RenderError::RecursiveTemplate`        |
+//! | `E0204` | `RenderError::IncludeCycle`             |
+//! | `E0301` | `EngineError::NotFound`                 |
+//! | `E0302` | `EngineError::LoadFailed`               |
+//! | `E0303` | `EngineError::BundleFailed`             |
+//! | `E0401` | `RegisterError::Duplicate`              |
+//!
+//! Codes are never reused, new errors get new codes.
 
-use std::fmt;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::Utf8Error;
+
+use args::Arity;
+use parse::Span;
+#[cfg(feature = "std")]
 use std::error::Error;
 
-/// The error our format function might through
-#[derive(Debug, PartialEq)]
+/// An error found while parsing a template
+///
+/// Every variant about a placeholder has the span of the placeholder that couldn't be parsed, see [`Diagnostic`](../diagnostic/struct.Diagnostic.html) for showing it. More variants may be added as the syntax grows.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum EdoError {
-    /// An error occured while parsing the template
-    ParsingError,
+    /// A `{` without a closing `}`, the span runs to the end of the line
+    UnterminatedPlaceholder {
+        /// Where the placeholder starts
+        span: Span,
+    },
+    /// An argument list that isn't closed, or has a brace or unbalanced parentheses
+    BadArguments {
+        /// Where the placeholder appears
+        span: Span,
+    },
+    /// A `|` that isn't followed by a filter name made of alphanumerics and `_`
+    BadFilter {
+        /// Where the placeholder appears
+        span: Span,
+    },
+    /// A `:` after the arguments or filters that isn't followed by a format spec, see [`Format`](../format/struct.Format.html)
+    BadFormat {
+        /// Where the placeholder appears
+        span: Span,
+    },
+    /// A filter, format spec or raw placeholder in a byte template, which supports none of them
+    UnsupportedInBytes {
+        /// Where the placeholder appears
+        span: Span,
+    },
+    /// A placeholder name in a byte template that isn't UTF-8
+    InvalidUtf8 {
+        /// Where the placeholder appears
+        span: Span,
+        /// Why the name isn't UTF-8
+        error: Utf8Error,
+    },
+    /// A mustache section that isn't closed, or a closing tag that doesn't match the open section, see [`Edo::new_mustache`](../struct.Edo.html#method.new_mustache)
+    UnmatchedSection {
+        /// Where the tag appears
+        span: Span,
+    },
+    /// A mustache tag edo doesn't read, such as one without a name or one changing the delimiters
+    UnsupportedTag {
+        /// Where the tag appears
+        span: Span,
+    },
+    /// Parentheses or placeholders in an argument nested deeper than [`MAX_NESTING`](../parse/constant.MAX_NESTING.html)
+    NestedTooDeep {
+        /// From the start of the placeholder to where it nests too deep
+        span: Span,
+    },
+    /// Delimiters that no [`Syntax`](../parse/enum.Syntax.html) uses, see [`EdoBuilder::delimiters`](../builder/struct.EdoBuilder.html#method.delimiters)
+    UnsupportedDelimiters {
+        /// What placeholders were to start with
+        open: String,
+        /// What placeholders were to end with
+        close: String,
+    },
+}
+
+impl EdoError {
+    /// The stable code of this error, see the [module docs](index.html)
+    ///
+    /// # Examples
+    /// ```
+    /// use edo::parse::parse;
+    ///
+    /// assert_eq!(parse("Hello {name").unwrap_err().code(), "E0001");
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match *self {
+            EdoError::UnterminatedPlaceholder { .. } => "E0001",
+            EdoError::BadArguments { .. } => "E0002",
+            EdoError::BadFilter { .. } => "E0003",
+            EdoError::UnsupportedInBytes { .. } => "E0004",
+            EdoError::InvalidUtf8 { .. } => "E0005",
+            EdoError::BadFormat { .. } => "E0006",
+            EdoError::UnmatchedSection { .. } => "E0007",
+            EdoError::UnsupportedTag { .. } => "E0008",
+            EdoError::NestedTooDeep { .. } => "E0009",
+            EdoError::UnsupportedDelimiters { .. } => "E0010",
+        }
+    }
+
+    /// Where the placeholder that couldn't be parsed appears, if the error is about one
+    pub fn span(&self) -> Option<Span> {
+        match *self {
+            EdoError::UnterminatedPlaceholder { span } |
+            EdoError::BadArguments { span } |
+            EdoError::BadFilter { span } |
+            EdoError::BadFormat { span } |
+            EdoError::UnmatchedSection { span } |
+            EdoError::UnsupportedTag { span } |
+            EdoError::NestedTooDeep { span } |
+            EdoError::UnsupportedInBytes { span } |
+            EdoError::InvalidUtf8 { span, .. } => Some(span),
+            EdoError::UnsupportedDelimiters { .. } => None,
+        }
+    }
 }
 
 impl fmt::Display for EdoError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            EdoError::ParsingError => write!(f, "Parsing error"),
+            EdoError::UnterminatedPlaceholder { .. } => write!(f, "Unterminated placeholder, expected a closing }}"),
+            EdoError::BadArguments { .. } => write!(f, "Invalid argument list, expected arguments separated by commas and a closing )"),
+            EdoError::BadFilter { .. } => write!(f, "Invalid filter, filter names must be alphanumeric or _"),
+            EdoError::BadFormat { .. } => write!(f, "Invalid format spec, expected a fill, alignment, width and precision such as *>12 or 08.2"),
+            EdoError::UnsupportedInBytes { .. } => write!(f, "Byte templates don't support filters, format specs or raw placeholders"),
+            EdoError::InvalidUtf8 { ref error, .. } => write!(f, "Placeholder name is not valid UTF-8: {}", error),
+            EdoError::UnmatchedSection { .. } => write!(f, "Unmatched section, expected a closing tag with the name of the open section"),
+            EdoError::UnsupportedTag { .. } => write!(f, "Unsupported mustache tag, expected a name or a section, comment or partial"),
+            EdoError::NestedTooDeep { .. } => write!(f, "Placeholder is nested too deep"),
+            EdoError::UnsupportedDelimiters { ref open, ref close } => write!(f, "Unsupported delimiters {} and {}, expected {{ and }} or {{{{ and }}}}", open, close),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl Error for EdoError {
-    fn description(&self) -> &str {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            EdoError::InvalidUtf8 { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// An error found while rendering a template
+///
+/// [`Edo::try_render`](../struct.Edo.html#method.try_render) returns the errors that abort a render as `Err`, and collects the rest in a [`RenderReport`](../report/struct.RenderReport.html). Spans point into the template the placeholder was parsed from, which is a registered template for errors inside nested templates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenderError {
+    /// A placeholder had no producer or fallback, only reported when [`RenderOptions::missing_producers`](../options/struct.RenderOptions.html#method.missing_producers) is set
+    MissingProducer {
+        /// The name of the placeholder
+        name: String,
+        /// Where the placeholder appears
+        span: Span,
+    },
+    /// A handler returned an error
+    HandlerFailed {
+        /// The name of the handler
+        name: String,
+        /// The arguments the handler was called with
+        args: Vec<String>,
+        /// The message the handler returned
+        message: String,
+        /// Where the placeholder appears
+        span: Span,
+    },
+    /// A placeholder used a filter that isn't registered
+    UnknownFilter {
+        /// The name of the filter
+        name: String,
+        /// Where the placeholder appears
+        span: Span,
+    },
+    /// A placeholder isn't on the [`RenderOptions::allow_only`](../options/struct.RenderOptions.html#method.allow_only) list
+    NotAllowed {
+        /// The name of the placeholder
+        name: String,
+        /// Where the placeholder appears
+        span: Span,
+    },
+    /// A handler registered with an arity was given a different number of arguments, so it wasn't called
+    WrongArity {
+        /// The name of the handler
+        name: String,
+        /// How many arguments the handler takes
+        expected: Arity,
+        /// How many arguments it was given
+        found: usize,
+        /// Where the extra arguments are, or the placeholder when there are too few
+        span: Span,
+    },
+    /// The output grew past [`RenderOptions::max_output`](../options/struct.RenderOptions.html#method.max_output)
+    OutputTooLarge {
+        /// The limit that was exceeded, in bytes
+        limit: usize,
+    },
+    /// Nested templates and placeholder arguments went deeper than [`RenderOptions::max_depth`](../options/struct.RenderOptions.html#method.max_depth)
+    DepthLimitExceeded {
+        /// The limit that was exceeded
+        limit: usize,
+        /// The names of the nested templates, outermost first, which leaves out the arguments
+        chain: Vec<String>,
+    },
+    /// A nested template included itself
+    RecursiveTemplate {
+        /// The names of the templates in the cycle, starting and ending with the same name
+        cycle: Vec<String>,
+    },
+    /// A template rendered by an [`Engine`](../engine/struct.Engine.html) included itself with `{include(name)}`
+    IncludeCycle {
+        /// The names of the templates in the cycle, starting and ending with the same name
+        cycle: Vec<String>,
+    },
+}
+
+impl RenderError {
+    /// The stable code of this error, see the [module docs](index.html)
+    pub fn code(&self) -> &'static str {
+        match *self {
+            RenderError::HandlerFailed { .. } => "E0101",
+            RenderError::MissingProducer { .. } => "E0102",
+            RenderError::UnknownFilter { .. } => "E0103",
+            RenderError::NotAllowed { .. } => "E0104",
+            RenderError::WrongArity { .. } => "E0105",
+            RenderError::OutputTooLarge { .. } => "E0201",
+            RenderError::DepthLimitExceeded { .. } => "E0202",
+            RenderError::RecursiveTemplate { .. } => "E0203",
+            RenderError::IncludeCycle { .. } => "E0204",
+        }
+    }
+
+    /// Where the placeholder that caused the error appears, if the error is about a single placeholder
+    pub fn span(&self) -> Option<Span> {
+        match *self {
+            RenderError::MissingProducer { span, .. } |
+            RenderError::HandlerFailed { span, .. } |
+            RenderError::UnknownFilter { span, .. } |
+            RenderError::NotAllowed { span, .. } |
+            RenderError::WrongArity { span, .. } => Some(span),
+            RenderError::OutputTooLarge { .. } |
+            RenderError::DepthLimitExceeded { .. } |
+            RenderError::RecursiveTemplate { .. } |
+            RenderError::IncludeCycle { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RenderError::MissingProducer { ref name, .. } => write!(f, "Nothing produces {}", name),
+            RenderError::HandlerFailed { ref message, .. } => write!(f, "{}", message),
+            RenderError::UnknownFilter { ref name, .. } => write!(f, "Unknown filter {}", name),
+            RenderError::NotAllowed { ref name, .. } => write!(f, "{} is not allowed", name),
+            RenderError::WrongArity { ref name, expected, found, .. } => write!(f, "{} takes {} but was given {}", name, expected, found),
+            RenderError::OutputTooLarge { limit } => write!(f, "Output is larger than the limit of {} bytes", limit),
+            RenderError::DepthLimitExceeded { limit, ref chain } =>
+                write!(f, "Templates are nested more than {} deep: {}", limit, chain.join(" -> ")),
+            RenderError::RecursiveTemplate { ref cycle } |
+            RenderError::IncludeCycle { ref cycle } => write!(f, "Template includes itself: {}", cycle.join(" -> ")),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for RenderError {}
+
+/// An error from rendering a template by name with an [`Engine`](../engine/struct.Engine.html)
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum EngineError {
+    /// The loader has no template with this name
+    NotFound {
+        /// The name of the template
+        name: String,
+    },
+    /// The loader failed to read the template
+    LoadFailed {
+        /// The name of the template
+        name: String,
+        /// Why it failed
+        message: String,
+    },
+    /// The template couldn't be parsed
+    Parse {
+        /// The name of the template
+        name: String,
+        /// The parse error, with a span into the template's source
+        error: EdoError,
+    },
+    /// The render was aborted
+    Render(RenderError),
+    /// A template bundle couldn't be read or written
+    BundleFailed {
+        /// The path of the bundle
+        path: String,
+        /// Why it failed
+        message: String,
+    },
+}
+
+impl EngineError {
+    /// The stable code of this error, which is the code of the parse or render error for those variants
+    pub fn code(&self) -> &'static str {
+        match *self {
+            EngineError::NotFound { .. } => "E0301",
+            EngineError::LoadFailed { .. } => "E0302",
+            EngineError::BundleFailed { .. } => "E0303",
+            EngineError::Parse { ref error, .. } => error.code(),
+            EngineError::Render(ref error) => error.code(),
+        }
+    }
+}
+
+impl From<RenderError> for EngineError {
+    fn from(error: RenderError) -> EngineError {
+        EngineError::Render(error)
+    }
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EngineError::NotFound { ref name } => write!(f, "No template named {}", name),
+            EngineError::LoadFailed { ref name, ref message } => write!(f, "Couldn't load template {}: {}", name, message),
+            EngineError::Parse { ref name, ref error } => write!(f, "Couldn't parse template {}: {}", name, error),
+            EngineError::Render(ref error) => write!(f, "{}", error),
+            EngineError::BundleFailed { ref path, ref message } => write!(f, "Couldn't use bundle {}: {}", path, message),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for EngineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            EngineError::Parse { ref error, .. } => Some(error),
+            EngineError::Render(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// An error registering a producer, see [`Edo::try_register_producer`](../struct.Edo.html#method.try_register_producer)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RegisterError {
+    /// A producer with this name is registered already
+    Duplicate {
+        /// The name of the producer
+        name: String,
+    },
+}
+
+impl RegisterError {
+    /// The stable code of this error, see the [module docs](index.html)
+    pub fn code(&self) -> &'static str {
         match *self {
-            EdoError::ParsingError => "Parsing error",
+            RegisterError::Duplicate { .. } => "E0401",
         }
     }
+}
 
-    fn cause(&self) -> Option<&Error> {
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            // Our custom error doesn't have an underlying cause,
-            // but we could modify it so that it does.
-            EdoError::ParsingError => None,
+            RegisterError::Duplicate { ref name } => write!(f, "A producer named {} is registered already", name),
         }
     }
 }
+
+#[cfg(feature = "std")]
+impl Error for RegisterError {}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::error::Error;
+
+    use parse::{parse_bytes, Span};
+    use super::EdoError;
+
+    #[test]
+    fn invalid_utf8_has_a_source() {
+        let error = parse_bytes(b"ab{\xff}").unwrap_err();
+        assert_eq!(error.span(), Some(Span { start: 2, end: 5 }));
+        assert!(error.source().is_some());
+        assert!(EdoError::BadFilter { span: Span::default() }.source().is_none());
+    }
+}
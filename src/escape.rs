@@ -0,0 +1,581 @@
+//! Escaping of placeholder output
+//!
+//! Every template has an [`Encoder`](trait.Encoder.html) that the value of each placeholder passes through before it is inserted into the output. It does nothing by default, [`Edo::set_escaping`](../struct.Edo.html#method.set_escaping) switches to one of the built in [`Escaping`](enum.Escaping.html) modes and [`Edo::set_encoder`](../struct.Edo.html#method.set_encoder) accepts your own. Literal text in the template is never encoded, and a placeholder written as `{!name}` or `{name|raw}` is inserted raw.
+//!
+//! The other escaping functions are registered as filters on every template, so `{path|urlencode}`, `{message|json}` and `{argument|shell_quote}` work out of the box. They can also wrap a handler directly:
+//!
+//! ```
+//! # use edo::Edo;
+//! use edo::escape;
+//!
+//! let mut template = Edo::new("rm {file}").unwrap();
+//! template.register_handler("file", |_, name: &str| Ok(escape::shell_quote(name)));
+//! assert_eq!(template.render("my file.txt"), "rm 'my file.txt'");
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use parse::Expression;
+
+/// Encodes placeholder values for the output format
+///
+/// Closures taking a `&str` are encoders too.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::escape::Encoder;
+///
+/// struct Csv;
+///
+/// impl Encoder for Csv {
+///     fn encode(&self, value: String) -> String {
+///         if value.contains(|c| c == ',' || c == '"' || c == '\n') {
+///             format!("\"{}\"", value.replace('"', "\"\""))
+///         } else {
+///             value
+///         }
+///     }
+/// }
+///
+/// let mut template = Edo::new("{name},{quote}").unwrap();
+/// template.set_encoder(Csv);
+/// template.register_static("name", "Gio");
+/// template.register_static("quote", "Hello, \"World\"");
+/// assert_eq!(template.render(""), "Gio,\"Hello, \"\"World\"\"\"");
+/// ```
+pub trait Encoder {
+    /// Encode a single placeholder value
+    fn encode(&self, value: String) -> String;
+}
+
+impl<F: Fn(&str) -> String> Encoder for F {
+    fn encode(&self, value: String) -> String {
+        self(&value)
+    }
+}
+
+/// How placeholder output is escaped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Escaping {
+    /// Insert values as they are
+    #[default]
+    None,
+    /// Escape values for HTML text and quoted attributes, see [`html`](fn.html.html)
+    Html,
+    /// Escape values for where they appear in an HTML document, see [`HtmlContext`](enum.HtmlContext.html)
+    ContextualHtml,
+}
+
+impl Encoder for Escaping {
+    fn encode(&self, value: String) -> String {
+        match *self {
+            Escaping::None => value,
+            Escaping::Html | Escaping::ContextualHtml => html(&value),
+        }
+    }
+}
+
+/// Where a placeholder sits in an HTML document
+///
+/// The contexts are found by [`html_contexts`](fn.html_contexts.html), a conservative scan of the literal text around the placeholders. It is not a full HTML parser, and values in places it doesn't recognise are escaped as strictly as possible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HtmlContext {
+    /// Text between tags or inside a comment, escaped with [`html`](fn.html.html)
+    Text,
+    /// A quoted attribute value, escaped with [`html`](fn.html.html)
+    Attribute,
+    /// Anywhere else inside a tag, such as an unquoted attribute value or an attribute name
+    ///
+    /// Everything that could end the attribute or start a new one is escaped as a character reference.
+    Tag,
+    /// The start of a URL attribute such as `href` or `src`
+    ///
+    /// URLs with a scheme other than `http`, `https`, `mailto`, `tel` or `ftp` are replaced with `#`, so `javascript:` URLs can't be injected.
+    Url,
+    /// Later in a URL attribute, such as a path segment or query value, escaped with [`urlencode`](fn.urlencode.html)
+    UrlComponent,
+    /// The contents of a `<script>` element, inserted as a JavaScript string
+    Script,
+    /// An event handler attribute such as `onclick`, inserted as an escaped JavaScript string
+    ScriptAttribute,
+    /// The contents of a `<style>` element or a `style` attribute
+    ///
+    /// Only letters, digits, spaces and `# . , % - _` are kept.
+    Css,
+}
+
+impl HtmlContext {
+    /// Escape a value for this context
+    ///
+    /// # Examples
+    /// ```
+    /// use edo::escape::HtmlContext;
+    ///
+    /// assert_eq!(HtmlContext::Url.escape("javascript:alert(1)"), "#");
+    /// assert_eq!(HtmlContext::UrlComponent.escape("a b"), "a%20b");
+    /// assert_eq!(HtmlContext::Script.escape("</script>"), r#""\u003c/script\u003e""#);
+    /// ```
+    pub fn escape(self, value: &str) -> String {
+        match self {
+            HtmlContext::Text | HtmlContext::Attribute => html(value),
+            HtmlContext::Tag => html_unquoted(value),
+            HtmlContext::Url => html_unquoted(safe_url(value)),
+            HtmlContext::UrlComponent => urlencode(value),
+            HtmlContext::Script => script(value),
+            HtmlContext::ScriptAttribute => html_unquoted(&script(value)),
+            HtmlContext::Css => value.chars()
+                .filter(|character| character.is_ascii_alphanumeric() || " #.,%-_".contains(*character))
+                .collect(),
+        }
+    }
+}
+
+/// Find the [`HtmlContext`](enum.HtmlContext.html) of every expression in a template
+///
+/// The result has one entry per expression. Placeholders get the context they appear in and literals get the context they start in.
+///
+/// # Examples
+/// ```
+/// use edo::escape::{html_contexts, HtmlContext};
+/// use edo::parse::parse;
+///
+/// let template = parse("<a href=\"{url}?q={query}\" title={title}>{text}</a>").unwrap();
+/// let contexts = html_contexts(&template);
+/// assert_eq!(contexts[1], HtmlContext::Url);
+/// assert_eq!(contexts[3], HtmlContext::UrlComponent);
+/// assert_eq!(contexts[5], HtmlContext::Tag);
+/// assert_eq!(contexts[7], HtmlContext::Text);
+/// ```
+pub fn html_contexts(template: &[Expression]) -> Vec<HtmlContext> {
+    html_contexts_in(template, HtmlContext::Text)
+}
+
+// The contexts of a nested template rendered in place of a placeholder in `context`, so its placeholders are escaped for where they end up
+// Attribute values are taken to be in double quotes
+pub(crate) fn html_contexts_in(template: &[Expression], context: HtmlContext) -> Vec<HtmlContext> {
+    let (state, tag, attribute) = match context {
+        HtmlContext::Text => (State::Text, "", ""),
+        HtmlContext::Attribute => (State::Value { quote: Some(b'"'), empty: false }, "", ""),
+        HtmlContext::Tag => (State::Tag, "", ""),
+        HtmlContext::Url => (State::Value { quote: Some(b'"'), empty: true }, "", "href"),
+        HtmlContext::UrlComponent => (State::Value { quote: Some(b'"'), empty: false }, "", "href"),
+        HtmlContext::Script => (State::RawText, "script", ""),
+        HtmlContext::ScriptAttribute => (State::Value { quote: Some(b'"'), empty: false }, "", "onclick"),
+        HtmlContext::Css => (State::RawText, "style", ""),
+    };
+    let mut scanner = Scanner {
+        state,
+        closing: false,
+        tag: tag.into(),
+        attribute: attribute.into(),
+    };
+    template.iter()
+        .map(|expression| {
+            let context = scanner.context();
+            match *expression {
+                Expression::Literal(text) => scanner.literal(text),
+                Expression::Function { .. } => scanner.placeholder(),
+            }
+            context
+        })
+        .collect()
+}
+
+// Attributes holding a URL
+const URL_ATTRIBUTES: &[&str] = &[
+    "action", "background", "cite", "codebase", "data", "formaction", "href",
+    "icon", "longdesc", "manifest", "poster", "src", "srcset", "usemap", "xlink:href",
+];
+
+// Schemes that can't run code
+const SAFE_SCHEMES: &[&str] = &["http", "https", "mailto", "tel", "ftp"];
+
+#[derive(Clone, Copy)]
+enum State {
+    Text,
+    Comment,
+    // Inside a `<script>` or `<style>` element
+    RawText,
+    TagName,
+    Tag,
+    AttributeName,
+    AfterAttributeName,
+    BeforeValue,
+    Value { quote: Option<u8>, empty: bool },
+}
+
+// Tracks where in the document the template is, one literal at a time
+struct Scanner {
+    state: State,
+    closing: bool,
+    tag: String,
+    attribute: String,
+}
+
+impl Scanner {
+    fn context(&self) -> HtmlContext {
+        match self.state {
+            State::Text | State::Comment => HtmlContext::Text,
+            State::RawText if self.tag == "style" => HtmlContext::Css,
+            State::RawText => HtmlContext::Script,
+            State::TagName | State::Tag | State::AttributeName | State::AfterAttributeName => HtmlContext::Tag,
+            State::BeforeValue => self.value_context(false, true),
+            State::Value { quote, empty } => self.value_context(quote.is_some(), empty),
+        }
+    }
+
+    fn value_context(&self, quoted: bool, empty: bool) -> HtmlContext {
+        if self.attribute.starts_with("on") {
+            HtmlContext::ScriptAttribute
+        } else if self.attribute == "style" {
+            HtmlContext::Css
+        } else if URL_ATTRIBUTES.contains(&self.attribute.as_str()) {
+            if empty { HtmlContext::Url } else { HtmlContext::UrlComponent }
+        } else if quoted {
+            HtmlContext::Attribute
+        } else {
+            HtmlContext::Tag
+        }
+    }
+
+    // A placeholder is some unknown text at its position
+    fn placeholder(&mut self) {
+        self.state = match self.state {
+            State::Tag | State::AfterAttributeName => {
+                self.attribute.clear();
+                State::AttributeName
+            },
+            State::BeforeValue => State::Value { quote: None, empty: false },
+            State::Value { quote, .. } => State::Value { quote, empty: false },
+            state => state,
+        };
+    }
+
+    fn literal(&mut self, text: &str) {
+        let bytes = text.as_bytes();
+        let mut index = 0;
+        while index < bytes.len() {
+            let byte = bytes[index];
+            let rest = &bytes[index..];
+            self.state = match self.state {
+                State::Text => match byte {
+                    b'<' if rest.starts_with(b"<!--") => {
+                        index += 3;
+                        State::Comment
+                    },
+                    b'<' if rest.get(1).is_some_and(u8::is_ascii_alphabetic) => self.open_tag(false),
+                    b'<' if rest.get(1) == Some(&b'/') => {
+                        index += 1;
+                        self.open_tag(true)
+                    },
+                    _ => State::Text,
+                },
+                State::Comment if rest.starts_with(b"-->") => {
+                    index += 2;
+                    State::Text
+                },
+                State::Comment => State::Comment,
+                State::RawText if byte == b'<' && rest.get(1) == Some(&b'/') && rest.get(2..2 + self.tag.len()).is_some_and(|name| name.eq_ignore_ascii_case(self.tag.as_bytes())) => {
+                    index += 1;
+                    self.open_tag(true)
+                },
+                State::RawText => State::RawText,
+                State::TagName => match byte {
+                    b'>' => self.close_tag(),
+                    b'/' => State::Tag,
+                    byte if byte.is_ascii_whitespace() => State::Tag,
+                    byte => {
+                        self.tag.push(byte.to_ascii_lowercase() as char);
+                        State::TagName
+                    },
+                },
+                State::Tag => match byte {
+                    b'>' => self.close_tag(),
+                    b'/' => State::Tag,
+                    byte if byte.is_ascii_whitespace() => State::Tag,
+                    byte => self.start_attribute(byte),
+                },
+                State::AttributeName => match byte {
+                    b'>' => self.close_tag(),
+                    b'=' => State::BeforeValue,
+                    b'/' => State::Tag,
+                    byte if byte.is_ascii_whitespace() => State::AfterAttributeName,
+                    byte => {
+                        self.attribute.push(byte.to_ascii_lowercase() as char);
+                        State::AttributeName
+                    },
+                },
+                State::AfterAttributeName => match byte {
+                    b'>' => self.close_tag(),
+                    b'=' => State::BeforeValue,
+                    b'/' => State::Tag,
+                    byte if byte.is_ascii_whitespace() => State::AfterAttributeName,
+                    byte => self.start_attribute(byte),
+                },
+                State::BeforeValue => match byte {
+                    b'>' => self.close_tag(),
+                    b'"' | b'\'' => State::Value { quote: Some(byte), empty: true },
+                    byte if byte.is_ascii_whitespace() => State::BeforeValue,
+                    _ => State::Value { quote: None, empty: false },
+                },
+                State::Value { quote: Some(quote), .. } if byte == quote => State::Tag,
+                State::Value { quote: None, .. } if byte == b'>' => self.close_tag(),
+                State::Value { quote: None, .. } if byte.is_ascii_whitespace() => State::Tag,
+                State::Value { quote, .. } => State::Value { quote, empty: false },
+            };
+            index += 1;
+        }
+    }
+
+    fn open_tag(&mut self, closing: bool) -> State {
+        self.closing = closing;
+        self.tag.clear();
+        State::TagName
+    }
+
+    fn close_tag(&mut self) -> State {
+        if !self.closing && (self.tag == "script" || self.tag == "style") {
+            State::RawText
+        } else {
+            State::Text
+        }
+    }
+
+    fn start_attribute(&mut self, byte: u8) -> State {
+        self.attribute.clear();
+        self.attribute.push(byte.to_ascii_lowercase() as char);
+        State::AttributeName
+    }
+}
+
+// Replace URLs with a scheme that could run code
+fn safe_url(value: &str) -> &str {
+    let trimmed = value.trim_start_matches(|character: char| character <= ' ');
+    match trimmed.find([':', '/', '?', '#']) {
+        Some(index) if trimmed[index..].starts_with(':') => {
+            let scheme = &trimmed[..index];
+            if SAFE_SCHEMES.iter().any(|safe| safe.eq_ignore_ascii_case(scheme)) {
+                value
+            } else {
+                "#"
+            }
+        },
+        _ => value,
+    }
+}
+
+// Escape everything that could end an unquoted attribute value
+fn html_unquoted(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    for character in value.chars() {
+        if character.is_alphanumeric() || "-._~:/?#[]@!$()*+,;%".contains(character) {
+            output.push(character);
+        } else {
+            let _ = write!(output, "&#x{:x};", character as u32);
+        }
+    }
+    output
+}
+
+// A JavaScript string that can't close the surrounding element
+fn script(value: &str) -> String {
+    let mut output = String::with_capacity(value.len() + 2);
+    for character in json(value).chars() {
+        match character {
+            '<' | '>' | '&' | '\'' | '\u{2028}' | '\u{2029}' => {
+                let _ = write!(output, "\\u{:04x}", character as u32);
+            },
+            character => output.push(character),
+        }
+    }
+    output
+}
+
+/// Escape `&`, `<`, `>`, `"` and `'` as HTML character references
+///
+/// # Examples
+/// ```
+/// assert_eq!(edo::escape::html("<a href='/'>Tom & Jerry</a>"), "&lt;a href=&#x27;/&#x27;&gt;Tom &amp; Jerry&lt;/a&gt;");
+/// ```
+pub fn html(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    for character in value.chars() {
+        match character {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#x27;"),
+            _ => output.push(character),
+        }
+    }
+    output
+}
+
+/// Percent-encode everything except the unreserved URL characters `A-Z a-z 0-9 - . _ ~`
+///
+/// The output is safe to use as a single path segment or query value.
+///
+/// # Examples
+/// ```
+/// assert_eq!(edo::escape::urlencode("a b/c?d=é"), "a%20b%2Fc%3Fd%3D%C3%A9");
+/// ```
+pub fn urlencode(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => output.push(byte as char),
+            _ => {
+                let _ = write!(output, "%{:02X}", byte);
+            },
+        }
+    }
+    output
+}
+
+/// Quote a value as a JSON string, including the surrounding quotes
+///
+/// # Examples
+/// ```
+/// assert_eq!(edo::escape::json("say \"hi\"\n"), r#""say \"hi\"\n""#);
+/// ```
+pub fn json(value: &str) -> String {
+    let mut output = String::with_capacity(value.len() + 2);
+    output.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            '\u{8}' => output.push_str("\\b"),
+            '\u{c}' => output.push_str("\\f"),
+            character if (character as u32) < 0x20 => {
+                let _ = write!(output, "\\u{:04x}", character as u32);
+            },
+            character => output.push(character),
+        }
+    }
+    output.push('"');
+    output
+}
+
+/// Quote a value as a single POSIX shell word
+///
+/// Values made of only letters, digits and `_ @ % + = : , . / -` are left alone, anything else is wrapped in single quotes.
+///
+/// # Examples
+/// ```
+/// assert_eq!(edo::escape::shell_quote("notes.txt"), "notes.txt");
+/// assert_eq!(edo::escape::shell_quote("it's; rm -rf /"), r"'it'\''s; rm -rf /'");
+/// ```
+pub fn shell_quote(value: &str) -> String {
+    let safe = |character: char| character.is_ascii_alphanumeric() || "_@%+=:,./-".contains(character);
+    if !value.is_empty() && value.chars().all(safe) {
+        return String::from(value);
+    }
+
+    let mut output = String::with_capacity(value.len() + 2);
+    output.push('\'');
+    for character in value.chars() {
+        if character == '\'' {
+            output.push_str(r"'\''");
+        } else {
+            output.push(character);
+        }
+    }
+    output.push('\'');
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use alloc::vec::Vec;
+
+    use parse::{parse, Expression};
+    use super::{html, html_contexts, json, shell_quote, urlencode, Encoder, Escaping, HtmlContext};
+
+    #[test]
+    fn escape_html() {
+        assert_eq!(html("plain text"), "plain text");
+        assert_eq!(html("<script>alert(\"hi\")</script>"), "&lt;script&gt;alert(&quot;hi&quot;)&lt;/script&gt;");
+        assert_eq!(html("&amp;"), "&amp;amp;");
+    }
+
+    #[test]
+    fn escape_url() {
+        assert_eq!(urlencode("safe-._~AZaz09"), "safe-._~AZaz09");
+        assert_eq!(urlencode("a&b=c d"), "a%26b%3Dc%20d");
+        assert_eq!(urlencode(""), "");
+    }
+
+    #[test]
+    fn escape_json() {
+        assert_eq!(json(""), r#""""#);
+        assert_eq!(json("tab\tback\\slash"), r#""tab\tback\\slash""#);
+        assert_eq!(json("\u{1}é"), r#""\u0001é""#);
+    }
+
+    #[test]
+    fn escape_shell() {
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("--flag=value"), "--flag=value");
+        assert_eq!(shell_quote("$(whoami)"), "'$(whoami)'");
+        assert_eq!(shell_quote("'"), r"''\'''");
+    }
+
+    #[test]
+    fn find_html_contexts() {
+        let template = match parse("<p class={a} id='{b}'>{c}<!-- {d} --></p><script>var x = {e};</script><style>p { color: {f} }</style><img src=\"{g}\" style=\"{h}\" onerror={i}>{j}") {
+            Ok(template) => template,
+            Err(err) => panic!("{}", err),
+        };
+        let placeholders: Vec<HtmlContext> = html_contexts(&template).into_iter()
+            .zip(&template)
+            .filter_map(|(context, expression)| match *expression {
+                Expression::Function { .. } => Some(context),
+                Expression::Literal(_) => None,
+            })
+            .collect();
+        assert_eq!(placeholders, vec![
+            HtmlContext::Tag,
+            HtmlContext::Attribute,
+            HtmlContext::Text,
+            HtmlContext::Text,
+            HtmlContext::Script,
+            HtmlContext::Css,
+            HtmlContext::Url,
+            HtmlContext::Css,
+            HtmlContext::ScriptAttribute,
+            HtmlContext::Text,
+        ]);
+    }
+
+    #[test]
+    fn escape_html_contexts() {
+        assert_eq!(HtmlContext::Tag.escape("x onload=alert(1)"), "x&#x20;onload&#x3d;alert(1)");
+        assert_eq!(HtmlContext::Url.escape("https://example.com/?a=1&b=2"), "https://example.com/?a&#x3d;1&#x26;b&#x3d;2");
+        assert_eq!(HtmlContext::Url.escape(" JavaScript:alert(1)"), "#");
+        assert_eq!(HtmlContext::Url.escape("/relative:path"), "/relative:path");
+        assert_eq!(HtmlContext::Css.escape("red; background: url(x)"), "red background urlx");
+    }
+
+    #[test]
+    fn escaping_modes() {
+        assert_eq!(Escaping::default(), Escaping::None);
+        assert_eq!(Escaping::None.encode("<b>".to_string()), "<b>");
+        assert_eq!(Escaping::Html.encode("<b>".to_string()), "&lt;b&gt;");
+    }
+
+    #[test]
+    fn closure_encoder() {
+        let encoder = |value: &str| value.replace('\\', "\\textbackslash{}");
+        assert_eq!(encoder.encode("a\\b".to_string()), "a\\textbackslash{}b");
+    }
+}
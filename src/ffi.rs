@@ -0,0 +1,165 @@
+//! C bindings
+//!
+//! Enable the `ffi` feature and build a C library with `cargo rustc --release --features ffi --crate-type cdylib` (or `staticlib`). The matching declarations are in `include/edo.h`.
+//!
+//! ```c
+//! EdoTemplate *template = edo_new("Hello {name}");
+//! edo_register_static(template, "name", "World!");
+//! char *output = edo_render(template);
+//! puts(output); // Hello World!
+//! edo_string_free(output);
+//! edo_free(template);
+//! ```
+//!
+//! All strings are NUL terminated UTF-8. Strings passed in are copied, so the caller keeps ownership of them.
+
+use std::boxed::Box;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+use parse;
+use Edo;
+
+/// A template created by `edo_new`
+pub struct EdoTemplate {
+    source: String,
+    statics: Vec<(String, String)>,
+}
+
+unsafe fn to_string(string: *const c_char) -> Option<String> {
+    if string.is_null() {
+        return None;
+    }
+    CStr::from_ptr(string).to_str().ok().map(|string| string.to_string())
+}
+
+/// Parse a new template
+///
+/// Returns `NULL` if the template is `NULL`, not valid UTF-8 or fails to parse. The template must be freed with `edo_free`.
+///
+/// # Safety
+/// `template` must be `NULL` or a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn edo_new(template: *const c_char) -> *mut EdoTemplate {
+    match to_string(template) {
+        Some(source) => match parse::parse(&source) {
+            Ok(_) => Box::into_raw(Box::new(EdoTemplate { source, statics: vec![] })),
+            Err(_) => ptr::null_mut(),
+        },
+        None => ptr::null_mut(),
+    }
+}
+
+/// Register a static replacement
+///
+/// Returns `0` on success and `-1` if any argument is `NULL` or a string is not valid UTF-8.
+///
+/// # Safety
+/// `template` must be `NULL` or a pointer returned by `edo_new` that has not been freed. `name` and `value` must be `NULL` or valid NUL terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn edo_register_static(template: *mut EdoTemplate, name: *const c_char, value: *const c_char) -> c_int {
+    match (template.as_mut(), to_string(name), to_string(value)) {
+        (Some(template), Some(name), Some(value)) => {
+            template.statics.push((name, value));
+            0
+        },
+        _ => -1,
+    }
+}
+
+/// Render the template into a newly allocated string
+///
+/// Returns `NULL` if `template` is `NULL` or the output contains a NUL byte. The output must be freed with `edo_string_free`.
+///
+/// # Safety
+/// `template` must be `NULL` or a pointer returned by `edo_new` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn edo_render(template: *const EdoTemplate) -> *mut c_char {
+    let template = match template.as_ref() {
+        Some(template) => template,
+        None => return ptr::null_mut(),
+    };
+
+    // C owns the source, so the template is parsed again for every render
+    let mut edo: Edo<()> = Edo::new(&template.source).expect("template was validated when it was created");
+    for (name, value) in &template.statics {
+        edo.register_static(name, value.as_str());
+    }
+    match CString::new(edo.render(&())) {
+        Ok(output) => output.into_raw(),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Free a string returned by `edo_render`
+///
+/// # Safety
+/// `string` must be `NULL` or a pointer returned by `edo_render` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn edo_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Free a template returned by `edo_new`
+///
+/// # Safety
+/// `template` must be `NULL` or a pointer returned by `edo_new` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn edo_free(template: *mut EdoTemplate) {
+    if !template.is_null() {
+        drop(Box::from_raw(template));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+    use std::ptr;
+
+    use super::{edo_free, edo_new, edo_register_static, edo_render, edo_string_free};
+
+    #[test]
+    fn render_statics() {
+        let source = CString::new("Hello {name}{missing}").unwrap();
+        let name = CString::new("name").unwrap();
+        let value = CString::new("World!").unwrap();
+        unsafe {
+            let template = edo_new(source.as_ptr());
+            assert!(!template.is_null());
+            assert_eq!(edo_register_static(template, name.as_ptr(), value.as_ptr()), 0);
+
+            let output = edo_render(template);
+            assert_eq!(CStr::from_ptr(output).to_str(), Ok("Hello World!"));
+            edo_string_free(output);
+            edo_free(template);
+        }
+    }
+
+    #[test]
+    fn invalid_template() {
+        let source = CString::new("Hello {name").unwrap();
+        unsafe {
+            assert!(edo_new(source.as_ptr()).is_null());
+            assert!(edo_new(ptr::null()).is_null());
+        }
+    }
+
+    #[test]
+    fn null_arguments() {
+        let source = CString::new("Hello").unwrap();
+        unsafe {
+            let template = edo_new(source.as_ptr());
+            assert_eq!(edo_register_static(template, ptr::null(), ptr::null()), -1);
+            assert_eq!(edo_register_static(ptr::null_mut(), source.as_ptr(), source.as_ptr()), -1);
+            assert!(edo_render(ptr::null()).is_null());
+            edo_string_free(ptr::null_mut());
+            edo_free(template);
+            edo_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Format specs, such as the `>12` in `{amount:>12}`
+
+use alloc::string::{String, ToString};
+
+/// How a placeholder's output is laid out, written after a `:` at the end of the placeholder
+///
+/// The syntax follows `std::fmt`: an optional fill character and alignment (`<` left, `^` center, `>` right), a `0` to pad numbers with zeros, a width, and a `.` followed by a precision. Output shorter than the width is padded with the fill, which defaults to a space. Width counts characters, not bytes. Width and precision can be at most 65535.
+///
+/// Output that is a number, like `-12.5`, is right aligned unless the spec says otherwise, the rest is left aligned. A precision rounds a number to that many decimals and cuts other output to that many characters. With a `0`, numbers are padded with zeros after their sign, and the fill and alignment are ignored.
+///
+/// The spec applies after the filters, so `{name|upper:^9}` centers the upper cased name.
+///
+/// A spec can start with a separator in double quotes, which joins the items of a list value, see [`Edo::register_list_handler`](../struct.Edo.html#method.register_list_handler). In `{tags:" / ">20}` the tags are joined with ` / ` and the result is right aligned.
+///
+/// # Examples
+/// ```
+/// use edo::format::Format;
+///
+/// let spec = Format::parse("*>6").unwrap();
+/// assert_eq!(spec.apply("abc"), "***abc");
+/// assert_eq!(Format::parse("<4").unwrap().apply("ab"), "ab  ");
+/// assert_eq!(Format::parse(".2").unwrap().apply("3.14159"), "3.14");
+/// assert_eq!(Format::parse("08").unwrap().apply("-42"), "-0000042");
+/// assert!(Format::parse("6x").is_none());
+/// assert_eq!(Format::parse("\", \"").unwrap().separator(), Some(", "));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Format<'s> {
+    separator: Option<&'s str>,
+    fill: Option<char>,
+    align: Option<Align>,
+    zero: bool,
+    width: Option<usize>,
+    precision: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    fn from_char(c: char) -> Option<Align> {
+        match c {
+            '<' => Some(Align::Left),
+            '^' => Some(Align::Center),
+            '>' => Some(Align::Right),
+            _ => None,
+        }
+    }
+}
+
+impl<'s> Format<'s> {
+    /// Parse a spec as it is written after the `:`, `None` if it isn't one
+    pub fn parse(spec: &'s str) -> Option<Format<'s>> {
+        let mut format = Format::default();
+        let spec = match spec.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"')?;
+                format.separator = Some(&quoted[..end]);
+                &quoted[end + 1..]
+            },
+            None => spec,
+        };
+        let mut chars = spec.chars();
+        let mut rest = spec;
+        match (chars.next(), chars.next().and_then(Align::from_char)) {
+            (Some(fill), Some(align)) => {
+                format.fill = Some(fill);
+                format.align = Some(align);
+                rest = chars.as_str();
+            },
+            (Some(first), None) => if let Some(align) = Align::from_char(first) {
+                format.align = Some(align);
+                rest = &spec[1..];
+            },
+            (None, _) => {},
+        }
+
+        if rest.len() > 1 && rest.starts_with('0') {
+            format.zero = true;
+            rest = &rest[1..];
+        }
+        let (width, precision) = match rest.find('.') {
+            Some(index) => (&rest[..index], Some(&rest[index + 1..])),
+            None => (rest, None),
+        };
+        format.width = count(width)?;
+        if format.width > Some(MAX_COUNT) {
+            return None;
+        }
+        if let Some(precision) = precision {
+            format.precision = Some(count(precision)??);
+        }
+        if format.precision > Some(MAX_COUNT) {
+            return None;
+        }
+        Some(format)
+    }
+
+    /// The separator that joins the items of a list, if the spec starts with one
+    pub fn separator(&self) -> Option<&'s str> {
+        self.separator
+    }
+
+    /// The number of characters output is padded to, if the spec has a width
+    pub fn width(&self) -> Option<usize> {
+        self.width
+    }
+
+    /// Lay out `value` as the spec says
+    pub fn apply(&self, value: &str) -> String {
+        let number = number(value);
+        let value = match (number, self.precision) {
+            (Some(number), Some(precision)) if value.starts_with('+') => format!("{:+.*}", precision, number),
+            (Some(number), Some(precision)) => format!("{:.*}", precision, number),
+            (None, Some(precision)) => value.chars().take(precision).collect(),
+            (_, None) => value.to_string(),
+        };
+        let length = value.chars().count();
+        let padding = self.width.map_or(0, |width| width.saturating_sub(length));
+
+        if self.zero && number.is_some() {
+            let sign = if value.starts_with('-') || value.starts_with('+') { 1 } else { 0 };
+            let mut output = String::with_capacity(value.len() + padding);
+            output.push_str(&value[..sign]);
+            output.extend(core::iter::repeat_n('0', padding));
+            output.push_str(&value[sign..]);
+            return output;
+        }
+
+        let align = self.align.unwrap_or(if number.is_some() { Align::Right } else { Align::Left });
+        let (before, after) = match align {
+            Align::Left => (0, padding),
+            Align::Center => (padding / 2, padding - padding / 2),
+            Align::Right => (padding, 0),
+        };
+        let fill = self.fill.unwrap_or(' ');
+        let mut output = String::with_capacity(value.len() + padding * fill.len_utf8());
+        output.extend(core::iter::repeat_n(fill, before));
+        output.push_str(&value);
+        output.extend(core::iter::repeat_n(fill, after));
+        output
+    }
+}
+
+// The largest width or precision a spec can have, as `std::fmt` allows
+const MAX_COUNT: usize = 65_535;
+
+// A width or precision, `None` when it's left out
+fn count(digits: &str) -> Option<Option<usize>> {
+    if digits.is_empty() {
+        Some(None)
+    } else if digits.bytes().all(|c| c.is_ascii_digit()) {
+        digits.parse().ok().map(Some)
+    } else {
+        None
+    }
+}
+
+// The value of output that is written as a number, but not words like `inf` that parse as one
+fn number(value: &str) -> Option<f64> {
+    value.parse().ok().filter(|_| value.bytes().all(|c| c.is_ascii_digit() || b"+-.eE".contains(&c)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Format;
+
+    #[test]
+    fn apply_formats() {
+        let apply = |spec: &str, value: &str| Format::parse(spec).unwrap().apply(value);
+        assert_eq!(apply(">5", "ab"), "   ab");
+        assert_eq!(apply("<5", "ab"), "ab   ");
+        assert_eq!(apply("5", "ab"), "ab   ");
+        assert_eq!(apply("^5", "ab"), " ab  ");
+        assert_eq!(apply("-^6", "ab"), "--ab--");
+        assert_eq!(apply(">>3", "é"), ">>é");
+        assert_eq!(apply(">2", "long"), "long");
+        assert_eq!(apply("", "ab"), "ab");
+        assert_eq!(apply("<", "ab"), "ab");
+
+        // Numbers
+        assert_eq!(apply("5", "42"), "   42");
+        assert_eq!(apply("<5", "42"), "42   ");
+        assert_eq!(apply("05", "42"), "00042");
+        assert_eq!(apply("*<06", "-4.5"), "-004.5");
+        assert_eq!(apply("08.2", "+2.5"), "+0002.50");
+        assert_eq!(apply(".0", "2.5e1"), "25");
+        assert_eq!(apply(".3", "1"), "1.000");
+        assert_eq!(apply("05", "inf"), "inf  ");
+        assert_eq!(apply(".2", "abc"), "ab");
+        assert_eq!(apply(">6.3", "abcdef"), "   abc");
+        assert_eq!(apply("0", "7"), "7");
+
+        assert!(Format::parse("x").is_none());
+        assert!(Format::parse(">+5").is_none());
+        assert!(Format::parse("5 ").is_none());
+        assert!(Format::parse("5.").is_none());
+        assert!(Format::parse(".x").is_none());
+        assert!(Format::parse("5.2.1").is_none());
+        assert_eq!(Format::parse("65535").unwrap().width(), Some(65_535));
+        assert!(Format::parse("65536").is_none());
+        assert!(Format::parse("20000000000").is_none());
+        assert_eq!(apply(".65535", "ab"), "ab");
+        assert!(Format::parse(".65536").is_none());
+        assert!(Format::parse(".99999999999").is_none());
+
+        let joined = Format::parse("\"-\"*^5").unwrap();
+        assert_eq!((joined.separator(), joined.apply("a").as_str()), (Some("-"), "**a**"));
+        assert_eq!(Format::parse("\"\"").unwrap().separator(), Some(""));
+        assert!(Format::parse("\"-").is_none());
+        assert!(Format::parse("\"-\"x").is_none());
+    }
+}
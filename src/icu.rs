@@ -0,0 +1,276 @@
+//! ICU MessageFormat messages, see [`Edo::new_icu`](../struct.Edo.html#method.new_icu)
+//!
+//! A subset of the syntax is read into placeholders for the [`plural`](../builtins/fn.plural.html) and [`numbers`](../builtins/fn.numbers.html) builtins, so messages from existing catalogs don't have to be rewritten:
+//!
+//! | Message | Renders like |
+//! |---|---|
+//! | `{name}` | `{name}` |
+//! | `{count, number}`, `{count, number, integer}` | `{num({count}, , locale)}`, `{num({count}, 0, locale)}` |
+//! | `{count, plural, =0 {none} one {# file} other {# files}}` | `{icu_plural({count}, locale, =0: none, one: # file, other: # files)}` |
+//! | `{gender, select, female {she} other {they}}` | `{select({gender}, female: she, other: they)}` |
+//!
+//! Text is quoted as ICU quotes it: `''` is an apostrophe, and an apostrophe before `{`, `}` or `#` starts quoted text that runs to the next apostrophe. The forms of a plural or select are text, they can't have placeholders of their own. Other argument types, styles and plural offsets are parse errors.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use error::EdoError;
+use parse::{Expression, Span};
+
+// A message read into placeholders, whose text is kept in one string for the expressions to borrow
+pub(crate) struct Message {
+    pub(crate) text: String,
+    pieces: Vec<Piece>,
+}
+
+enum Piece {
+    Literal(Range<usize>),
+    Placeholder {
+        name: Range<usize>,
+        arguments: Vec<Range<usize>>,
+        span: Span,
+    },
+}
+
+impl Message {
+    // The expressions of the message, borrowing from `text`, which has to be a copy of the message's text
+    pub(crate) fn expressions<'t>(&self, text: &'t str) -> Vec<Expression<'t>> {
+        self.pieces.iter().map(|piece| match *piece {
+            Piece::Literal(ref range) => Expression::Literal(&text[range.clone()]),
+            Piece::Placeholder { ref name, ref arguments, span } => Expression::Function {
+                name: &text[name.clone()],
+                arguments: arguments.iter().map(|range| &text[range.clone()]).collect(),
+                argument_spans: arguments.iter().map(|_| span).collect(),
+                has_parentheses: !arguments.is_empty(),
+                case: None,
+                filters: vec![],
+                format: None,
+                raw: false,
+                span,
+            },
+        }).collect()
+    }
+
+    fn push(&mut self, text: &str) -> Range<usize> {
+        let start = self.text.len();
+        self.text.push_str(text);
+        start..self.text.len()
+    }
+}
+
+// Read an ICU message whose plurals and numbers are in `locale`
+pub(crate) fn parse(message: &str, locale: &str) -> Result<Message, EdoError> {
+    let mut parser = Parser { message, offset: 0 };
+    let mut read = Message { text: String::new(), pieces: vec![] };
+    loop {
+        let literal = parser.text(false);
+        if !literal.is_empty() {
+            let range = read.push(&literal);
+            read.pieces.push(Piece::Literal(range));
+        }
+        if parser.rest().is_empty() {
+            return Ok(read);
+        }
+        parser.placeholder(&mut read, locale)?;
+    }
+}
+
+struct Parser<'m> {
+    message: &'m str,
+    offset: usize,
+}
+
+impl<'m> Parser<'m> {
+    fn rest(&self) -> &'m str {
+        &self.message[self.offset..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.offset += rest.len() - rest.trim_start().len();
+    }
+
+    // Read up to the next character in `ends`, trimmed
+    fn word(&mut self, ends: &[char]) -> &'m str {
+        let rest = self.rest();
+        let length = rest.find(|c: char| ends.contains(&c)).unwrap_or(rest.len());
+        self.offset += length;
+        rest[..length].trim()
+    }
+
+    // Read and step over `expected`, if it's next
+    fn eat(&mut self, expected: char) -> bool {
+        let found = self.rest().starts_with(expected);
+        if found {
+            self.offset += expected.len_utf8();
+        }
+        found
+    }
+
+    // Read text up to a `{`, or a `}` in the form of a plural or select, unquoting it
+    fn text(&mut self, in_form: bool) -> String {
+        let mut text = String::new();
+        let mut quoted = false;
+        let mut chars = self.rest().char_indices().peekable();
+        let start = self.offset;
+        while let Some((index, c)) = chars.next() {
+            let next = chars.peek().map(|&(_, next)| next);
+            match c {
+                '\'' if next == Some('\'') => {
+                    text.push('\'');
+                    chars.next();
+                },
+                '\'' if quoted => quoted = false,
+                '\'' if next.is_some_and(|next| "{}#".contains(next)) => quoted = true,
+                '{' if !quoted => {
+                    self.offset = start + index;
+                    return text;
+                },
+                '}' if !quoted && in_form => {
+                    self.offset = start + index;
+                    return text;
+                },
+                c => text.push(c),
+            }
+        }
+        self.offset = self.message.len();
+        text
+    }
+
+    // Read a placeholder starting at the `{` into `read`
+    fn placeholder(&mut self, read: &mut Message, locale: &str) -> Result<(), EdoError> {
+        let start = self.offset;
+        self.eat('{');
+        let name = self.word(&[',', '}', '{']);
+        let (function, arguments) = if self.eat('}') {
+            (name, vec![])
+        } else if self.eat(',') {
+            let kind = self.word(&[',', '}', '{']);
+            let reference = format!("{{{}}}", name);
+            match kind {
+                "number" => {
+                    let precision = if self.eat(',') {
+                        match self.word(&['}', '{']) {
+                            "integer" => "0",
+                            _ => return Err(self.bad(start)),
+                        }
+                    } else {
+                        ""
+                    };
+                    if !self.eat('}') {
+                        return Err(self.end_error(start));
+                    }
+                    ("num", vec![reference, precision.into(), locale.into()])
+                },
+                "plural" | "select" if self.eat(',') => {
+                    let mut arguments = vec![reference];
+                    if kind == "plural" {
+                        arguments.push(locale.into());
+                    }
+                    loop {
+                        self.skip_whitespace();
+                        if self.eat('}') {
+                            break;
+                        }
+                        let key = self.word(&['{', '}', ' ', '\t', '\n', '\r']);
+                        self.skip_whitespace();
+                        if key.is_empty() || !self.eat('{') {
+                            return Err(self.end_error(start));
+                        }
+                        let form = self.text(true);
+                        if !self.eat('}') {
+                            return Err(self.end_error(start));
+                        }
+                        arguments.push(format!("{}: {}", key, escape(&form)));
+                    }
+                    (if kind == "plural" { "icu_plural" } else { "select" }, arguments)
+                },
+                _ => return Err(self.end_error(start)),
+            }
+        } else {
+            return Err(self.end_error(start));
+        };
+        if name.is_empty() {
+            return Err(self.bad(start));
+        }
+        let span = Span { start, end: self.offset };
+        let name = read.push(function);
+        let arguments = arguments.iter().map(|argument| read.push(argument)).collect();
+        read.pieces.push(Piece::Placeholder { name, arguments, span });
+        Ok(())
+    }
+
+    // The error for a placeholder that stops making sense where the parser is, or runs to the end
+    fn end_error(&self, start: usize) -> EdoError {
+        if self.rest().is_empty() {
+            let end = self.message[start..].find('\n').map_or(self.message.len(), |end| start + end);
+            EdoError::UnterminatedPlaceholder { span: Span { start, end } }
+        } else {
+            self.bad(start)
+        }
+    }
+
+    fn bad(&self, start: usize) -> EdoError {
+        let end = self.rest().find('}').map_or(self.message.len(), |end| self.offset + end + 1);
+        EdoError::BadArguments { span: Span { start, end } }
+    }
+}
+
+// Escape the characters that end or split an argument, see `parse::unescape`
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if ",(){}\\".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use error::EdoError;
+    use parse::Span;
+    use Edo;
+
+    #[test]
+    fn render_icu_messages() {
+        let mut edo = match Edo::new_icu("{name} has {count, plural, =0 {no files} one {# file} other {# files, (really)}} in '{'{folder}'}', {gender, select, female {her} other {their}} {size, number} KB{total, number, integer} ''", "en") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("name", "Gio");
+        edo.register_static("folder", "docs");
+        edo.register_static("gender", "x");
+        edo.register_static("size", "1234.5");
+        edo.register_static("total", "2.6");
+        edo.register_static("count", "0");
+        assert_eq!(edo.render(&()), "Gio has no files in {docs}, their 1,234.5 KB3 '");
+        edo.register_static("count", "3");
+        assert_eq!(edo.render(&()), "Gio has 3 files, (really) in {docs}, their 1,234.5 KB3 '");
+
+        let mut german: Edo<()> = Edo::new_icu("{n, number} {n, plural, one {Datei} other {Dateien}}", "de").unwrap();
+        german.register_static("n", "1000");
+        assert_eq!(german.render(&()), "1.000 Dateien");
+        assert_eq!(german.segments().count(), 3);
+        let mut partial = german.render_partial(&()).unwrap();
+        drop(german);
+        assert_eq!(partial.render(&()), "1.000 Dateien");
+    }
+
+    #[test]
+    fn reject_icu_messages() {
+        let error = |message: &str| Edo::<()>::new_icu(message, "en").err();
+        assert_eq!(error("a {name"), Some(EdoError::UnterminatedPlaceholder { span: Span { start: 2, end: 7 } }));
+        assert_eq!(error("{n, plural, one {x}"), Some(EdoError::UnterminatedPlaceholder { span: Span { start: 0, end: 19 } }));
+        assert_eq!(error("{n, date} b"), Some(EdoError::BadArguments { span: Span { start: 0, end: 9 } }));
+        assert_eq!(error("{n, number, percent}"), Some(EdoError::BadArguments { span: Span { start: 0, end: 20 } }));
+        assert_eq!(error("{n, plural, one {{x}} other {y}}").map(|error| error.to_string().is_empty()), Some(false));
+        assert_eq!(error("{}"), Some(EdoError::BadArguments { span: Span { start: 0, end: 2 } }));
+        assert!(error("it''s '{literal}' }").is_none());
+    }
+}
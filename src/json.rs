@@ -0,0 +1,112 @@
+//! Using a `serde_json::Value` as the render context
+//!
+//! Call [`Edo::use_context_lookup`](../struct.Edo.html#method.use_context_lookup) (or register [`lookup`](fn.lookup.html) as the template's fallback) and any placeholder without a handler is resolved by walking its dot separated name through the context. Handlers and static replacements still take precedence.
+//!
+//! # Examples
+//! ```
+//! # extern crate edo;
+//! # #[macro_use] extern crate serde_json;
+//! # fn main() {
+//! use edo::Edo;
+//!
+//! let mut template = Edo::new("{greeting} {user.name} from {user.address.city}").unwrap();
+//! template.register_static("greeting", "Hello");
+//! template.use_context_lookup();
+//! let output = template.render(&json!({
+//!     "user": {
+//!         "name": "Gio",
+//!         "address": { "city": "Portland" },
+//!     },
+//! }));
+//! assert_eq!(output, "Hello Gio from Portland");
+//! # }
+//! ```
+
+use std::borrow::Cow;
+use std::string::{String, ToString};
+
+use serde_json::Value;
+
+use context::Lookup;
+
+/// Find the value at a dot separated path, such as `user.address.city`
+///
+/// Numeric segments index into arrays (`items.0.name`). Strings are returned without quotes, `null` becomes an empty string and arrays or objects are returned as JSON. Returns `None` when the path does not exist.
+pub fn lookup(path: &str, value: &Value) -> Option<String> {
+    path.split('.')
+        .try_fold(value, |value, segment| match *value {
+            Value::Object(ref map) => map.get(segment),
+            Value::Array(ref items) => segment.parse::<usize>().ok().and_then(|index| items.get(index)),
+            _ => None,
+        })
+        .map(|found| match *found {
+            Value::String(ref string) => string.clone(),
+            Value::Null => "".to_string(),
+            ref other => other.to_string(),
+        })
+}
+
+/// Placeholder names are treated as paths, see [`lookup`](fn.lookup.html)
+impl Lookup for Value {
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        lookup(name, self).map(Cow::Owned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::string::ToString;
+
+    use serde_json::Value;
+
+    use super::lookup;
+    use Edo;
+
+    fn context() -> Value {
+        json!({
+            "name": "Gio",
+            "age": 30,
+            "admin": true,
+            "nickname": null,
+            "address": { "city": "Portland", "zip": "97201" },
+            "pets": [{ "name": "Rex" }, { "name": "Tom" }],
+        })
+    }
+
+    #[test]
+    fn lookup_top_level() {
+        assert_eq!(lookup("name", &context()), Some("Gio".to_string()));
+        assert_eq!(lookup("age", &context()), Some("30".to_string()));
+        assert_eq!(lookup("admin", &context()), Some("true".to_string()));
+        assert_eq!(lookup("nickname", &context()), Some("".to_string()));
+    }
+
+    #[test]
+    fn lookup_nested() {
+        assert_eq!(lookup("address.city", &context()), Some("Portland".to_string()));
+        assert_eq!(lookup("pets.1.name", &context()), Some("Tom".to_string()));
+        assert_eq!(lookup("address", &context()), Some(r#"{"city":"Portland","zip":"97201"}"#.to_string()));
+    }
+
+    #[test]
+    fn lookup_missing() {
+        assert_eq!(lookup("email", &context()), None);
+        assert_eq!(lookup("address.street", &context()), None);
+        assert_eq!(lookup("pets.5.name", &context()), None);
+        assert_eq!(lookup("name.first", &context()), None);
+    }
+
+    #[test]
+    fn handlers_take_precedence() {
+        let mut edo = match Edo::new("{name} lives in {address.city}{email}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_handler("name", |_, _| Ok("Someone".to_string()));
+        edo.register_fallback(lookup);
+        assert_eq!(
+            edo.render(&context()),
+            "Someone lives in Portland"
+        );
+    }
+}
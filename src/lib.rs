@@ -1,6 +1,6 @@
 //! Edo is a VERY simple templating language. It allows you to register handlers that are executed when their matching names are found in the template.
 //!
-//! For example, with the template `"Hello {name}"`, the `name` handler would be executed and the string it returns would be substituted in place of the original `{name}`. Handler functions can also accept arguments, which are passed in as a `Vec<str>`.
+//! For example, with the template `"Hello {name}"`, the `name` handler would be executed and the string it returns would be substituted in place of the original `{name}`. Handler functions can also accept arguments, which are passed in as [`Args`](args/struct.Args.html).
 //!
 //! # Examples
 //!
@@ -33,60 +33,567 @@
 //! let output = template.render("");
 //! assert_eq!(output, "Hello World");
 //! ```
+//!
+//! # `no_std`
+//! Edo only needs `alloc`. Disable the default `std` feature to use it on `no_std` targets, which leaves out the `std::error::Error` implementations, the `HashMap` context and the `json`, `ffi` and `wasm` features.
+#![no_std]
 #![deny(missing_docs)]
 
 #[macro_use]
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
 extern crate nom;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+#[cfg(feature = "normalize")]
+extern crate unicode_normalization;
+#[cfg(feature = "watch")]
+extern crate notify;
+#[cfg(any(feature = "json", all(test, feature = "serde")))]
+#[cfg_attr(all(test, feature = "json"), macro_use)]
+extern crate serde_json;
+
+#[macro_use]
+mod macros;
 
+pub mod args;
+pub mod builder;
+pub mod builtins;
+pub mod catalog;
+#[cfg(feature = "std")]
+mod bundle;
+pub mod bytes;
+pub mod context;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod diagnostic;
+pub mod engine;
 pub mod error;
-mod parse;
+pub mod escape;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod format;
+pub mod icu;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod loader;
+pub mod mustache;
+pub mod observer;
+pub mod options;
+pub mod parse;
+pub mod producer;
+mod pseudo;
+pub mod report;
+#[cfg(feature = "serde")]
+pub mod ser;
+pub mod signature;
+pub mod simple;
+pub mod template;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+// Paths used by `edo_format!` and the `edo_derive` macros, which can't rely on `std` being available
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::borrow::Cow;
+    pub use alloc::string::ToString;
+    pub use core::option::Option;
+}
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 
-use std::str;
-use std::collections::HashMap;
+use args::{Args, Arity};
+use builder::EdoBuilder;
+use builtins::{BuiltinHandler, Builtins};
+#[cfg(feature = "locale")]
+use catalog::Catalog;
+use catalog::Message;
+use context::{Lookup, Mut};
+use engine::Compiled;
+use error::{EdoError, RegisterError, RenderError};
+use escape::{Encoder, Escaping, HtmlContext};
+use format::Format;
+use observer::Observer;
+use options::RenderOptions;
+use parse::{Expression, Span, Syntax};
+use producer::Producer;
+use report::{PlaceholderMetrics, PlaceholderOutcome, RenderReport, Resolution, Severity, Timer, TraceEvent};
+use signature::Signature;
 
-use error::EdoError;
-use parse::Expression;
+type Handler<C> = Box<dyn Fn(Args, &C) -> Result<String, String>>;
+type ListHandler<C> = Box<dyn Fn(Args, &C) -> Result<Vec<String>, String>>;
+#[cfg(feature = "locale")]
+type LocalizedHandler<C> = Box<dyn Fn(Args, &C, Option<&str>) -> Result<String, String>>;
+type Fallback<'a, C> = Box<dyn Fn(&str, &C) -> Option<String> + 'a>;
+// Shared so `render_partial` can hand them on to the template it makes
+type Filter = Rc<dyn Fn(&str) -> String>;
+// The templates an engine render can include, by name
+pub(crate) type Partials<'r> = BTreeMap<String, &'r [Expression<'r>]>;
 
-enum ValueProducer<'a, C> {
-    Handler(Box<Fn(Vec<&'a str>, C) -> Result<String, String>>),
+enum ValueProducer<'a, C: ?Sized> {
+    Handler(Handler<C>, Option<Arity>),
+    ListHandler(ListHandler<C>),
+    #[cfg(feature = "locale")]
+    Localized(LocalizedHandler<C>),
+    Builtin(BuiltinHandler),
     Static(String),
+    StaticList(Vec<String>),
+    Template(Vec<Expression<'a>>),
+    // A mustache section, rendered for the items of `value`, see `mustache`
+    Section {
+        value: String,
+        inverted: bool,
+        body: Compiled,
+    },
+}
+
+// What a render keeps track of as it works through nested templates
+struct RenderState<'r, C: 'r + ?Sized> {
+    // The names of the templates being rendered, outermost first
+    stack: Vec<String>,
+    // Only set when rendering from an engine, for `{include(name)}`
+    partials: Option<&'r Partials<'r>>,
+    // The names of the engine templates being rendered, outermost first
+    includes: Vec<String>,
+    // Producers for this render only, looked up before the template's own
+    producers: Option<&'r BTreeMap<String, ValueProducer<'r, C>>>,
+    // How many placeholder arguments are being rendered, whose values are passed on without escaping
+    arguments: usize,
+    report: RenderReport,
+    // Only collected by `render_traced`
+    trace: Option<Vec<TraceEvent>>,
+    // Passed to builtin handlers
+    locale: Option<String>,
+    // Used instead of the template's own options, see `render_with_options`
+    options: Option<&'r RenderOptions>,
+    // The items of the mustache sections being rendered, innermost last, for `{{.}}`
+    items: Vec<String>,
+}
+
+// The expressions of a template, borrowed from the text it was given or parsed from its own copy
+enum Parsed<'a> {
+    Borrowed(Vec<Expression<'a>>),
+    Owned(Compiled),
+}
+
+impl<'a> Parsed<'a> {
+    // Shortened to this borrow, so expressions of its own copy can't outlive it
+    fn expressions(&self) -> Cow<'_, [Expression<'_>]> {
+        match *self {
+            Parsed::Borrowed(ref expressions) => Cow::Borrowed(expressions),
+            Parsed::Owned(ref compiled) => Cow::Owned(compiled.expressions()),
+        }
+    }
 }
 
 /// A single template. Allows registering of handlers and rendering
-pub struct Edo<'a, C> {
+pub struct Edo<'a, C: ?Sized> {
     #[doc(hidden)]
-    value_producers: HashMap<&'a str, ValueProducer<'a, C>>,
-    template: Vec<Expression<'a>>,
+    value_producers: BTreeMap<String, ValueProducer<'a, C>>,
+    // Asked before the fallback, see `use_context_lookup`
+    lookup: Option<Fallback<'a, C>>,
+    fallback: Option<Fallback<'a, C>>,
+    filters: BTreeMap<String, Filter>,
+    encoder: Rc<dyn Encoder + 'a>,
+    html_contexts: Option<Vec<HtmlContext>>,
+    options: RenderOptions,
+    observers: Vec<Box<dyn Observer + 'a>>,
+    #[cfg(feature = "locale")]
+    catalog: Option<Box<dyn Catalog + 'a>>,
+    metrics: BTreeMap<String, PlaceholderMetrics>,
+    template: Parsed<'a>,
+    // How `set_template` and `register_template` parse templates
+    syntax: Syntax,
 }
 
-impl<'a, C: Clone> Edo<'a, C> {
+impl<'a, C: ?Sized> Edo<'a, C> {
     /// Creates a new template instance.
     ///
     /// # Examples
     /// ```no_run
     /// # #![allow(unused_variables)]
     /// # use edo::Edo;
-    /// let template: Result<Edo<&str>, _> = Edo::new("Hello {name}");
+    /// let template: Result<Edo<str>, _> = Edo::new("Hello {name}");
     /// ```
     pub fn new(template_string: &'a str) -> Result<Edo<'a, C>, EdoError> {
-        Ok(Edo {
-            value_producers: HashMap::new(),
-            template: try!(parse::parse(template_string)),
-        })
+        Ok(Edo::from_expressions(parse::parse(template_string)?))
+    }
+
+    /// Creates a new template whose placeholders are written with `syntax`, see [`Syntax`](parse/enum.Syntax.html)
+    ///
+    /// The template keeps the syntax, [`set_template`](#method.set_template), [`parse_into`](#method.parse_into) and [`register_template`](#method.register_template) parse with it too.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::parse::Syntax;
+    ///
+    /// let mut template = Edo::new_with_syntax("function greet() { return '{{ greeting(world) }}'; }", Syntax::DoubleBrace).unwrap();
+    /// template.register_handler("greeting", |args, _| Ok(format!("Hello {}", args[0])));
+    /// assert_eq!(template.render(&()), "function greet() { return 'Hello world'; }");
+    /// ```
+    pub fn new_with_syntax(template_string: &'a str, syntax: Syntax) -> Result<Edo<'a, C>, EdoError> {
+        let mut edo = Edo::from_expressions(parse::parse_with_syntax(template_string, syntax)?);
+        edo.syntax = syntax;
+        Ok(edo)
+    }
+
+    /// Creates a new template with its producers, so it doesn't have to be changed after it's made
+    ///
+    /// A producer replaces the ones before it with the same name. See [`Producer`](producer/struct.Producer.html) for an example.
+    pub fn with_producers<'n, I>(template_string: &'a str, producers: I) -> Result<Edo<'a, C>, EdoError> where
+        I: IntoIterator<Item = (&'n str, Producer<'a, C>)> {
+        let mut edo = Edo::new(template_string)?;
+        for (name, producer) in producers {
+            edo.register_producer(name, producer);
+        }
+        Ok(edo)
+    }
+
+    /// Start configuring a template in one expression, see [`EdoBuilder`](builder/struct.EdoBuilder.html)
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::escape::Escaping;
+    ///
+    /// let template: Edo<()> = Edo::builder("<b>{name}</b>").escaping(Escaping::Html).build().unwrap();
+    /// ```
+    pub fn builder(template_string: &'a str) -> EdoBuilder<'a, C> {
+        EdoBuilder::new(template_string)
+    }
+
+    /// Creates a new template from an indented multi-line string, removing the indentation its lines have in common
+    ///
+    /// Templates embedded in Rust code can then be indented along with it. A line break right at the start is dropped, and lines with only whitespace become empty. The template keeps its own copy of the dedented text, and the spans in errors point into that copy.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new_dedented("
+    ///     [server]
+    ///       port = {port}
+    ///     ").unwrap();
+    /// template.register_static("port", "80");
+    /// assert_eq!(template.render(""), "[server]\n  port = 80\n");
+    /// ```
+    pub fn new_dedented(template_string: &str) -> Result<Edo<'a, C>, EdoError> {
+        let compiled = Compiled::build(dedent(template_string), parse::parse)?;
+        Ok(Edo { template: Parsed::Owned(compiled), ..Edo::default() })
+    }
+
+    /// Creates a new template from an ICU MessageFormat message, whose plurals and numbers are in `locale`
+    ///
+    /// The plural and number builtins the message uses are registered, see [`icu`](icu/index.html) for the syntax that is supported. Like [`new_dedented`](#method.new_dedented), the template keeps its own copy of the message.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new_icu("{name} has {count, plural, one {# file} other {# files}}", "en").unwrap();
+    /// template.register_static("name", "Gio");
+    /// template.register_static("count", "3");
+    /// assert_eq!(template.render(&()), "Gio has 3 files");
+    /// ```
+    pub fn new_icu(message: &str, locale: &str) -> Result<Edo<'a, C>, EdoError> {
+        let message = icu::parse(message, locale)?;
+        let compiled = Compiled::copy(&message.expressions(&message.text));
+        let mut edo = Edo { template: Parsed::Owned(compiled), ..Edo::default() };
+        edo.register_builtins(builtins::plural().merge(builtins::numbers()));
+        Ok(edo)
+    }
+
+    /// Creates a new template from a mustache template
+    ///
+    /// Placeholders are escaped for HTML, as mustache does, see [`set_escaping`](#method.set_escaping) to change it. Sections are rendered with the template's producers, see [`mustache`](mustache/index.html) for the syntax that is supported. The template keeps its own copy of the source.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new_mustache("Hello {{name}}{{#admin}} (admin){{/admin}}{{! greeting }}").unwrap();
+    /// template.register_static("name", "<Gio>");
+    /// template.register_handler("admin", |_, user: &str| Ok((user == "gio").to_string()));
+    /// assert_eq!(template.render("gio"), "Hello &lt;Gio&gt; (admin)");
+    /// assert_eq!(template.render("jo"), "Hello &lt;Gio&gt;");
+    /// ```
+    pub fn new_mustache(source: &str) -> Result<Edo<'a, C>, EdoError> {
+        let template = mustache::parse(source)?;
+        let mut edo = Edo { template: Parsed::Owned(Compiled::copy(&template.expressions())), ..Edo::default() };
+        for (name, producer) in template.producers() {
+            edo.value_producers.insert(name.to_string(), producer);
+        }
+        edo.set_escaping(Escaping::Html);
+        Ok(edo)
+    }
+
+    /// Creates a new template instance from an already parsed template
+    ///
+    /// This is mostly useful for code generation, such as the `edo!` macro in `edo_derive`, which parses templates at compile time.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::parse::{Expression, Span};
+    ///
+    /// let mut template = Edo::from_expressions(vec![
+    ///     Expression::Literal("Hello "),
+    ///     Expression::Function { name: "name", arguments: vec![], argument_spans: vec![], has_parentheses: false, case: None, filters: vec![], format: None, raw: false, span: Span { start: 6, end: 12 } },
+    /// ]);
+    /// template.register_static("name", "World!");
+    /// assert_eq!(template.render(""), "Hello World!");
+    /// ```
+    pub fn from_expressions(template: Vec<Expression<'a>>) -> Edo<'a, C> {
+        let mut edo = Edo {
+            value_producers: BTreeMap::new(),
+            lookup: None,
+            fallback: None,
+            filters: BTreeMap::new(),
+            encoder: Rc::new(Escaping::None),
+            html_contexts: None,
+            options: RenderOptions::new(),
+            observers: vec![],
+            #[cfg(feature = "locale")]
+            catalog: None,
+            metrics: BTreeMap::new(),
+            template: Parsed::Borrowed(template),
+            syntax: Syntax::Brace,
+        };
+        edo.register_filter("urlencode", escape::urlencode);
+        edo.register_filter("json", escape::json);
+        edo.register_filter("shell_quote", escape::shell_quote);
+        edo
+    }
+
+    /// Parse `template_string` and render it from now on, keeping everything registered
+    ///
+    /// The current template is kept if the new one doesn't parse.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new("Hello {name}").unwrap();
+    /// template.register_static("name", "World!");
+    /// template.set_template("Bye {name}").unwrap();
+    /// assert_eq!(template.render(""), "Bye World!");
+    /// assert!(template.set_template("Bye {name").is_err());
+    /// assert_eq!(template.render(""), "Bye World!");
+    /// ```
+    pub fn set_template(&mut self, template_string: &'a str) -> Result<(), EdoError> {
+        let template = parse::parse_with_syntax(template_string, self.syntax)?;
+        self.set_parsed(Parsed::Borrowed(template));
+        Ok(())
+    }
+
+    fn set_parsed(&mut self, template: Parsed<'a>) {
+        self.template = template;
+        if self.html_contexts.is_some() {
+            self.html_contexts = Some(escape::html_contexts(&self.template.expressions()));
+        }
+    }
+
+    /// Parse a copy of `template_string` and render it from now on, like [`set_template`](#method.set_template)
+    ///
+    /// The template keeps the copy, so the source can be dropped right away, such as a template read from a file into an empty [`Edo::default`](#impl-Default).
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template: Edo<()> = Edo::default();
+    /// template.register_static("name", "World!");
+    /// assert_eq!(template.render(&()), "");
+    ///
+    /// let source = String::from("Hello {name}");
+    /// template.parse_into(&source).unwrap();
+    /// drop(source);
+    /// assert_eq!(template.render(&()), "Hello World!");
+    /// ```
+    ///
+    /// What is read from the copy, like the [`segments`](#method.segments) or [`extract_messages`](#method.extract_messages), borrows from the template and can't outlive it:
+    ///
+    /// ```compile_fail
+    /// # use edo::Edo;
+    /// let mut template: Edo<'static, ()> = Edo::default();
+    /// template.parse_into(&String::from("Hello {name}")).unwrap();
+    /// let messages = template.extract_messages();
+    /// drop(template);
+    /// assert_eq!(messages[0].text, "Hello");
+    /// ```
+    pub fn parse_into(&mut self, template_string: &str) -> Result<(), EdoError> {
+        let syntax = self.syntax;
+        let compiled = Compiled::build(template_string.to_string(), |text| parse::parse_with_syntax(text, syntax))?;
+        self.set_parsed(Parsed::Owned(compiled));
+        Ok(())
     }
 
     /// Register a new function handler
     ///
+    /// The handler is called with however many arguments the placeholder has, from none up, and a reference to the context of the render. Use [`register_handler_with_arity`](#method.register_handler_with_arity) to limit them.
+    ///
+    /// Like every `register_` method for a single name, this returns the producer it replaced, if there was one. [`try_register_producer`](#method.try_register_producer) refuses to replace one instead.
+    ///
     /// # Examples
     /// ```no_run
     /// # #![allow(unused_variables)]
     /// # use edo::Edo;
-    /// let mut template: Edo<&str> = Edo::new("Hello {name}").unwrap();
+    /// let mut template: Edo<str> = Edo::new("Hello {name}").unwrap();
     /// template.register_handler("name", |_, _| Ok("World!".to_string()));
     /// ```
-    pub fn register_handler<F>(&mut self, name: &'a str, handler: F) where
-        F: 'static + Fn(Vec<&'a str>, C) -> Result<String, String> {
-        self.value_producers.insert(name, ValueProducer::Handler(Box::new(handler)));
+    pub fn register_handler<F>(&mut self, name: &str, handler: F) -> Option<Producer<'a, C>> where
+        F: 'static + Fn(Args, &C) -> Result<String, String> {
+        self.value_producers.insert(name.to_string(), ValueProducer::Handler(Box::new(handler), None)).map(Producer)
+    }
+
+    /// Register a handler that only sees the part of the context that `map` picks out
+    ///
+    /// The handler can then be written against the part it needs, and registered on templates with different contexts.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::args::Args;
+    ///
+    /// struct User { name: String }
+    /// struct Page { title: String, user: User }
+    ///
+    /// fn greet(_: Args, user: &User) -> Result<String, String> {
+    ///     Ok(format!("Hi {}", user.name))
+    /// }
+    ///
+    /// let page = Page { title: "Home".to_string(), user: User { name: "Gio".to_string() } };
+    /// let mut template = Edo::new("{title}: {greet}").unwrap();
+    /// template.register_handler("title", |_, page: &Page| Ok(page.title.clone()));
+    /// template.register_handler_mapped("greet", |page: &Page| &page.user, greet);
+    /// assert_eq!(template.render(&page), "Home: Hi Gio");
+    /// ```
+    pub fn register_handler_mapped<V, M, F>(&mut self, name: &str, map: M, handler: F) -> Option<Producer<'a, C>> where
+        V: ?Sized,
+        M: 'static + Fn(&C) -> &V,
+        F: 'static + Fn(Args, &V) -> Result<String, String> {
+        self.register_handler(name, move |args, context| handler(args, map(context)))
+    }
+
+    /// Register a handler that is also called with the locale of the render, if it has one
+    ///
+    /// Needs the `locale` feature. The locale is set with [`RenderOptions::locale`](options/struct.RenderOptions.html#method.locale), [`render_in`](#method.render_in) or [`Engine::render_in`](engine/struct.Engine.html#method.render_in), so one handler serves every locale.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new("{greet}").unwrap();
+    /// template.register_localized_handler("greet", |_, name: &str, locale| Ok(match locale {
+    ///     Some("es") => format!("Hola {}", name),
+    ///     _ => format!("Hello {}", name),
+    /// }));
+    /// assert_eq!(template.render_in("es", "Gio"), "Hola Gio");
+    /// assert_eq!(template.render("Gio"), "Hello Gio");
+    /// ```
+    #[cfg(feature = "locale")]
+    pub fn register_localized_handler<F>(&mut self, name: &str, handler: F) -> Option<Producer<'a, C>> where
+        F: 'static + Fn(Args, &C, Option<&str>) -> Result<String, String> {
+        self.value_producers.insert(name.to_string(), ValueProducer::Localized(Box::new(handler))).map(Producer)
+    }
+
+    /// Register a handler that returns a list, which is joined into the placeholder's value
+    ///
+    /// The items are joined with the separator in double quotes at the start of the placeholder's format spec, as in `{tags:" / "}`, or else with [`RenderOptions::list_separator`](options/struct.RenderOptions.html#method.list_separator). The separator is part of the value, so it is filtered and escaped along with the items.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new("{tags} [{tags(2):\" | \"}]").unwrap();
+    /// template.register_list_handler("tags", |args, _| {
+    ///     let count = args.get_parsed(0).unwrap_or(3);
+    ///     Ok(vec!["rust", "templates", "cli"].into_iter().take(count).map(String::from).collect())
+    /// });
+    /// assert_eq!(template.render(&()), "rust, templates, cli [rust | templates]");
+    /// ```
+    pub fn register_list_handler<F>(&mut self, name: &str, handler: F) -> Option<Producer<'a, C>> where
+        F: 'static + Fn(Args, &C) -> Result<Vec<String>, String> {
+        self.value_producers.insert(name.to_string(), ValueProducer::ListHandler(Box::new(handler))).map(Producer)
+    }
+
+    /// Register a new function handler that takes `arity` arguments
+    ///
+    /// A placeholder with a different number of arguments doesn't call the handler, it is left empty and reported as [`RenderError::WrongArity`](error/enum.RenderError.html#variant.WrongArity). [`validate`](#method.validate) finds them before rendering.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::error::RenderError;
+    ///
+    /// let mut template = Edo::new("{greet(Gio)} {greet()}").unwrap();
+    /// template.register_handler_with_arity("greet", 1, |args, _| Ok(format!("Hi {}", args[0])));
+    /// let (output, errors) = template.render_with_errors(&());
+    /// assert_eq!(output, "Hi Gio ");
+    /// assert_eq!(errors[0].to_string(), "greet takes 1 argument but was given 0");
+    /// ```
+    pub fn register_handler_with_arity<A, F>(&mut self, name: &str, arity: A, handler: F) -> Option<Producer<'a, C>> where
+        A: Into<Arity>,
+        F: 'static + Fn(Args, &C) -> Result<String, String> {
+        self.value_producers.insert(name.to_string(), ValueProducer::Handler(Box::new(handler), Some(arity.into()))).map(Producer)
+    }
+
+    /// Register a new function handler with `required` arguments followed by optional ones, which get `defaults` when left out
+    ///
+    /// The handler always sees `required` plus `defaults.len()` arguments. Placeholders with fewer than `required` arguments or more than that are reported as [`RenderError::WrongArity`](error/enum.RenderError.html#variant.WrongArity), see [`register_handler_with_arity`](#method.register_handler_with_arity).
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new("[{pad(x)}] [{pad(x, 3)}] [{pad(x, 3, .)}]").unwrap();
+    /// template.register_handler_with_defaults("pad", 1, &["5", " "], |args, _| {
+    ///     let width: usize = args.get_parsed(1)?;
+    ///     Ok(format!("{}{}", args[0], args[2].repeat(width.saturating_sub(args[0].len()))))
+    /// });
+    /// assert_eq!(template.render(&()), "[x    ] [x  ] [x..]");
+    /// ```
+    pub fn register_handler_with_defaults<F>(&mut self, name: &str, required: usize, defaults: &[&str], handler: F) -> Option<Producer<'a, C>> where
+        F: 'static + Fn(Args, &C) -> Result<String, String> {
+        let defaults: Vec<String> = defaults.iter().map(|default| default.to_string()).collect();
+        let arity = Arity::between(required, required + defaults.len());
+        self.register_handler_with_arity(name, arity, move |args, context| {
+            let (given, has_parentheses) = (args.len() - required, args.has_parentheses());
+            let mut args: Vec<&str> = args.into_iter().collect();
+            args.extend(defaults[given..].iter().map(String::as_str));
+            handler(Args::new(args, has_parentheses), context)
+        })
+    }
+
+    /// Register a producer made beforehand, replacing and returning any producer with the same name
+    pub fn register_producer(&mut self, name: &str, producer: Producer<'a, C>) -> Option<Producer<'a, C>> {
+        self.value_producers.insert(name.to_string(), producer.0).map(Producer)
+    }
+
+    /// Register a producer made beforehand, unless there is one with the same name already
+    ///
+    /// Modules registering producers on a shared template can't then replace each other's by accident.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::error::RegisterError;
+    /// use edo::producer::Producer;
+    ///
+    /// let mut template: Edo<()> = Edo::new("{date}").unwrap();
+    /// template.try_register_producer("date", Producer::value("2024-01-01")).unwrap();
+    /// let error = template.try_register_producer("date", Producer::value("1/1/2024")).unwrap_err();
+    /// assert_eq!(error, RegisterError::Duplicate { name: "date".to_string() });
+    /// assert_eq!(template.render(&()), "2024-01-01");
+    /// ```
+    pub fn try_register_producer(&mut self, name: &str, producer: Producer<'a, C>) -> Result<(), RegisterError> {
+        if self.value_producers.contains_key(name) {
+            return Err(RegisterError::Duplicate { name: name.to_string() });
+        }
+        self.value_producers.insert(name.to_string(), producer.0);
+        Ok(())
     }
 
     /// Register a static replacement
@@ -95,15 +602,208 @@ impl<'a, C: Clone> Edo<'a, C> {
     /// ```no_run
     /// # #![allow(unused_variables)]
     /// # use edo::Edo;
-    /// let mut template: Edo<&str> = Edo::new("Hello {name}").unwrap();
+    /// let mut template: Edo<str> = Edo::new("Hello {name}").unwrap();
     /// template.register_static("name", "World!");
     /// ```
-    pub fn register_static<S: Into<String>>(&mut self, name: &'a str, input: S) {
-        self.value_producers.insert(name, ValueProducer::Static(input.into()));
+    pub fn register_static<S: Into<String>>(&mut self, name: &str, input: S) -> Option<Producer<'a, C>> {
+        self.value_producers.insert(name.to_string(), ValueProducer::Static(input.into())).map(Producer)
+    }
+
+    /// Register a static list, joined like the value of a [list handler](#method.register_list_handler)
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new("Items: {items}\n- {items:\"\n- \"}").unwrap();
+    /// template.register_static_list("items", vec!["eggs", "milk"]);
+    /// assert_eq!(template.render(""), "Items: eggs, milk\n- eggs\n- milk");
+    /// ```
+    pub fn register_static_list<I>(&mut self, name: &str, items: I) -> Option<Producer<'a, C>> where
+        I: IntoIterator,
+        I::Item: Into<String> {
+        self.value_producers.insert(name.to_string(), ValueProducer::StaticList(items.into_iter().map(Into::into).collect())).map(Producer)
+    }
+
+    /// Register a template that is rendered in place of a placeholder
+    ///
+    /// The nested template uses the same producers and context, so templates can include each other. Its output is inserted without escaping, since its own placeholders are already escaped. Nesting is limited by [`RenderOptions::max_depth`](options/struct.RenderOptions.html#method.max_depth) and a template that includes itself aborts the render.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new("{header} Welcome!").unwrap();
+    /// template.register_template("header", "[{title}]").unwrap();
+    /// template.register_static("title", "Home");
+    /// assert_eq!(template.render(""), "[Home] Welcome!");
+    /// ```
+    pub fn register_template(&mut self, name: &str, template: &'a str) -> Result<Option<Producer<'a, C>>, EdoError> {
+        let template = parse::parse_with_syntax(template, self.syntax)?;
+        Ok(self.value_producers.insert(name.to_string(), ValueProducer::Template(template)).map(Producer))
+    }
+
+    /// Register static replacements for every field of a serializable value
+    ///
+    /// Fields are named by their path from `prefix`, joined with a `.`, so `{user.address.city}` is the `city` of the `address` of the value registered as `user`. Sequence elements are named by their index. With an empty prefix the top level fields are registered under their own names.
+    ///
+    /// # Examples
+    /// ```
+    /// # extern crate edo;
+    /// # extern crate serde;
+    /// # use serde::Serialize;
+    /// # use edo::Edo;
+    /// #[derive(Serialize)]
+    /// struct User {
+    ///     name: String,
+    ///     languages: Vec<String>,
+    /// }
+    ///
+    /// # fn main() {
+    /// let user = User { name: "Gio".to_string(), languages: vec!["Rust".to_string()] };
+    /// let mut template = Edo::new("{user.name} writes {user.languages.0}").unwrap();
+    /// template.register_serialize("user", &user).unwrap();
+    /// assert_eq!(template.render(""), "Gio writes Rust");
+    /// # }
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn register_serialize<T: serde::Serialize + ?Sized>(&mut self, prefix: &str, value: &T) -> Result<(), ser::Error> {
+        for (name, value) in ser::flatten(prefix, value)? {
+            self.value_producers.insert(name, ValueProducer::Static(value));
+        }
+        Ok(())
+    }
+
+    /// Register a fallback that is asked for the value of any placeholder without a handler or static replacement
+    ///
+    /// The fallback gets the placeholder name and the render context. Returning `None` leaves the placeholder empty. Registering a new fallback replaces the old one. With [`use_context_lookup`](#method.use_context_lookup), it is only asked for names the context doesn't have.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new("{greeting} {name}").unwrap();
+    /// template.register_static("greeting", "Hello");
+    /// template.register_fallback(|name, context: &str| match name {
+    ///     "name" => Some(context.to_string()),
+    ///     _ => None,
+    /// });
+    /// assert_eq!(template.render("World"), "Hello World");
+    /// ```
+    pub fn register_fallback<F>(&mut self, fallback: F) where
+        F: 'a + Fn(&str, &C) -> Option<String> {
+        self.fallback = Some(Box::new(fallback));
+    }
+
+    /// Look up placeholders without a handler or static replacement in the render context
+    ///
+    /// Names are looked up with [`Lookup::get`](context/trait.Lookup.html#tymethod.get). Those the context doesn't have go on to the fallback registered with [`register_fallback`](#method.register_fallback), and are reported as [`RenderOptions::missing_producers`](options/struct.RenderOptions.html#method.missing_producers) says if it doesn't have them either.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new("Hello {name}{punctuation}").unwrap();
+    /// template.use_context_lookup();
+    /// template.register_fallback(|name, _| if name == "punctuation" { Some("!".to_string()) } else { None });
+    /// assert_eq!(template.render(&vec![("name", "World")]), "Hello World!");
+    /// assert_eq!(template.render(&vec![("name", "World"), ("punctuation", "?")]), "Hello World?");
+    /// ```
+    pub fn use_context_lookup(&mut self) where C: 'a + Lookup {
+        self.lookup = Some(Box::new(|name, context: &C| context.get(name).map(Cow::into_owned)));
+    }
+
+    /// Register a set of ready made handlers and filters from [`builtins`](builtins/index.html)
+    ///
+    /// They replace any handlers, statics or filters with the same names. Observers that come with them, like the one that resets [`builtins::counter`](builtins/fn.counter.html), are registered too.
+    pub fn register_builtins(&mut self, builtins: Builtins) {
+        for (name, handler) in builtins.handlers {
+            self.value_producers.insert(name, ValueProducer::Builtin(handler));
+        }
+        self.filters.extend(builtins.filters.into_iter().map(|(name, filter)| (name, Rc::from(filter))));
+        for observer in builtins.observers {
+            self.observers.push(observer);
+        }
+    }
+
+    /// Register a filter, used as `{name|filter}`
+    ///
+    /// Filters are applied to a placeholder's value from left to right, before it is escaped. Every template starts with the `urlencode`, `json` and `shell_quote` filters from [`escape`](escape/index.html). Using a filter that isn't registered is reported as an error and leaves the placeholder empty. `raw` is reserved for marking a placeholder as raw, see [`set_escaping`](#method.set_escaping).
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new("{name|upper}, {name|upper|json}").unwrap();
+    /// template.register_static("name", "gio");
+    /// template.register_filter("upper", |value| value.to_uppercase());
+    /// assert_eq!(template.render(""), "GIO, \"GIO\"");
+    /// ```
+    pub fn register_filter<F>(&mut self, name: &str, filter: F) where
+        F: 'static + Fn(&str) -> String {
+        self.filters.insert(name.to_string(), Rc::new(filter));
+    }
+
+    /// Escape the output of every placeholder
+    ///
+    /// Placeholders written as `{!name}` or `{name|raw}` are still inserted raw, for values that are already safe such as pre-rendered HTML.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::escape::Escaping;
+    ///
+    /// let mut template = Edo::new("<p>{comment}</p>{!footer}").unwrap();
+    /// template.set_escaping(Escaping::Html);
+    /// template.register_static("comment", "<script>alert(1)</script>");
+    /// template.register_static("footer", "<hr>");
+    /// assert_eq!(template.render(""), "<p>&lt;script&gt;alert(1)&lt;/script&gt;</p><hr>");
+    /// ```
+    pub fn set_escaping(&mut self, escaping: Escaping) {
+        self.set_encoder(escaping);
+        if escaping == Escaping::ContextualHtml {
+            self.html_contexts = Some(escape::html_contexts(&self.template.expressions()));
+        }
+    }
+
+    /// Encode the output of every placeholder with a custom encoder, replacing the current one
+    ///
+    /// Raw placeholders written as `{!name}` or `{name|raw}` are not encoded. See [`Encoder`](escape/trait.Encoder.html) for an example.
+    pub fn set_encoder<E: 'a + Encoder>(&mut self, encoder: E) {
+        self.encoder = Rc::new(encoder);
+        self.html_contexts = None;
+    }
+
+    /// Set the options used by every render, see [`RenderOptions`](options/struct.RenderOptions.html)
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::options::RenderOptions;
+    ///
+    /// let mut template = Edo::new("{a}{a}").unwrap();
+    /// template.register_static("a", "1234");
+    /// template.set_options(RenderOptions::new().max_output(6));
+    /// assert!(template.try_render("").is_err());
+    /// ```
+    pub fn set_options(&mut self, options: RenderOptions) {
+        self.options = options;
+    }
+
+    /// Translate the literal text of the template with `catalog` when it renders in a locale, replacing the current catalog
+    ///
+    /// Needs the `locale` feature. The locale is set with [`RenderOptions::locale`](options/struct.RenderOptions.html#method.locale) or [`render_in`](#method.render_in), and renders without one aren't translated. See [`catalog`](catalog/index.html) for an example.
+    #[cfg(feature = "locale")]
+    pub fn set_catalog<T: 'a + Catalog>(&mut self, catalog: T) {
+        self.catalog = Some(Box::new(catalog));
+    }
+
+    /// Register an observer that is called as the template renders, see [`observer`](observer/index.html)
+    ///
+    /// Observers are called in the order they were registered.
+    pub fn register_observer<O: 'a + Observer>(&mut self, observer: O) {
+        self.observers.push(Box::new(observer));
     }
 
     /// Render template into a string
     ///
+    /// Returns an empty string if the render is aborted, see [`try_render`](#method.try_render).
+    ///
     /// # Examples
     /// ```
     /// # use edo::Edo;
@@ -112,13 +812,14 @@ impl<'a, C: Clone> Edo<'a, C> {
     /// let output = template.render("");
     /// assert_eq!(output, "Hello World!");
     /// ```
-    // TODO: add a strict mode that errors when there is no handler
-    pub fn render(&mut self, context: C) -> String {
+    pub fn render(&mut self, context: &C) -> String {
         self.render_with_errors(context).0
     }
 
     /// Render a template into a string and recieve a vector of errors
     ///
+    /// If the render is aborted the output is empty and the reason is the last error. Warnings are left out, see [`try_render`](#method.try_render).
+    ///
     /// # Examples
     /// ```
     /// # use edo::Edo;
@@ -126,41 +827,904 @@ impl<'a, C: Clone> Edo<'a, C> {
     /// template.register_handler("name", |_, _| Err("Something Broke".to_string()));
     /// let (output, errors) = template.render_with_errors("");
     /// assert_eq!(output, "Hello ");
-    /// assert_eq!(errors, vec!["Something Broke".to_string()]);
+    /// assert_eq!(errors[0].to_string(), "Something Broke");
+    /// ```
+    pub fn render_with_errors(&mut self, context: &C) -> (String, Vec<RenderError>) {
+        match self.try_render(context) {
+            Ok(report) => (report.output, report.errors),
+            Err(error) => ("".to_string(), vec![error]),
+        }
+    }
+
+    /// Render a template into a report of the output, warnings and errors, or the error that aborted the render
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::error::RenderError;
+    /// use edo::options::RenderOptions;
+    ///
+    /// let mut template = Edo::new("Hello {name}").unwrap();
+    /// template.register_static("name", "World!");
+    /// assert_eq!(template.try_render("").unwrap().output, "Hello World!");
+    ///
+    /// template.set_options(RenderOptions::new().max_output(8));
+    /// assert_eq!(template.try_render(""), Err(RenderError::OutputTooLarge { limit: 8 }));
+    /// ```
+    pub fn try_render(&mut self, context: &C) -> Result<RenderReport, RenderError> {
+        let state = self.new_state(None);
+        self.render_report(state, context)
+    }
+
+    /// The template as a function from a context to its output, for code that takes a formatter function
+    ///
+    /// The function renders like [`render`](#method.render), but it only borrows the template, so renders through it aren't added to the [`metrics`](#method.metrics).
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new("[INFO] {message}").unwrap();
+    /// template.register_handler("message", |_, message: &str| Ok(message.to_string()));
+    /// let lines: Vec<String> = vec!["Started", "Stopped"].into_iter().map(template.as_fn()).collect();
+    /// assert_eq!(lines, vec!["[INFO] Started", "[INFO] Stopped"]);
+    /// ```
+    pub fn as_fn<'s>(&'s self) -> Box<dyn Fn(&C) -> String + 's> {
+        Box::new(move |context| {
+            let mut state = self.new_state(None);
+            self.start_render();
+            self.render_template(context, &mut state).unwrap_or_default()
+        })
+    }
+
+    /// Render a template with `options` instead of the ones set with [`set_options`](#method.set_options), for this render only
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::options::RenderOptions;
+    ///
+    /// let mut template = Edo::new("Hello {name}").unwrap();
+    /// let strict = RenderOptions::new().strict(true);
+    /// assert_eq!(template.render_with_options(&(), &strict), "Hello ");
+    /// assert!(template.try_render_with_options(&(), &strict).unwrap().errors[0].to_string().contains("name"));
+    /// assert!(template.try_render(&()).unwrap().errors.is_empty());
+    /// ```
+    pub fn render_with_options(&mut self, context: &C, options: &RenderOptions) -> String {
+        self.try_render_with_options(context, options).map(|report| report.output).unwrap_or_default()
+    }
+
+    /// Like [`render_with_options`](#method.render_with_options), with the report of [`try_render`](#method.try_render)
+    pub fn try_render_with_options(&mut self, context: &C, options: &RenderOptions) -> Result<RenderReport, RenderError> {
+        let state = self.new_state(Some(options));
+        self.render_report(state, context)
+    }
+
+    /// Render a template with builtins formatting for `locale`, like `de` or `pt-BR`
+    ///
+    /// Needs the `locale` feature. This overrides [`RenderOptions::locale`](options/struct.RenderOptions.html#method.locale) for this render.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::builtins;
+    ///
+    /// let mut template = Edo::new("{num(1234.5, 2)}").unwrap();
+    /// template.register_builtins(builtins::numbers());
+    /// assert_eq!(template.render_in("fr", ""), "1\u{202f}234,50");
+    /// assert_eq!(template.render_in("en", ""), "1,234.50");
+    /// ```
+    #[cfg(feature = "locale")]
+    pub fn render_in(&mut self, locale: &str, context: &C) -> String {
+        self.try_render_in(locale, context).map(|report| report.output).unwrap_or_default()
+    }
+
+    /// Like [`try_render`](#method.try_render), with builtins formatting for `locale`
+    ///
+    /// Needs the `locale` feature.
+    #[cfg(feature = "locale")]
+    pub fn try_render_in(&mut self, locale: &str, context: &C) -> Result<RenderReport, RenderError> {
+        let state = RenderState { locale: Some(locale.to_string()), ..self.new_state(None) };
+        self.render_report(state, context)
+    }
+
+    /// Render a template and recieve a trace of every expression, in the order they were started
+    ///
+    /// Placeholders inside a nested template come just after the placeholder that included it. Tracing costs an allocation per expression, so only use this for debugging.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::report::Resolution;
+    ///
+    /// let mut template = Edo::new("Hello {name(Gio)}").unwrap();
+    /// template.register_handler("name", |args, _| Ok(args[0].to_string()));
+    /// let (report, trace) = template.render_traced("").unwrap();
+    /// assert_eq!(report.output, "Hello Gio");
+    /// assert_eq!(trace.len(), 2);
+    /// assert_eq!(trace[0].output_length, 6);
+    ///
+    /// let placeholder = trace[1].placeholder.as_ref().unwrap();
+    /// assert_eq!((placeholder.name.as_str(), placeholder.resolution), ("name", Resolution::Handler));
+    /// assert_eq!(trace[1].arguments, vec!["Gio".to_string()]);
+    /// ```
+    pub fn render_traced(&mut self, context: &C) -> Result<(RenderReport, Vec<TraceEvent>), RenderError> {
+        let mut state = RenderState { trace: Some(vec![]), ..self.new_state(None) };
+        self.start_render();
+        state.report.output = self.render_template(context, &mut state)?;
+        report::add_metrics(&mut self.metrics, &state.report.placeholders);
+        Ok((state.report, state.trace.unwrap_or_default()))
+    }
+
+    /// Call counts and durations for each placeholder name, added up over every finished render of this template
+    ///
+    /// Use this to find the placeholders that make renders slow. [`RenderReport::metrics`](report/struct.RenderReport.html#method.metrics) has the same numbers for a single render.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new("{a} {a} {b}").unwrap();
+    /// template.register_static("a", "1");
+    /// template.register_handler("b", |_, _| Err("slow and broken".to_string()));
+    /// template.render("");
+    /// template.render("");
+    /// assert_eq!(template.metrics()["a"].calls, 4);
+    /// assert_eq!(template.metrics()["b"].failures, 2);
+    /// ```
+    pub fn metrics(&self) -> &BTreeMap<String, PlaceholderMetrics> {
+        &self.metrics
+    }
+
+    /// Forget the metrics collected so far
+    pub fn reset_metrics(&mut self) {
+        self.metrics.clear();
+    }
+
+    /// Check the template against what is registered without rendering it
+    ///
+    /// Finds placeholders whose handler was registered with an arity they don't match, as [`RenderError::WrongArity`](error/enum.RenderError.html#variant.WrongArity). Nested templates are checked too, their errors have spans into their own templates.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template: Edo<()> = Edo::new("{pad(x)} {pad(x, 10, -, y)}").unwrap();
+    /// template.register_handler_with_arity("pad", 1..=3, |args, _| Ok(args[0].to_string()));
+    /// let errors = template.validate();
+    /// assert_eq!(errors.len(), 1);
+    /// assert_eq!(errors[0].to_string(), "pad takes 1 to 3 arguments but was given 4");
+    /// ```
+    pub fn validate(&self) -> Vec<RenderError> {
+        let mut errors = vec![];
+        let templates = self.value_producers.values().filter_map(|producer| match *producer {
+            ValueProducer::Template(ref nested) => Some(Cow::Borrowed(&nested[..])),
+            ValueProducer::Section { ref body, .. } => Some(Cow::Owned(body.expressions())),
+            _ => None,
+        });
+        for expression in core::iter::once(self.template.expressions()).chain(templates).collect::<Vec<_>>().iter().flat_map(|template| template.iter()) {
+            self.validate_expression(expression, &mut errors);
+        }
+        errors
+    }
+
+    fn validate_expression(&self, expression: &Expression, errors: &mut Vec<RenderError>) {
+        if let Expression::Function { name, ref arguments, ref argument_spans, span, .. } = *expression {
+            if let Some(&ValueProducer::Handler(_, Some(expected))) = self.value_producers.get(name) {
+                if !expected.allows(arguments.len()) {
+                    let span = extra_arguments(expected, argument_spans, span);
+                    errors.push(RenderError::WrongArity { name: name.to_string(), expected, found: arguments.len(), span });
+                }
+            }
+            for (index, argument) in arguments.iter().enumerate() {
+                if let Some(reference) = reference(argument, argument_spans.get(index).cloned().unwrap_or(span)) {
+                    self.validate_expression(&reference, errors);
+                }
+            }
+        }
+    }
+
+    /// The parsed template, literal text and placeholders in order, for rendering or analysing it another way
+    ///
+    /// Iterating over `&edo` does the same. The expressions borrow from the template, so text it keeps a copy of, like the source given to [`parse_into`](#method.parse_into), can't outlive it.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::parse::Expression;
+    ///
+    /// let template: Edo<()> = Edo::new("Hi {name|upper}!").unwrap();
+    /// let names: Vec<_> = template.segments().filter_map(|segment| match segment {
+    ///     Expression::Function { name, ref filters, .. } => Some((name, filters.clone())),
+    ///     Expression::Literal(_) => None,
+    /// }).collect();
+    /// assert_eq!(names, vec![("name", vec!["upper"])]);
+    /// assert_eq!((&template).into_iter().count(), 3);
+    /// ```
+    pub fn segments(&self) -> alloc::vec::IntoIter<Expression<'_>> {
+        self.template.expressions().into_owned().into_iter()
+    }
+
+    /// The literal text of the template that a [`catalog`](catalog/index.html) would translate, for making translation files
+    ///
+    /// Each message has its place in the template and the names of the placeholders next to it, which give translators context. The messages borrow from the template.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let template: Edo<()> = Edo::new("Hello {name}, welcome back").unwrap();
+    /// let messages: Vec<_> = template.extract_messages().iter().map(|message| (message.text, message.span.start)).collect();
+    /// assert_eq!(messages, vec![("Hello", 0), (", welcome back", 12)]);
+    /// ```
+    pub fn extract_messages(&self) -> Vec<Message<'_>> {
+        catalog::messages(&self.template.expressions())
+    }
+
+    /// The placeholders this template uses and their numbers of arguments, without the ones in nested templates
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let template: Edo<()> = Edo::new("{greeting(formal)}, {name}").unwrap();
+    /// let signature = template.signature();
+    /// assert_eq!(signature.placeholders().keys().collect::<Vec<_>>(), vec!["greeting", "name"]);
+    /// assert!(signature.placeholders()["greeting"].contains(&1));
     /// ```
-    pub fn render_with_errors(&mut self, context: C) -> (String, Vec<String>) {
-        // Keep track of errors
-        let mut errors: Vec<String> = vec![];
+    pub fn signature(&self) -> Signature {
+        Signature::of(&self.template.expressions())
+    }
+
+    /// Fill in the placeholders that have a producer, making a new template with the others left as they are
+    ///
+    /// Values are filtered and escaped as in a full render. The new template keeps the escaping, filters and options of this one, so the remaining placeholders are escaped the same way, but has no producers, register those of the remaining placeholders on it. Handler failures leave their placeholders empty, the render is only an error if it's aborted.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// let mut template = Edo::new("{site} - {page}").unwrap();
+    /// template.register_static("site", "Edo");
+    /// let mut page = template.render_partial("").unwrap();
+    ///
+    /// page.register_static("page", "Home");
+    /// assert_eq!(page.render(""), "Edo - Home");
+    /// ```
+    pub fn render_partial(&self, context: &C) -> Result<Edo<'a, C>, RenderError> {
+        let mut state = self.new_state(None);
+        self.start_render();
+        let expressions = self.template.expressions();
+        let mut values = Vec::with_capacity(expressions.len());
+        for (index, expression) in expressions.iter().enumerate() {
+            values.push(match *expression {
+                Expression::Function { name, .. } if self.produces(name, context) => {
+                    let html_contexts = self.html_contexts.as_ref().map(|contexts| &contexts[index..=index]);
+                    Some(self.render_expressions(core::slice::from_ref(expression), html_contexts, context, &mut state)?)
+                },
+                _ => None,
+            });
+        }
+        let template: Vec<_> = expressions.iter().zip(&values).map(|(expression, value)| match (expression, value) {
+            (_, Some(value)) => Expression::Literal(value),
+            (&Expression::Literal(text), None) => Expression::Literal(text),
+            (&Expression::Function { name, ref arguments, ref argument_spans, has_parentheses, case, ref filters, format, raw, span }, None) => Expression::Function {
+                name,
+                arguments: arguments.clone(),
+                argument_spans: argument_spans.clone(),
+                has_parentheses,
+                case,
+                filters: filters.clone(),
+                format,
+                raw,
+                span,
+            },
+        }).collect();
+        // The new template keeps a copy of all of its text, so it doesn't borrow from this one
+        let mut partial = Edo {
+            template: Parsed::Owned(Compiled::copy(&template)),
+            syntax: self.syntax,
+            filters: self.filters.clone(),
+            encoder: Rc::clone(&self.encoder),
+            options: self.options.clone(),
+            ..Edo::default()
+        };
+        if self.html_contexts.is_some() {
+            partial.html_contexts = Some(escape::html_contexts(&partial.template.expressions()));
+        }
+        Ok(partial)
+    }
+
+    // Whether a placeholder would get a value from a producer, the context or the fallback
+    fn produces(&self, name: &str, context: &C) -> bool {
+        self.value_producers.contains_key(name) || self.looked_up(name, context).is_some()
+    }
+
+    // The value of a placeholder without a producer, from the context or else the fallback
+    fn looked_up(&self, name: &str, context: &C) -> Option<(Resolution, String)> {
+        self.lookup.as_ref().and_then(|lookup| lookup(name, context)).map(|value| (Resolution::Context, value))
+            .or_else(|| self.fallback.as_ref().and_then(|fallback| fallback(name, context)).map(|value| (Resolution::Fallback, value)))
+    }
+
+    /// Render another parsed template using this template's producers and settings
+    ///
+    /// `html_contexts` are those of `template`, used if the escaping is contextual.
+    pub(crate) fn render_parsed(&self, template: &[Expression], html_contexts: &[HtmlContext], context: &C) -> Result<RenderReport, RenderError> {
+        let html_contexts = self.html_contexts.as_ref().map(|_| html_contexts);
+        let mut state = self.new_state(None);
+        self.start_render();
+        state.report.output = self.render_prefixed(template, html_contexts, context, &mut state)?;
+        Ok(state.report)
+    }
+
+    /// Render an engine template, called `name` if it came from the loader, which can include the templates in `partials`
+    ///
+    /// `producers` are looked up before this template's own, and `locale` is used instead of the one in the options.
+    pub(crate) fn render_with_partials(&self, name: Option<&str>, template: &[Expression], partials: &Partials, producers: Option<&BTreeMap<String, ValueProducer<C>>>, locale: Option<&str>, context: &C) -> Result<RenderReport, RenderError> {
+        let html_contexts = self.html_contexts.as_ref().map(|_| escape::html_contexts(template));
+        let includes = name.map(str::to_string).into_iter().collect();
+        let mut state = RenderState { partials: Some(partials), includes, producers, ..self.new_state(None) };
+        if let Some(locale) = locale {
+            state.locale = Some(locale.to_string());
+        }
+        self.start_render();
+        state.report.output = self.render_expressions(template, html_contexts.as_deref(), context, &mut state)?;
+        Ok(state.report)
+    }
+
+    fn render_expressions(&self, template: &[Expression], html_contexts: Option<&[HtmlContext]>, context: &C, state: &mut RenderState<C>) -> Result<String, RenderError> {
+        let mut output = String::new();
+        // Set when a standalone placeholder was removed, so the rest of its line goes too
+        let mut trim_line = false;
+
+        // Iterate over the template and
+        // 1. Leave literals untouched
+        // 2. Call the handlers for each function call and replace within the output
+        for (index, expression) in template.iter().enumerate() {
+            // Trace events are added before rendering so nested templates come after the placeholder that includes them
+            let event = match state.trace {
+                Some(ref mut trace) => {
+                    trace.push(TraceEvent {
+                        template: state.stack.last().cloned(),
+                        index,
+                        placeholder: None,
+                        arguments: vec![],
+                        output_length: 0,
+                    });
+                    Some(trace.len() - 1)
+                },
+                None => None,
+            };
+
+            let piece = match *expression {
+                #[cfg(feature = "locale")]
+                Expression::Literal(text) if self.catalog.is_some() => self.translated(text, state),
+                #[cfg(feature = "normalize")]
+                Expression::Literal(text) if self.options(state).get_normalize_literals() =>
+                    nfc(text).unwrap_or_else(|| text.to_string()),
+                Expression::Literal(text) => text.to_string(),
+                Expression::Function { name, ref arguments, span, .. } if !self.options(state).is_allowed(name) => {
+                    for observer in &self.observers {
+                        observer.on_placeholder_start(name, arguments, span);
+                    }
+                    self.report(state, Severity::Error, RenderError::NotAllowed { name: name.to_string(), span });
+                    self.finish_placeholder(state, PlaceholderOutcome { name: name.to_string(), span, resolution: Resolution::Skipped, duration: None });
+                    "".to_string()
+                },
+                Expression::Function { name, ref arguments, ref argument_spans, has_parentheses, case, ref filters, format, raw, span } => {
+                    for observer in &self.observers {
+                        observer.on_placeholder_start(name, arguments, span);
+                    }
+                    let timer = Timer::start();
+                    let mut raw = raw;
+                    let html_context = html_contexts.map(|html_contexts| html_contexts[index]);
+                    let partial = match (state.partials, arguments.as_slice()) {
+                        (Some(partials), [included]) if name == "include" => partials.get(*included).map(|partial| (*included, *partial)),
+                        _ => None,
+                    };
+                    let producer = state.producers.and_then(|producers| producers.get(name)).or_else(|| self.value_producers.get(name));
+                    // Specs in hand built expressions that don't parse are left out
+                    let format = format.and_then(Format::parse);
+                    let separator = format.and_then(|format| format.separator()).unwrap_or_else(|| self.options(state).get_list_separator());
+                    let (resolution, mut value) = match (partial, producer) {
+                        (Some((included, partial)), _) => {
+                            raw = true;
+                            (Resolution::Template, self.render_include(included, partial, html_context, context, state)?)
+                        },
+                        (None, None) if name == "." && !state.items.is_empty() => (Resolution::Static, state.items[state.items.len() - 1].clone()),
+                        (None, None) => match self.looked_up(name, context) {
+                            Some(resolved) => resolved,
+                            None => {
+                                let severity = self.options(state).get_missing_producers();
+                                self.report(state, severity, RenderError::MissingProducer { name: name.to_string(), span });
+                                (Resolution::Skipped, "".to_string())
+                            },
+                        },
+                        (None, Some(value_producer)) => match *value_producer {
+                            ValueProducer::Handler(_, Some(expected)) if !expected.allows(arguments.len()) => {
+                                let span = extra_arguments(expected, argument_spans, span);
+                                self.report(state, Severity::Error, RenderError::WrongArity { name: name.to_string(), expected, found: arguments.len(), span });
+                                (Resolution::Failed, "".to_string())
+                            },
+                            ValueProducer::Handler(ref handler, _) => {
+                                let values = self.argument_values(arguments, argument_spans, span, context, state)?;
+                                let args = Args::borrow(&values, has_parentheses);
+                                let result = handler(args.clone(), context);
+                                self.handled(state, name, &args, span, result)
+                            },
+                            ValueProducer::ListHandler(ref handler) => {
+                                let values = self.argument_values(arguments, argument_spans, span, context, state)?;
+                                let args = Args::borrow(&values, has_parentheses);
+                                let result = handler(args.clone(), context).map(|items| items.join(separator));
+                                self.handled(state, name, &args, span, result)
+                            },
+                            #[cfg(feature = "locale")]
+                            ValueProducer::Localized(ref handler) => {
+                                let values = self.argument_values(arguments, argument_spans, span, context, state)?;
+                                let args = Args::borrow(&values, has_parentheses);
+                                let result = handler(args.clone(), context, state.locale.as_deref());
+                                self.handled(state, name, &args, span, result)
+                            },
+                            ValueProducer::Builtin(ref handler) => {
+                                let values = self.argument_values(arguments, argument_spans, span, context, state)?;
+                                let args = Args::borrow(&values, has_parentheses);
+                                let result = handler(args.as_slice().to_vec(), state.locale.as_deref());
+                                self.handled(state, name, &args, span, result)
+                            },
+                            ValueProducer::Static(ref value) => (Resolution::Static, value.clone()),
+                            ValueProducer::StaticList(ref items) => (Resolution::Static, items.join(separator)),
+                            ValueProducer::Template(ref nested) => {
+                                raw = true;
+                                (Resolution::Template, self.render_nested(name, nested, html_context, context, state)?)
+                            },
+                            ValueProducer::Section { ref value, inverted, ref body } => {
+                                raw = true;
+                                let body = &body.expressions();
+                                let items = self.section_items(value, span, context, state)?;
+                                let mut output = String::new();
+                                if inverted && items.is_empty() {
+                                    output = self.render_nested(name, body, html_context, context, state)?;
+                                }
+                                for item in items.into_iter().filter(|_| !inverted) {
+                                    state.items.push(item);
+                                    let rendered = self.render_nested(name, body, html_context, context, state);
+                                    state.items.pop();
+                                    output += &rendered?;
+                                }
+                                (Resolution::Template, output)
+                            },
+                        },
+                    };
+                    #[cfg(feature = "normalize")]
+                    {
+                        if let Some(normalized) = nfc(&value).filter(|_| self.options(state).get_normalize_output()) {
+                            value = normalized;
+                        }
+                    }
+                    if let Some(case) = case {
+                        value = case.apply(&value);
+                    }
+                    for filter in filters {
+                        match self.filters.get(*filter) {
+                            Some(filter) => value = filter(&value),
+                            None => {
+                                self.report(state, Severity::Error, RenderError::UnknownFilter { name: filter.to_string(), span });
+                                value = "".to_string();
+                                break;
+                            },
+                        }
+                    }
+                    if let Some(format) = format {
+                        // Padding is at least a byte a character, so a width that can't fit isn't allocated
+                        if let Some(limit) = self.options(state).get_max_output().filter(|limit| output.len() + format.width().unwrap_or(0) > *limit) {
+                            return Err(self.abort(RenderError::OutputTooLarge { limit }));
+                        }
+                        value = format.apply(&value);
+                    }
+                    let mut piece = match html_contexts {
+                        _ if raw || state.arguments > 0 => value,
+                        Some(html_contexts) => html_contexts[index].escape(&value),
+                        None => self.encoder.encode(value),
+                    };
+                    if self.options(state).get_isolate_bidi() && state.arguments == 0 && resolution != Resolution::Template && !piece.is_empty() {
+                        piece = format!("\u{2068}{}\u{2069}", piece);
+                    }
+                    self.finish_placeholder(state, PlaceholderOutcome { name: name.to_string(), span, resolution, duration: timer.elapsed() });
+                    piece
+                }
+            };
+            let piece = match *expression {
+                Expression::Literal(_) if self.options(state).get_pseudo_localize() => pseudo::localize(&piece),
+                _ => piece,
+            };
+
+            if let Expression::Literal(text) = *expression {
+                for observer in &self.observers {
+                    observer.on_literal(text);
+                }
+            }
+
+            if let Some(event) = event {
+                let (placeholder, arguments) = match *expression {
+                    Expression::Function { ref arguments, .. } => (
+                        state.report.placeholders.last().cloned(),
+                        arguments.iter().map(|argument| argument.to_string()).collect(),
+                    ),
+                    Expression::Literal(_) => (None, vec![]),
+                };
+                if let Some(ref mut trace) = state.trace {
+                    trace[event].placeholder = placeholder;
+                    trace[event].arguments = arguments;
+                    trace[event].output_length = piece.len();
+                }
+            }
+
+            if self.options(state).get_fail_fast() && !state.report.errors.is_empty() {
+                return Err(state.report.errors.remove(0));
+            }
+            let piece = match *expression {
+                Expression::Literal(_) if trim_line => {
+                    trim_line = false;
+                    match piece.find('\n') {
+                        Some(end) => piece[end + 1..].to_string(),
+                        None => "".to_string(),
+                    }
+                },
+                Expression::Function { .. } if piece.is_empty() && self.options(state).get_trim_standalone() && standalone(template, index) => {
+                    output.truncate(output.trim_end_matches([' ', '\t']).len());
+                    trim_line = true;
+                    piece
+                },
+                Expression::Function { .. } if self.options(state).get_indent_continuations() && state.arguments == 0 && piece.contains('\n') => {
+                    let line = &output[output.rfind('\n').map_or(0, |end| end + 1)..];
+                    indent(&piece, &line[..line.len() - line.trim_start_matches([' ', '\t']).len()])
+                },
+                _ => piece,
+            };
+            if let Some(limit) = self.options(state).get_max_output() {
+                if output.len() + piece.len() > limit {
+                    return Err(self.abort(RenderError::OutputTooLarge { limit }));
+                }
+            }
+            output.push_str(&piece);
+        }
+        Ok(output)
+    }
+
+    // The values of the arguments, unescaped or rendered if they are placeholders, in order
+    // Expressions built by hand might not have argument spans, their arguments get the span of the placeholder
+    fn argument_values<'s>(&self, arguments: &[&'s str], spans: &[Span], span: Span, context: &C, state: &mut RenderState<C>) -> Result<Vec<Cow<'s, str>>, RenderError> {
+        let mut values = Vec::with_capacity(arguments.len());
+        for (index, argument) in arguments.iter().enumerate() {
+            values.push(match reference(argument, spans.get(index).cloned().unwrap_or(span)) {
+                Some(reference) => {
+                    state.arguments += 1;
+                    // Arguments nest as deeply as templates do, so they count towards the same limit
+                    let limit = self.options(state).get_max_depth();
+                    if state.stack.len() + state.arguments > limit {
+                        return Err(self.abort(RenderError::DepthLimitExceeded { limit, chain: state.stack.clone() }));
+                    }
+                    let value = self.render_expressions(core::slice::from_ref(&reference), None, context, state);
+                    state.arguments -= 1;
+                    Cow::Owned(value?)
+                },
+                None => parse::unescape(argument),
+            });
+        }
+        Ok(values)
+    }
+
+    // The items a mustache section is rendered for, those of a list or a value that isn't empty or `false`
+    fn section_items(&self, name: &str, span: Span, context: &C, state: &mut RenderState<C>) -> Result<Vec<String>, RenderError> {
+        let producer = state.producers.and_then(|producers| producers.get(name)).or_else(|| self.value_producers.get(name));
+        match producer {
+            Some(ValueProducer::StaticList(items)) => Ok(items.clone()),
+            Some(ValueProducer::ListHandler(handler)) => match handler(Args::new(vec![], false), context) {
+                Ok(items) => Ok(items),
+                Err(message) => {
+                    self.handled(state, name, &[], span, Err(message));
+                    Ok(vec![])
+                },
+            },
+            _ => {
+                let value = Expression::Function { name, arguments: vec![], argument_spans: vec![], has_parentheses: false, case: None, filters: vec![], format: None, raw: true, span };
+                state.arguments += 1;
+                let value = self.render_expressions(core::slice::from_ref(&value), None, context, state);
+                state.arguments -= 1;
+                let value = value?;
+                Ok(if value.is_empty() || value == "false" { vec![] } else { vec![value] })
+            },
+        }
+    }
+
+    fn render_include(&self, name: &str, template: &[Expression], html_context: Option<HtmlContext>, context: &C, state: &mut RenderState<C>) -> Result<String, RenderError> {
+        if let Some(start) = state.includes.iter().position(|entered| entered == name) {
+            let mut cycle = state.includes[start..].to_vec();
+            cycle.push(name.to_string());
+            return Err(self.abort(RenderError::IncludeCycle { cycle }));
+        }
+        state.includes.push(name.to_string());
+        let output = self.render_nested(name, template, html_context, context, state)?;
+        state.includes.pop();
+        Ok(output)
+    }
+
+    // Contextual escaping carries on from the context of the placeholder the template is rendered for
+    fn render_nested(&self, name: &str, template: &[Expression], html_context: Option<HtmlContext>, context: &C, state: &mut RenderState<C>) -> Result<String, RenderError> {
+        if let Some(start) = state.stack.iter().position(|entered| entered == name) {
+            let mut cycle = state.stack[start..].to_vec();
+            cycle.push(name.to_string());
+            return Err(self.abort(RenderError::RecursiveTemplate { cycle }));
+        }
+        state.stack.push(name.to_string());
+        let limit = self.options(state).get_max_depth();
+        if state.stack.len() + state.arguments > limit {
+            return Err(self.abort(RenderError::DepthLimitExceeded { limit, chain: state.stack.clone() }));
+        }
+
+        let html_contexts = html_context.map(|html_context| escape::html_contexts_in(template, html_context));
+        let output = self.render_expressions(template, html_contexts.as_deref(), context, state)?;
+        state.stack.pop();
+        Ok(output)
+    }
+
+    // Render the template from a fresh state, adding to the metrics
+    fn render_report(&mut self, mut state: RenderState<C>, context: &C) -> Result<RenderReport, RenderError> {
+        self.start_render();
+        state.report.output = self.render_template(context, &mut state)?;
+        report::add_metrics(&mut self.metrics, &state.report.placeholders);
+        Ok(state.report)
+    }
+
+    // Render the whole template, with the line prefix if there is one
+    fn render_template(&self, context: &C, state: &mut RenderState<C>) -> Result<String, RenderError> {
+        self.render_prefixed(&self.template.expressions(), self.html_contexts.as_deref(), context, state)
+    }
+
+    // Render `template`, with the line prefix if there is one
+    fn render_prefixed(&self, template: &[Expression], html_contexts: Option<&[HtmlContext]>, context: &C, state: &mut RenderState<C>) -> Result<String, RenderError> {
+        let output = self.render_expressions(template, html_contexts, context, state)?;
+        let prefix = match self.options(state).get_line_prefix() {
+            Some(prefix) if !output.is_empty() => prefix,
+            _ => return Ok(output),
+        };
+        let mut prefixed = String::with_capacity(output.len() + prefix.len() * (output.matches('\n').count() + 1));
+        for line in output.split_inclusive('\n') {
+            prefixed.push_str(prefix);
+            prefixed.push_str(line);
+        }
+        match self.options(state).get_max_output() {
+            Some(limit) if prefixed.len() > limit => Err(self.abort(RenderError::OutputTooLarge { limit })),
+            _ => Ok(prefixed),
+        }
+    }
+
+    // The state a render starts with
+    fn new_state<'r>(&self, options: Option<&'r RenderOptions>) -> RenderState<'r, C> {
+        RenderState {
+            stack: vec![],
+            partials: None,
+            includes: vec![],
+            producers: None,
+            arguments: 0,
+            report: RenderReport::default(),
+            trace: None,
+            #[cfg(feature = "locale")]
+            locale: options.unwrap_or(&self.options).get_locale().map(str::to_string),
+            #[cfg(not(feature = "locale"))]
+            locale: None,
+            options,
+            items: vec![],
+        }
+    }
+
+    // The options of this render
+    fn options<'s>(&'s self, state: &RenderState<'s, C>) -> &'s RenderOptions {
+        state.options.unwrap_or(&self.options)
+    }
+
+    // A literal in the locale of the render, if the catalog translates it
+    #[cfg(feature = "locale")]
+    fn translated(&self, text: &str, state: &RenderState<C>) -> String {
+        match (&self.catalog, &state.locale) {
+            (Some(catalog), Some(locale)) => catalog::translate(&**catalog, self.options(state).get_locale_chain(), locale, text)
+                .unwrap_or_else(|| text.to_string()),
+            _ => text.to_string(),
+        }
+    }
+
+    // Tell the observers a render is starting
+    fn start_render(&self) {
+        for observer in &self.observers {
+            observer.on_render_start();
+        }
+    }
+
+    // Record a problem, telling the observers unless it's ignored
+    fn report(&self, state: &mut RenderState<C>, severity: Severity, error: RenderError) {
+        if severity != Severity::Ignore {
+            for observer in &self.observers {
+                observer.on_error(&error);
+            }
+        }
+        state.report.report(severity, error);
+    }
+
+    // Turn what a handler returned into the placeholder's value, reporting failures
+    fn handled(&self, state: &mut RenderState<C>, name: &str, arguments: &[&str], span: Span, result: Result<String, String>) -> (Resolution, String) {
+        match result {
+            Ok(string) => (Resolution::Handler, string),
+            Err(message) => {
+                let severity = self.options(state).get_handler_failures();
+                self.report(state, severity, RenderError::HandlerFailed {
+                    name: name.to_string(),
+                    args: arguments.iter().map(|argument| argument.to_string()).collect(),
+                    message,
+                    span,
+                });
+                (Resolution::Failed, "".to_string())
+            },
+        }
+    }
+
+    fn finish_placeholder(&self, state: &mut RenderState<C>, outcome: PlaceholderOutcome) {
+        for observer in &self.observers {
+            observer.on_placeholder_end(&outcome);
+        }
+        state.report.placeholders.push(outcome);
+    }
+
+    // Tell the observers about an error that stops the render
+    fn abort(&self, error: RenderError) -> RenderError {
+        for observer in &self.observers {
+            observer.on_error(&error);
+        }
+        error
+    }
+}
+
+impl<'e, 'a, C: ?Sized> IntoIterator for &'e Edo<'a, C> {
+    type Item = Expression<'e>;
+    type IntoIter = alloc::vec::IntoIter<Expression<'e>>;
+
+    fn into_iter(self) -> alloc::vec::IntoIter<Expression<'e>> {
+        self.segments()
+    }
+}
+
+/// Templates are equal when they parse to the same literals and placeholders, whatever is registered on them
+///
+/// Spans are left out, see [`Expression::same_structure`](parse/enum.Expression.html#method.same_structure), so whitespace inside placeholders doesn't matter. Tests can check that two sources mean the same, and caches can keep one of them.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// let mut a: Edo<()> = Edo::new("Hi {name(formal)|upper}").unwrap();
+/// a.register_static("name", "Gio");
+/// assert!(a == Edo::new("Hi { name( formal ) | upper }").unwrap());
+/// assert!(a != Edo::new("Hi {name}").unwrap());
+/// ```
+impl<'a, 'b, C: ?Sized> PartialEq<Edo<'b, C>> for Edo<'a, C> {
+    fn eq(&self, other: &Edo<'b, C>) -> bool {
+        let (template, other) = (self.template.expressions(), other.template.expressions());
+        template.len() == other.len() && template.iter().zip(other.iter()).all(|(a, b)| a.same_structure(b))
+    }
+}
+
+/// An empty template, which renders to nothing until it is given one with [`parse_into`](#method.parse_into) or [`set_template`](#method.set_template)
+impl<'a, C: ?Sized> Default for Edo<'a, C> {
+    fn default() -> Edo<'a, C> {
+        Edo::from_expressions(vec![])
+    }
+}
+
+impl<'a, T: Default> Edo<'a, Mut<T>> {
+    /// Render a template whose handlers can change `context`
+    ///
+    /// Handlers get the context in a [`Mut`](context/struct.Mut.html), and what they change is in `context` once the render is done. The context is moved out for the render, so it is left at its default if a handler panics.
+    pub fn render_mut(&mut self, context: &mut T) -> String {
+        self.try_render_mut(context).map(|report| report.output).unwrap_or_default()
+    }
+
+    /// Like [`render_mut`](#method.render_mut), with the report of [`try_render`](#method.try_render)
+    pub fn try_render_mut(&mut self, context: &mut T) -> Result<RenderReport, RenderError> {
+        let shared = Mut::new(core::mem::take(context));
+        let report = self.try_render(&shared);
+        *context = shared.into_inner();
+        report
+    }
+}
+
+// The NFC form of `text`, or `None` if it is already normalized
+#[cfg(feature = "normalize")]
+fn nfc(text: &str) -> Option<String> {
+    use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+    match is_nfc_quick(text.chars()) {
+        IsNormalized::Yes => None,
+        _ => Some(text.nfc().collect()),
+    }
+}
+
+// The placeholder an argument refers to, with spans moved to where the argument is in the template
+fn reference<'s>(argument: &'s str, span: Span) -> Option<Expression<'s>> {
+    let mut reference = parse::reference(argument)?;
+    reference.move_to(span.start);
+    Some(reference)
+}
+
+// Put `indentation` before every line of `value` after the first, except empty ones
+fn indent(value: &str, indentation: &str) -> String {
+    let mut lines = value.split('\n');
+    let mut output = lines.next().unwrap_or("").to_string();
+    for line in lines {
+        output.push('\n');
+        if !line.is_empty() && line != "\r" {
+            output.push_str(indentation);
+        }
+        output.push_str(line);
+    }
+    output
+}
 
-        // Iterate over the template and
-        // 1. Leave literals untouched
-        // 2. Call the handlers for each function call and replace within the output
-        (self.template.iter()
-            .map(|expression| match *expression {
-                Expression::Literal(text) => text.to_string(),
-                Expression::Function { name, ref arguments } => {
-                    match self.value_producers.get(name) {
-                        None => "".to_string(),
-                        Some(value_producer) => match value_producer {
-                            &ValueProducer::Handler(ref handler) => match handler(arguments.clone(), context.clone()) {
-                                Ok(string) => string,
-                                Err(error_string) => {
-                                    errors.push(error_string);
-                                    "".to_string()
-                                },
-                            },
-                            &ValueProducer::Static(ref value) => value.clone(),
-                        },
-                    }
-                }
-            })
-            .collect::<Vec<String>>()
-            .concat(), errors)
+// Whether the placeholder at `index` is the only thing on its line, apart from spaces and tabs
+fn standalone(template: &[Expression], index: usize) -> bool {
+    let blank = |text: &str| text.trim_matches([' ', '\t']).is_empty();
+    let before = match index.checked_sub(1).map(|before| (before, &template[before])) {
+        None => true,
+        Some((before, &Expression::Literal(text))) => match text.rfind('\n') {
+            Some(start) => blank(&text[start + 1..]),
+            None => before == 0 && blank(text),
+        },
+        Some(_) => false,
+    };
+    let after = match template.get(index + 1) {
+        None => true,
+        Some(&Expression::Literal(text)) => match text.find('\n') {
+            Some(end) => blank(text[..end].strip_suffix('\r').unwrap_or(&text[..end])),
+            None => index + 2 == template.len() && blank(text),
+        },
+        Some(_) => false,
+    };
+    before && after
+}
+
+// Remove the indentation every line that isn't blank starts with, see `Edo::new_dedented`
+fn dedent(text: &str) -> String {
+    let text = text.strip_prefix('\n').or_else(|| text.strip_prefix("\r\n")).unwrap_or(text);
+    let whitespace = |c: char| c == ' ' || c == '\t';
+    let blank = |line: &str| line.trim_start_matches(whitespace).trim_end_matches(['\r', '\n']).is_empty();
+    let indentation = text.split_inclusive('\n')
+        .filter(|line| !blank(line))
+        .map(|line| line.len() - line.trim_start_matches(whitespace).len())
+        .min()
+        .unwrap_or(0);
+    let mut output = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        if blank(line) {
+            output.push_str(line.trim_start_matches(whitespace));
+        } else {
+            output.push_str(&line[indentation..]);
+        }
+    }
+    output
+}
+
+// Where the arguments past what a handler takes are, or the whole placeholder when there are too few
+fn extra_arguments(expected: Arity, spans: &[Span], span: Span) -> Span {
+    match (expected.max().and_then(|max| spans.get(max)), spans.last()) {
+        (Some(first), Some(last)) => Span { start: first.start, end: last.end },
+        _ => span,
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    use args::{Args, Arity};
+    use error::{EdoError, RenderError};
+    use escape::Escaping;
+    use observer::Observer;
+    use options::RenderOptions;
+    use parse::{Span, Syntax, MAX_NESTING};
+    use report::{PlaceholderOutcome, Resolution, Severity};
     use super::Edo;
 
     #[test]
@@ -173,29 +1737,29 @@ mod tests {
     fn register_handler() {
         let mut edo: Edo<&str> = match Edo::new("Hello {name}") {
             Ok(edo) => edo,
-            Err(err) => panic!(err),
+            Err(err) => panic!("{}", err),
         };
-        edo.register_handler("name", |_, context| Ok("World!".to_string()));
-        assert!(edo.value_producers.get("name").is_some());
+        edo.register_handler("name", |_, _| Ok("World!".to_string()));
+        assert!(edo.value_producers.contains_key("name"));
     }
 
     #[test]
     fn register_static() {
         let mut edo: Edo<&str> = match Edo::new("Hello {name}") {
             Ok(edo) => edo,
-            Err(err) => panic!(err),
+            Err(err) => panic!("{}", err),
         };
         edo.register_static("name", "World!");
-        assert!(edo.value_producers.get("name").is_some());
+        assert!(edo.value_producers.contains_key("name"));
     }
 
     #[test]
     fn render_template() {
         let mut edo = match Edo::new("Hello {name}") {
             Ok(edo) => edo,
-            Err(err) => panic!(err),
+            Err(err) => panic!("{}", err),
         };
-        edo.register_handler("name", |_, context| Ok("World!".to_string()));
+        edo.register_handler("name", |_, _| Ok("World!".to_string()));
         assert_eq!(
             edo.render(""),
             "Hello World!"
@@ -206,7 +1770,7 @@ mod tests {
     fn render_template_with_missing_handler() {
         let mut edo = match Edo::new("Hello {name}") {
             Ok(edo) => edo,
-            Err(err) => panic!(err),
+            Err(err) => panic!("{}", err),
         };
         assert_eq!(
             edo.render(""),
@@ -214,11 +1778,367 @@ mod tests {
         );
     }
 
+    #[test]
+    fn render_template_with_fallback() {
+        let mut edo = match Edo::new("{a} {b} {c}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("a", "static");
+        edo.register_fallback(|name, context: &str| if name == "c" {
+            None
+        } else {
+            Some(format!("{} {}", context, name))
+        });
+        assert_eq!(
+            edo.render("fallback"),
+            "static fallback b "
+        );
+    }
+
+    #[test]
+    fn render_template_with_html_escaping() {
+        let mut edo = match Edo::new("<a title=\"{title}\">{name}</a>{!raw}{raw|raw}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.set_escaping(Escaping::Html);
+        edo.register_static("title", "\"quoted\"");
+        edo.register_handler("name", |_, _| Ok("Tom & Jerry".to_string()));
+        edo.register_fallback(|_, _| Some("<br>".to_string()));
+        assert_eq!(
+            edo.render(""),
+            "<a title=\"&quot;quoted&quot;\">Tom &amp; Jerry</a><br><br>"
+        );
+    }
+
+    #[test]
+    fn render_template_with_filters() {
+        let mut edo = match Edo::new("{path|urlencode} {path|json} {path|shell_quote} {path|reverse|json} {path|nope}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("path", "a b");
+        edo.register_filter("reverse", |value| value.chars().rev().collect());
+        let (output, errors) = edo.render_with_errors("");
+        assert_eq!(output, "a%20b \"a b\" 'a b' \"b a\" ");
+        assert_eq!(errors, vec![RenderError::UnknownFilter { name: "nope".to_string(), span: Span { start: 68, end: 79 } }]);
+    }
+
+    #[test]
+    fn render_template_with_contextual_html_escaping() {
+        let mut edo = match Edo::new("<a href=\"{url}/{url}\" onclick=\"go({url})\">{url}</a>") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("url", "javascript:alert('x')");
+        edo.set_escaping(Escaping::ContextualHtml);
+        assert_eq!(
+            edo.render(""),
+            "<a href=\"#/javascript%3Aalert%28%27x%27%29\" onclick=\"go(&#x22;javascript:alert(&#x5c;u0027x&#x5c;u0027)&#x22;)\">javascript:alert(&#x27;x&#x27;)</a>"
+        );
+
+        // A new template is escaped for its own contexts
+        edo.set_template("<p title=\"{url}\">{url}</p><a href=\"{url}\">").unwrap();
+        assert_eq!(
+            edo.render(""),
+            "<p title=\"javascript:alert(&#x27;x&#x27;)\">javascript:alert(&#x27;x&#x27;)</p><a href=\"#\">"
+        );
+
+        // Nested templates are escaped for the context they are rendered in
+        edo.register_template("link", "{url}").unwrap();
+        edo.set_template("<a href=\"{link}\">{link}</a>").unwrap();
+        assert_eq!(edo.render(""), "<a href=\"#\">javascript:alert(&#x27;x&#x27;)</a>");
+
+        // A custom encoder replaces contextual escaping
+        edo.set_encoder(|value: &str| value.len().to_string());
+        edo.set_template("<p title=\"{url}\">{url}</p><a href=\"{url}\">").unwrap();
+        assert_eq!(edo.render(""), "<p title=\"21\">21</p><a href=\"21\">");
+    }
+
+    #[test]
+    fn render_escaped_arguments() {
+        let mut edo = match Edo::new(r"{echo(a\,b, \{x\}, \d+(\)), \\)}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_handler("echo", |args, _| Ok(args.join("|")));
+        assert_eq!(edo.render(&()), r"a,b|{x}|\d+())|\");
+    }
+
+    #[test]
+    fn render_placeholder_arguments() {
+        let mut edo = match Edo::new("{shout({name})} {wrap( {shout({name|raw}, !)} , {missing})} {!name}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.set_escaping(Escaping::Html);
+        edo.register_static("name", "<b>");
+        edo.register_handler("shout", |args, _| Ok(args.concat().to_uppercase()));
+        edo.register_handler("wrap", |args, _| Ok(format!("[{}]", args.join("|"))));
+        let report = edo.try_render(&()).unwrap();
+        assert_eq!(report.output, "&lt;B&gt; [&lt;B&gt;!|] <b>");
+        let names: Vec<_> = report.placeholders.iter().map(|placeholder| placeholder.name.as_str()).collect();
+        assert_eq!(names, vec!["name", "shout", "name", "shout", "missing", "wrap", "name"]);
+        assert_eq!(report.placeholders[4].span, Span { start: 48, end: 57 });
+    }
+
+    #[test]
+    fn limit_placeholder_argument_depth() {
+        let nested = |depth: usize| format!("{}x{}", "{f(".repeat(depth), ")}".repeat(depth));
+        let (shallow, deep, inner, outer, too_deep) = (nested(8), nested(MAX_NESTING), nested(20), nested(20).replace('x', "{inner}"), nested(2000));
+        let mut edo = Edo::new(&shallow).unwrap();
+        edo.register_handler("f", |args, _| Ok(format!("<{}>", args.concat())));
+        assert_eq!(edo.render(&()), "<<<<<<<<x>>>>>>>>");
+
+        edo.set_template(&deep).unwrap();
+        assert_eq!(edo.try_render(&()), Err(RenderError::DepthLimitExceeded { limit: 32, chain: vec![] }));
+        edo.set_options(RenderOptions::new().max_depth(MAX_NESTING));
+        assert_eq!(edo.render(&()).len(), 2 * MAX_NESTING + 1);
+
+        // Nested templates and arguments share the limit
+        edo.set_options(RenderOptions::new());
+        edo.register_template("inner", &inner).unwrap();
+        edo.set_template(&outer).unwrap();
+        assert_eq!(
+            edo.try_render(&()),
+            Err(RenderError::DepthLimitExceeded { limit: 32, chain: vec!["inner".to_string()] })
+        );
+
+        assert_eq!(edo.set_template(&too_deep).unwrap_err().code(), "E0009");
+    }
+
+    #[test]
+    fn render_format_specs() {
+        let mut edo = match Edo::new("|{item:<6}|{amount(3):*>6}|{item|upper:^7}|{missing:>2}|") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.set_escaping(Escaping::Html);
+        edo.register_static("item", "a&b");
+        edo.register_handler("amount", |args, _| Ok(args[0].repeat(2)));
+        edo.register_filter("upper", |value| value.to_uppercase());
+        assert_eq!(edo.render(&()), "|a&amp;b   |****33|  A&amp;B  |  |");
+
+        edo.set_template("{price:.2} {price:8.1} {id:08} {id:.2}").unwrap();
+        edo.register_static("price", "2.5");
+        edo.register_static("id", "-42");
+        assert_eq!(edo.render(&()), "2.50      2.5 -0000042 -42.00");
+
+        edo.set_template("{item^} {item!title:>5} {amount(x)!upper}").unwrap();
+        assert_eq!(edo.render(&()), "A&amp;B   A&amp;b XX");
+    }
+
+    #[test]
+    fn render_standalone_lines() {
+        let mut edo = match Edo::new("{a}\n x {a}\n\t{a}  \r\n{b}\n  {a}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("a", "");
+        edo.register_static("b", "b");
+        assert_eq!(edo.render(&()), "\n x \n\t  \r\nb\n  ");
+        edo.set_options(RenderOptions::new().trim_standalone(true));
+        assert_eq!(edo.render(&()), " x \nb\n");
+    }
+
+    #[test]
+    fn render_indented_continuations() {
+        let mut edo = match Edo::new("root:\n\t  key: {value}\n{value} {text(a\nb)}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("value", "a\n\nb\r\n\r\nc\n");
+        edo.register_handler("text", |args, _| Ok(args[0].to_string()));
+        edo.set_options(RenderOptions::new().indent_continuations(true));
+        assert_eq!(edo.render(&()), "root:\n\t  key: a\n\n\t  b\r\n\r\n\t  c\n\na\n\nb\r\n\r\nc\n a\n b");
+
+        // The indentation counts towards the limit
+        let mut edo = match Edo::new("    {a}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("a", "b\nc\nd");
+        edo.set_options(RenderOptions::new().indent_continuations(true).max_output(9));
+        assert_eq!(edo.try_render(&()), Err(RenderError::OutputTooLarge { limit: 9 }));
+        edo.set_options(RenderOptions::new().indent_continuations(true).max_output(17));
+        assert_eq!(edo.render(&()), "    b\n    c\n    d");
+    }
+
+    #[test]
+    fn render_line_prefix() {
+        let mut edo = match Edo::new("{a}\n\nb") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("a", "");
+        edo.set_options(RenderOptions::new().line_prefix("> "));
+        assert_eq!(edo.render(&()), "> \n> \n> b");
+        edo.set_options(RenderOptions::new().line_prefix("> ").max_output(8));
+        assert_eq!(edo.try_render(&()), Err(RenderError::OutputTooLarge { limit: 8 }));
+        edo.set_template("").unwrap();
+        assert_eq!(edo.render(&()), "");
+    }
+
+    #[test]
+    fn render_bidi_isolated() {
+        let mut edo = match Edo::new("{a} {b} {c({a})} {d}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("a", "\u{627}");
+        edo.register_static("b", "");
+        edo.register_handler("c", |args, _| Ok(args[0].to_string()));
+        edo.register_template("d", "<{a}>").unwrap();
+        edo.set_options(RenderOptions::new().isolate_bidi(true));
+        assert_eq!(edo.render(&()), "\u{2068}\u{627}\u{2069}  \u{2068}\u{627}\u{2069} <\u{2068}\u{627}\u{2069}>");
+    }
+
+    #[test]
+    fn validate_arities() {
+        let mut edo = match Edo::new("{greet(a)}{header}{greet}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_template("header", "{greet(a, b, c)}").unwrap();
+        edo.register_handler_with_arity("greet", 1..3, |args, _| Ok(args.join("+")));
+        let expected = Arity::between(1, 2);
+        assert_eq!(edo.validate(), vec![
+            RenderError::WrongArity { name: "greet".to_string(), expected, found: 0, span: Span { start: 18, end: 25 } },
+            RenderError::WrongArity { name: "greet".to_string(), expected, found: 3, span: Span { start: 13, end: 14 } },
+        ]);
+        assert_eq!(edo.render_with_errors(&()).0, "a");
+
+        edo.register_static("greet", "hi");
+        assert_eq!(edo.validate(), vec![]);
+    }
+
+    #[test]
+    fn render_template_in_passes() {
+        let mut edo = match Edo::new("<h1>{title}</h1>{user(name)|upper} {now} {greeting}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.set_escaping(Escaping::Html);
+        edo.register_static("title", "Tom & Jerry");
+        edo.register_fallback(|name, _| if name == "greeting" { Some("hi".to_string()) } else { None });
+        let mut partial = edo.render_partial("").unwrap();
+        drop(edo);
+
+        partial.register_static("now", "noon");
+        let mut last = partial.render_partial("").unwrap();
+        drop(partial);
+
+        last.register_handler("user", |args, _| Ok(args.join(",")));
+        last.register_filter("upper", |value| value.to_uppercase());
+        assert_eq!(last.render(""), "<h1>Tom &amp; Jerry</h1>NAME noon hi");
+    }
+
+    #[test]
+    fn escape_in_later_passes() {
+        let mut edo = match Edo::new("<p>{title}</p><p>{body|shout}</p>") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.set_escaping(Escaping::Html);
+        edo.register_filter("shout", |value| value.to_uppercase());
+        edo.set_options(RenderOptions::new().max_output(40));
+        edo.register_static("title", "a < b");
+        let mut partial = edo.render_partial("").unwrap();
+        drop(edo);
+
+        partial.register_static("body", "<script>");
+        assert_eq!(partial.render(""), "<p>a &lt; b</p><p>&lt;SCRIPT&gt;</p>");
+        partial.register_static("body", "<script>".repeat(2));
+        assert_eq!(partial.try_render(""), Err(RenderError::OutputTooLarge { limit: 40 }));
+
+        let mut edo = Edo::new("<a href=\"{url}\">{name}</a>").unwrap();
+        edo.set_escaping(Escaping::ContextualHtml);
+        edo.register_static("name", "<b>");
+        let mut partial = edo.render_partial("").unwrap();
+        partial.register_static("url", "javascript:alert(1)");
+        assert_eq!(partial.render(""), "<a href=\"#\">&lt;b&gt;</a>");
+    }
+
+    #[test]
+    fn render_dedented_template() {
+        assert_eq!(super::dedent("\n    a\n\n      b\r\n  \n    c"), "a\n\n  b\r\n\nc");
+        assert_eq!(super::dedent("  a\n\tb"), " a\nb");
+        assert_eq!(super::dedent(""), "");
+
+        let source = String::from("\n  {a}\n    {b}");
+        let mut edo = match Edo::new_dedented(&source) {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        drop(source);
+        assert_eq!(edo.segments().count(), 3);
+        edo.register_static("a", "1");
+        let mut partial = edo.render_partial(&()).unwrap();
+        drop(edo);
+        partial.register_static("b", "2");
+        assert_eq!(partial.render(&()), "1\n  2");
+        assert_eq!(Edo::<()>::new_dedented("\n  {a\n").err(), Some(EdoError::UnterminatedPlaceholder { span: Span { start: 0, end: 2 } }));
+    }
+
+    #[test]
+    fn parse_into_default_template() {
+        let mut edo: Edo<()> = Edo::default();
+        edo.register_static("a", "1");
+        assert_eq!(edo.render(&()), "");
+        edo.parse_into(&String::from("{a}{a}")).unwrap();
+        assert!(edo.parse_into(&String::from("{a")).is_err());
+        assert_eq!(edo.render(&()), "11");
+        let partial = edo.render_partial(&()).unwrap();
+        edo.parse_into("{b}").unwrap();
+        assert_eq!((edo.render(&()), partial.segments().len()), ("".to_string(), 2));
+    }
+
+    #[test]
+    fn partial_outlives_template() {
+        let mut edo: Edo<()> = Edo::default();
+        edo.parse_into(&String::from("{a(x, y)!upper} and {b(z)|shell_quote:>3}")).unwrap();
+        edo.register_static("a", "1");
+        let mut partial = edo.render_partial(&()).unwrap();
+        drop(edo);
+        partial.register_handler("b", |args, _| Ok(args[0].to_string()));
+        assert_eq!(partial.render(&()), "1 and   z");
+        assert_eq!(partial.segments().len(), 3);
+    }
+
+    #[test]
+    fn keep_double_brace_syntax() {
+        let mut edo: Edo<()> = match Edo::new_with_syntax("{{a}}", Syntax::DoubleBrace) {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_template("a", "{ {{ b }} }").unwrap();
+        edo.register_static("b", "1");
+        assert_eq!(edo.render(&()), "{ 1 }");
+        edo.parse_into("{b}{{b}}").unwrap();
+        assert_eq!(edo.render(&()), "{b}1");
+        assert_eq!(edo.render_partial(&()).map(|mut partial| partial.set_template("{b}").map(|_| partial.render(&()))), Ok(Ok("{b}".to_string())));
+    }
+
+    #[test]
+    fn render_template_with_encoder() {
+        let mut edo = match Edo::new("{a}-{!a}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("a", "x");
+        edo.set_encoder(|value: &str| format!("[{}]", value));
+        assert_eq!(
+            edo.render(""),
+            "[x]-x"
+        );
+    }
+
     #[test]
     fn render_template_with_arguments() {
         let mut edo = match Edo::new("Hello {name(Gio, yes)}") {
             Ok(edo) => edo,
-            Err(err) => panic!(err),
+            Err(err) => panic!("{}", err),
         };
         edo.register_handler("name", |args, _|
             Ok(format!("{}{}", args[0], if args[1] == "yes" { "!" } else { "" }))
@@ -233,7 +2153,7 @@ mod tests {
     fn render_template_with_context() {
         let mut edo = match Edo::new("Hello {name}") {
             Ok(edo) => edo,
-            Err(err) => panic!(err),
+            Err(err) => panic!("{}", err),
         };
         edo.register_handler("name", |_, context: &str| Ok(context.to_string()));
         assert_eq!(
@@ -242,15 +2162,387 @@ mod tests {
         );
     }
 
+    #[test]
+    fn render_with_per_call_options() {
+        let mut edo = match Edo::new("{a}\n{missing}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_template("a", "{b}").unwrap();
+        edo.register_static("b", "1");
+        edo.set_options(RenderOptions::new().line_prefix("# "));
+        let options = RenderOptions::new().max_depth(0).missing_producers(Severity::Warning);
+        assert_eq!(edo.try_render_with_options(&(), &options), Err(RenderError::DepthLimitExceeded { limit: 0, chain: vec!["a".to_string()] }));
+        let report = edo.try_render_with_options(&(), &RenderOptions::new().missing_producers(Severity::Warning)).unwrap();
+        assert_eq!((report.output.as_str(), report.warnings.len()), ("1\n", 1));
+        assert_eq!(edo.render_with_options(&(), &RenderOptions::new().max_output(1)), "");
+        assert_eq!(edo.render(&()), "# 1\n");
+    }
+
+    #[test]
+    fn render_template_with_mapped_context() {
+        let mut edo = match Edo::new("{first} {second}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        let shout = |_: Args, text: &str| Ok(text.to_uppercase());
+        edo.register_handler_mapped("first", |pair: &(&str, &str)| pair.0, shout);
+        edo.register_handler_mapped("second", |pair: &(&str, &str)| pair.1, shout);
+        assert_eq!(edo.render(&("a", "b")), "A B");
+    }
+
+    #[test]
+    fn render_with_output_limit() {
+        let mut edo = match Edo::new("ab{c}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("c", "cd");
+        edo.set_options(RenderOptions::new().max_output(4));
+        assert_eq!(edo.render_with_errors(""), ("abcd".to_string(), vec![]));
+
+        edo.set_options(RenderOptions::new().max_output(3));
+        assert_eq!(edo.try_render(""), Err(RenderError::OutputTooLarge { limit: 3 }));
+        assert_eq!(
+            edo.render_with_errors(""),
+            ("".to_string(), vec![RenderError::OutputTooLarge { limit: 3 }])
+        );
+
+        assert!(Edo::<()>::new("{c|trim:20000000000}").is_err());
+        edo.set_template("ab{c:65535}").unwrap();
+        edo.set_options(RenderOptions::new().max_output(100));
+        assert_eq!(edo.try_render(""), Err(RenderError::OutputTooLarge { limit: 100 }));
+        edo.set_template("ab{c:98}").unwrap();
+        assert_eq!(edo.try_render("").map(|report| report.output.len()), Ok(100));
+    }
+
+    #[test]
+    fn render_nested_templates() {
+        let mut edo = match Edo::new("<{page}>") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.set_escaping(Escaping::Html);
+        edo.register_template("page", "{header}|{body}").unwrap();
+        edo.register_template("header", "<h1>{title}</h1>").unwrap();
+        edo.register_static("title", "Tom & Jerry");
+        edo.register_handler("body", |_, _| Err("no body".to_string()));
+        assert_eq!(
+            edo.render_with_errors(""),
+            ("<<h1>Tom &amp; Jerry</h1>|>".to_string(), vec![RenderError::HandlerFailed {
+                name: "body".to_string(),
+                args: vec![],
+                message: "no body".to_string(),
+                span: Span { start: 9, end: 15 },
+            }])
+        );
+
+        edo.set_options(RenderOptions::new().max_depth(1));
+        assert_eq!(
+            edo.try_render(""),
+            Err(RenderError::DepthLimitExceeded { limit: 1, chain: vec!["page".to_string(), "header".to_string()] })
+        );
+    }
+
+    #[test]
+    fn render_recursive_templates() {
+        let mut edo: Edo<str> = match Edo::new("{a}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_template("a", "a{b}").unwrap();
+        edo.register_template("b", "b{c}").unwrap();
+        edo.register_template("c", "c{a}").unwrap();
+        let error = edo.try_render("").unwrap_err();
+        assert_eq!(error.to_string(), "Template includes itself: a -> b -> c -> a");
+    }
+
+    #[test]
+    fn render_sandboxed() {
+        let mut edo = match Edo::new("{a}{b}{c}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("a", "1");
+        edo.register_template("b", "{a}{c}").unwrap();
+        edo.register_fallback(|name, _| Some(name.to_string()));
+        edo.set_options(RenderOptions::new().allow_only(vec!["a".to_string(), "b".to_string()]));
+        assert_eq!(
+            edo.render_with_errors(""),
+            ("11".to_string(), vec![
+                RenderError::NotAllowed { name: "c".to_string(), span: Span { start: 3, end: 6 } },
+                RenderError::NotAllowed { name: "c".to_string(), span: Span { start: 6, end: 9 } },
+            ])
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "normalize")]
+    fn render_normalized() {
+        let mut edo = match Edo::new("Cafe\u{301} {name}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("name", "Jose\u{301}");
+        assert_eq!(edo.render(""), "Cafe\u{301} Jose\u{301}");
+
+        edo.set_options(RenderOptions::new().normalize_output(true));
+        assert_eq!(edo.render(""), "Cafe\u{301} Jos\u{e9}");
+
+        edo.set_options(RenderOptions::new().normalize_output(true).normalize_literals(true));
+        assert_eq!(edo.render(""), "Caf\u{e9} Jos\u{e9}");
+    }
+
     #[test]
     fn render_with_errors() {
         let mut edo = match Edo::new("Hello {name}") {
             Ok(edo) => edo,
-            Err(err) => panic!(err),
+            Err(err) => panic!("{}", err),
         };
         edo.register_handler("name", |_, _| Err("BORK".to_string()));
         let (output, errors) = edo.render_with_errors("");
         assert_eq!(output, "Hello ");
-        assert_eq!(errors, vec!["BORK"]);
+        assert_eq!(errors, vec![RenderError::HandlerFailed {
+            name: "name".to_string(),
+            args: vec![],
+            message: "BORK".to_string(),
+            span: Span { start: 6, end: 12 },
+        }]);
+    }
+
+    #[test]
+    fn render_strict() {
+        let mut edo = match Edo::new("{greet(Gio, Ana)}{name}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_handler("greet", |_, _| Err("BORK".to_string()));
+        assert_eq!(edo.render_with_errors("").1.len(), 1);
+
+        edo.set_options(RenderOptions::new().strict(true));
+        assert_eq!(
+            edo.render_with_errors(""),
+            ("".to_string(), vec![
+                RenderError::HandlerFailed {
+                    name: "greet".to_string(),
+                    args: vec!["Gio".to_string(), "Ana".to_string()],
+                    message: "BORK".to_string(),
+                    span: Span { start: 0, end: 17 },
+                },
+                RenderError::MissingProducer { name: "name".to_string(), span: Span { start: 17, end: 23 } },
+            ])
+        );
+
+        edo.register_fallback(|_, _| Some("x".to_string()));
+        assert_eq!(edo.render_with_errors("").1.len(), 1);
+    }
+
+    #[test]
+    fn render_report_severities() {
+        let mut edo = match Edo::new("{greet}{name}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_handler("greet", |_, _| Err("BORK".to_string()));
+        let report = edo.try_render("").unwrap();
+        assert!(report.warnings.is_empty());
+        assert_eq!(report.errors.len(), 1);
+        assert!(!report.is_ok());
+
+        edo.set_options(RenderOptions::new().missing_producers(Severity::Error).handler_failures(Severity::Warning));
+        let report = edo.try_render("").unwrap();
+        assert_eq!(report.warnings[0].to_string(), "BORK");
+        assert_eq!(report.errors, vec![RenderError::MissingProducer { name: "name".to_string(), span: Span { start: 7, end: 13 } }]);
+        assert_eq!(report.errors[0].code(), "E0102");
+        assert_eq!(report.warnings[0].code(), "E0101");
+
+        edo.set_options(RenderOptions::new().handler_failures(Severity::Ignore));
+        let report = edo.try_render("").unwrap();
+        assert!(report.is_ok() && report.warnings.is_empty());
+    }
+
+    #[test]
+    fn render_fail_fast() {
+        let mut edo = match Edo::new("{a}{b}{c}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("a", "1");
+        edo.register_template("b", "{missing}{broken}").unwrap();
+        edo.register_handler("broken", |_, _| Err("BORK".to_string()));
+        edo.register_handler("c", |_, _| panic!("rendered past the error"));
+        edo.set_options(RenderOptions::new().fail_fast(true).missing_producers(Severity::Warning));
+        assert_eq!(
+            edo.try_render(""),
+            Err(RenderError::HandlerFailed {
+                name: "broken".to_string(),
+                args: vec![],
+                message: "BORK".to_string(),
+                span: Span { start: 9, end: 17 },
+            })
+        );
+
+        edo.set_options(RenderOptions::new().fail_fast(true).handler_failures(Severity::Warning));
+        edo.register_static("c", "3");
+        assert_eq!(edo.render(""), "13");
+    }
+
+    #[test]
+    fn render_traced() {
+        let mut edo = match Edo::new("<{page}>") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_template("page", "{title(a, b)}!").unwrap();
+        edo.register_handler("title", |args, _| Ok(args.join("")));
+        let (report, trace) = edo.render_traced("").unwrap();
+        assert_eq!(report.output, "<ab!>");
+        let events: Vec<_> = trace.iter()
+            .map(|event| (
+                event.template.as_deref(),
+                event.index,
+                event.placeholder.as_ref().map(|placeholder| placeholder.resolution),
+                event.arguments.len(),
+                event.output_length,
+            ))
+            .collect();
+        assert_eq!(events, vec![
+            (None, 0, None, 0, 1),
+            (None, 1, Some(Resolution::Template), 0, 3),
+            (Some("page"), 0, Some(Resolution::Handler), 2, 2),
+            (Some("page"), 1, None, 0, 1),
+            (None, 2, None, 0, 1),
+        ]);
+    }
+
+    #[test]
+    fn render_observed() {
+        struct Log(RefCell<Vec<String>>);
+
+        impl Observer for Log {
+            fn on_render_start(&self) {
+                self.0.borrow_mut().push("render".to_string());
+            }
+
+            fn on_literal(&self, text: &str) {
+                self.0.borrow_mut().push(format!("literal {}", text));
+            }
+
+            fn on_placeholder_start(&self, name: &str, arguments: &[&str], _: Span) {
+                self.0.borrow_mut().push(format!("start {}({})", name, arguments.join(",")));
+            }
+
+            fn on_placeholder_end(&self, outcome: &PlaceholderOutcome) {
+                self.0.borrow_mut().push(format!("end {} {:?}", outcome.name, outcome.resolution));
+            }
+
+            fn on_error(&self, error: &RenderError) {
+                self.0.borrow_mut().push(format!("error {}", error.code()));
+            }
+        }
+
+        let log = Log(RefCell::new(vec![]));
+        let mut edo = match Edo::new("a{b(x)}{c}{d}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_handler("b", |_, _| Err("BORK".to_string()));
+        edo.register_template("c", "{d}").unwrap();
+        edo.register_static("d", "1234");
+        edo.register_observer(&log);
+        edo.set_options(RenderOptions::new().max_output(6));
+        assert!(edo.try_render("").is_err());
+        assert_eq!(*log.0.borrow(), vec![
+            "render",
+            "literal a",
+            "start b(x)",
+            "error E0101",
+            "end b Failed",
+            "start c()",
+            "start d()",
+            "end d Static",
+            "end c Template",
+            "start d()",
+            "end d Static",
+            "error E0201",
+        ]);
+    }
+
+    #[test]
+    #[cfg(feature = "locale")]
+    fn render_in_locales() {
+        let mut edo: Edo<()> = match Edo::new("{num(1234.5, 1)} {icu_plural(3, one: # plik, few: # pliki, other: # pliku)} {icu_plural(3, en, one: # file, other: # files)}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_builtins(::builtins::numbers().merge(::builtins::plural()));
+        edo.set_options(RenderOptions::new().locale("pl"));
+        assert_eq!(edo.render(&()), "1\u{202f}234,5 3 pliki 3 files");
+        assert_eq!(edo.render_in("de", &()), "1.234,5 3 pliku 3 files");
+
+        edo.set_template("{lang} {lang(x)}").unwrap();
+        edo.register_localized_handler("lang", |args, _, locale| Ok(args.first().map_or(locale.unwrap_or("none"), |arg| *arg).to_string()));
+        assert_eq!(edo.render_in("de", &()), "de x");
+        edo.set_options(RenderOptions::new());
+        assert_eq!(edo.render(&()), "none x");
+    }
+
+    #[test]
+    fn render_metrics() {
+        let mut edo = match Edo::new("{a}{b}{a}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("a", "1");
+        edo.register_template("b", "{a}").unwrap();
+        let report = edo.try_render("").unwrap();
+        assert_eq!(report.metrics()["a"].calls, 3);
+        assert_eq!(report.metrics()["b"].calls, 1);
+
+        edo.render("");
+        assert_eq!(edo.metrics()["a"].calls, 6);
+        assert_eq!(edo.metrics()["a"].failures, 0);
+        assert!(edo.metrics()["b"].total_duration >= edo.metrics()["b"].max_duration);
+        edo.reset_metrics();
+        assert!(edo.metrics().is_empty());
+    }
+
+    #[test]
+    fn render_report_placeholders() {
+        let mut edo = match Edo::new("{a}{b}{c}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("a", "1");
+        edo.register_template("b", "{d}").unwrap();
+        edo.register_fallback(|name, _| if name == "d" { Some("4".to_string()) } else { None });
+        let report = edo.try_render("").unwrap();
+        let outcomes: Vec<_> = report.placeholders.iter()
+            .map(|outcome| (outcome.name.as_str(), outcome.span, outcome.resolution))
+            .collect();
+        assert_eq!(outcomes, vec![
+            ("a", Span { start: 0, end: 3 }, Resolution::Static),
+            ("d", Span { start: 0, end: 3 }, Resolution::Fallback),
+            ("b", Span { start: 3, end: 6 }, Resolution::Template),
+            ("c", Span { start: 6, end: 9 }, Resolution::Skipped),
+        ]);
+        assert!(report.placeholders.iter().all(|outcome| outcome.duration.is_some() == cfg!(feature = "std")));
+    }
+
+    #[test]
+    fn render_context_lookup_before_fallback() {
+        let mut edo = match Edo::new("{a}{b}{c}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_fallback(|name, _| if name == "c" { None } else { Some("fallback".to_string()) });
+        edo.use_context_lookup();
+        edo.register_static("a", "static");
+        edo.set_options(RenderOptions::new().missing_producers(Severity::Error));
+        let report = edo.try_render(&vec![("a", "context"), ("b", "context")]).unwrap();
+        assert_eq!(report.output, "staticcontext");
+        assert_eq!(report.placeholders.iter().map(|outcome| outcome.resolution).collect::<Vec<_>>(), vec![Resolution::Static, Resolution::Context, Resolution::Skipped]);
+        assert_eq!(report.errors, vec![RenderError::MissingProducer { name: "c".to_string(), span: Span { start: 6, end: 9 } }]);
+        assert_eq!(edo.render(&vec![]), "staticfallback");
     }
 }
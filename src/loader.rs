@@ -0,0 +1,451 @@
+//! Where an [`Engine`](../engine/struct.Engine.html) finds templates
+//!
+//! A [`Loader`](trait.Loader.html) returns the source of a template given its name. [`FileLoader`](struct.FileLoader.html) reads templates from a directory, and with the `watch` feature [`WatchingLoader`](struct.WatchingLoader.html) also watches them for changes. [`MemoryLoader`](struct.MemoryLoader.html) holds templates in memory, such as defaults built into the binary, and [`CompositeLoader`](struct.CompositeLoader.html) tries several loaders in order.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use std::path::{Component, Path, PathBuf};
+#[cfg(feature = "std")]
+use std::{format, fs, io};
+#[cfg(feature = "std")]
+use std::string::ToString;
+#[cfg(feature = "watch")]
+use alloc::collections::BTreeSet;
+#[cfg(feature = "watch")]
+use core::fmt;
+#[cfg(feature = "watch")]
+use core::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "watch")]
+use std::sync::{Arc, Mutex, PoisonError};
+
+#[cfg(feature = "watch")]
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// A source of templates
+pub trait Loader {
+    /// The source of the template called `name`, `Ok(None)` if there is no such template, or why it couldn't be read
+    fn load(&self, name: &str) -> Result<Option<String>, String>;
+
+    /// A number that changes when templates that were loaded before may have changed
+    ///
+    /// An engine drops the templates it has parsed when this changes, so they are loaded again. Loaders whose templates never change can keep the default of 0.
+    fn generation(&self) -> u64 {
+        0
+    }
+}
+
+impl<L: Loader + ?Sized> Loader for &L {
+    fn load(&self, name: &str) -> Result<Option<String>, String> {
+        (**self).load(name)
+    }
+
+    fn generation(&self) -> u64 {
+        (**self).generation()
+    }
+}
+
+/// Templates held in memory
+///
+/// # Examples
+/// ```
+/// use edo::loader::{Loader, MemoryLoader};
+///
+/// let loader = MemoryLoader::new().template("hello", "Hello {name}");
+/// assert_eq!(loader.load("hello"), Ok(Some("Hello {name}".to_string())));
+/// assert_eq!(loader.load("bye"), Ok(None));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MemoryLoader {
+    templates: BTreeMap<String, String>,
+    generation: u64,
+}
+
+impl MemoryLoader {
+    /// A loader without templates
+    pub fn new() -> MemoryLoader {
+        MemoryLoader::default()
+    }
+
+    /// Add a template, replacing any with the same name
+    pub fn template<N: Into<String>, S: Into<String>>(mut self, name: N, source: S) -> MemoryLoader {
+        self.insert(name, source);
+        self
+    }
+
+    /// Add or replace a template
+    ///
+    /// Replacing a template bumps the loader's generation, so an engine parses it again.
+    pub fn insert<N: Into<String>, S: Into<String>>(&mut self, name: N, source: S) {
+        if self.templates.insert(name.into(), source.into()).is_some() {
+            self.generation += 1;
+        }
+    }
+
+    /// Remove a template
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let removed = self.templates.remove(name);
+        if removed.is_some() {
+            self.generation += 1;
+        }
+        removed
+    }
+}
+
+impl<N: Into<String>, S: Into<String>> core::iter::FromIterator<(N, S)> for MemoryLoader {
+    fn from_iter<I: IntoIterator<Item = (N, S)>>(templates: I) -> MemoryLoader {
+        let mut loader = MemoryLoader::new();
+        for (name, source) in templates {
+            loader.insert(name, source);
+        }
+        loader
+    }
+}
+
+impl Loader for MemoryLoader {
+    fn load(&self, name: &str) -> Result<Option<String>, String> {
+        Ok(self.templates.get(name).cloned())
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+/// Tries several loaders in order, using the first that has the template
+///
+/// Put overrides first, like a [`FileLoader`](struct.FileLoader.html) for an operator's templates, and defaults last. A loader that fails stops the search, so a broken override isn't silently replaced by the default.
+///
+/// # Examples
+/// ```
+/// use edo::engine::Engine;
+/// use edo::loader::{CompositeLoader, MemoryLoader};
+///
+/// let defaults = MemoryLoader::new()
+///     .template("header", "Welcome")
+///     .template("footer", "Bye");
+/// let overrides = MemoryLoader::new().template("header", "Welcome to ACME");
+///
+/// let mut engine: Engine<()> = Engine::new(CompositeLoader::new().with(overrides).with(defaults));
+/// assert_eq!(engine.render("header", &()).unwrap().output, "Welcome to ACME");
+/// assert_eq!(engine.render("footer", &()).unwrap().output, "Bye");
+/// ```
+#[derive(Default)]
+pub struct CompositeLoader {
+    loaders: Vec<Box<dyn Loader>>,
+}
+
+impl CompositeLoader {
+    /// A loader that has no templates until loaders are added
+    pub fn new() -> CompositeLoader {
+        CompositeLoader::default()
+    }
+
+    /// Try `loader` after the loaders added before it
+    pub fn with<L: 'static + Loader>(mut self, loader: L) -> CompositeLoader {
+        self.loaders.push(Box::new(loader));
+        self
+    }
+}
+
+impl Loader for CompositeLoader {
+    fn load(&self, name: &str) -> Result<Option<String>, String> {
+        for loader in &self.loaders {
+            if let Some(source) = loader.load(name)? {
+                return Ok(Some(source));
+            }
+        }
+        Ok(None)
+    }
+
+    // Changes whenever one of the loaders' generations does, as long as they only go up
+    fn generation(&self) -> u64 {
+        self.loaders.iter().fold(0, |generation: u64, loader| generation.wrapping_add(loader.generation()))
+    }
+}
+
+/// Loads templates from files in a directory
+///
+/// The name of a template is its path relative to the directory, like `emails/welcome`, with [`extension`](#method.extension) added. Names that would leave the directory, such as `../secrets`, are rejected.
+///
+/// # Examples
+/// ```
+/// use edo::loader::{FileLoader, Loader};
+///
+/// let directory = std::env::temp_dir().join(format!("edo-doc-file-loader-{}", std::process::id()));
+/// std::fs::create_dir_all(&directory).unwrap();
+/// std::fs::write(directory.join("hello.txt"), "Hello {name}").unwrap();
+///
+/// let loader = FileLoader::new(&directory).extension("txt");
+/// assert_eq!(loader.load("hello"), Ok(Some("Hello {name}".to_string())));
+/// assert_eq!(loader.load("missing"), Ok(None));
+/// assert!(loader.load("../hello").is_err());
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct FileLoader {
+    root: PathBuf,
+    extension: Option<String>,
+}
+
+#[cfg(feature = "std")]
+impl FileLoader {
+    /// Load templates from `root`
+    pub fn new<P: Into<PathBuf>>(root: P) -> FileLoader {
+        FileLoader { root: root.into(), extension: None }
+    }
+
+    /// Add an extension to every name, like `html` or `txt`
+    pub fn extension<S: Into<String>>(mut self, extension: S) -> FileLoader {
+        self.extension = Some(extension.into());
+        self
+    }
+
+    // The file a template is read from
+    fn path(&self, name: &str) -> Result<PathBuf, String> {
+        let relative = Path::new(name);
+        if name.is_empty() || !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+            return Err(format!("{} is not a template name", name));
+        }
+        let mut path = self.root.join(relative);
+        if let Some(ref extension) = self.extension {
+            let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+            file_name.push(".");
+            file_name.push(extension);
+            path.set_file_name(file_name);
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Loader for FileLoader {
+    fn load(&self, name: &str) -> Result<Option<String>, String> {
+        match fs::read_to_string(self.path(name)?) {
+            Ok(source) => Ok(Some(source)),
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+}
+
+/// A [`FileLoader`](struct.FileLoader.html) that notices when templates change
+///
+/// Needs the `watch` feature. It watches the directory with the operating system's file events, through `notify`, and bumps its [`generation`](trait.Loader.html#method.generation) when a file it has loaded is written, created, removed or renamed. An [`Engine`](../engine/struct.Engine.html) then parses the templates again on their next render. Events arrive on a background thread, so a change shows up shortly after it is made, and checking the generation doesn't touch the file system.
+///
+/// # Examples
+/// ```
+/// use std::time::{Duration, Instant};
+/// use edo::engine::Engine;
+/// use edo::loader::WatchingLoader;
+///
+/// let directory = std::env::temp_dir().join(format!("edo-doc-watching-loader-{}", std::process::id()));
+/// std::fs::create_dir_all(&directory).unwrap();
+/// std::fs::write(directory.join("greeting"), "Hello").unwrap();
+///
+/// let mut engine: Engine<()> = Engine::new(WatchingLoader::new(&directory).unwrap());
+/// assert_eq!(engine.render("greeting", &()).unwrap().output, "Hello");
+///
+/// std::fs::write(directory.join("greeting"), "Hello again").unwrap();
+/// let start = Instant::now();
+/// while engine.render("greeting", &()).unwrap().output == "Hello" && start.elapsed() < Duration::from_secs(10) {
+///     std::thread::sleep(Duration::from_millis(10));
+/// }
+/// assert_eq!(engine.render("greeting", &()).unwrap().output, "Hello again");
+/// assert!(engine.generation() > 0);
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+#[cfg(feature = "watch")]
+pub struct WatchingLoader {
+    files: FileLoader,
+    // Events stop when the watcher is dropped
+    _watcher: RecommendedWatcher,
+    // The files loaded since they last changed, shared with the thread the events arrive on
+    loaded: Arc<Mutex<BTreeSet<PathBuf>>>,
+    generation: Arc<AtomicU64>,
+}
+
+#[cfg(feature = "watch")]
+impl WatchingLoader {
+    /// Load and watch templates from `root`, or why it can't be watched, such as it not existing
+    pub fn new<P: Into<PathBuf>>(root: P) -> Result<WatchingLoader, String> {
+        WatchingLoader::from_loader(FileLoader::new(root))
+    }
+
+    /// Watch the templates of a file loader
+    pub fn from_loader(files: FileLoader) -> Result<WatchingLoader, String> {
+        // Events name files by their full path, so the files are loaded from there too
+        let root = fs::canonicalize(&files.root).map_err(|error| format!("Can't watch {}: {}", files.root.display(), error))?;
+        let files = FileLoader { root, ..files };
+        let loaded = Arc::new(Mutex::new(BTreeSet::new()));
+        let generation = Arc::new(AtomicU64::new(0));
+
+        let (watched, changed) = (Arc::clone(&loaded), Arc::clone(&generation));
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let mut watched = watched.lock().unwrap_or_else(PoisonError::into_inner);
+            let reload = match event {
+                // Reading a template is an event too, but doesn't change it
+                Ok(Event { kind: EventKind::Access(_), .. }) => false,
+                // Only the first change to a file matters until it's loaded again, every path is removed so renames count once
+                Ok(event) => event.paths.iter().filter(|path| watched.remove(*path)).count() > 0,
+                // Events may have been missed, so reload everything
+                Err(_) => {
+                    watched.clear();
+                    true
+                },
+            };
+            if reload {
+                changed.fetch_add(1, Ordering::SeqCst);
+            }
+        }).map_err(|error| format!("Can't watch {}: {}", files.root.display(), error))?;
+        watcher.watch(&files.root, RecursiveMode::Recursive).map_err(|error| format!("Can't watch {}: {}", files.root.display(), error))?;
+
+        Ok(WatchingLoader { files, _watcher: watcher, loaded, generation })
+    }
+}
+
+#[cfg(feature = "watch")]
+impl fmt::Debug for WatchingLoader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WatchingLoader")
+            .field("files", &self.files)
+            .field("generation", &self.generation.load(Ordering::SeqCst))
+            .finish()
+    }
+}
+
+#[cfg(feature = "watch")]
+impl Loader for WatchingLoader {
+    fn load(&self, name: &str) -> Result<Option<String>, String> {
+        // Watched before reading, so a change made while it's read still counts, and a missing template is noticed when it's created
+        let path = self.files.path(name)?;
+        self.loaded.lock().unwrap_or_else(PoisonError::into_inner).insert(path);
+        self.files.load(name)
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+    #[cfg(feature = "std")]
+    use std::path::PathBuf;
+    #[cfg(feature = "std")]
+    use std::{format, fs};
+    #[cfg(feature = "watch")]
+    use std::thread;
+    #[cfg(feature = "watch")]
+    use std::time::{Duration, Instant};
+
+    #[cfg(feature = "std")]
+    use super::FileLoader;
+    #[cfg(feature = "watch")]
+    use super::WatchingLoader;
+    use super::{CompositeLoader, Loader, MemoryLoader};
+
+    // An empty directory of this test's own
+    #[cfg(feature = "std")]
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("edo-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("emails")).unwrap();
+        directory
+    }
+
+    // Wait for the watcher's thread to see a change
+    #[cfg(feature = "watch")]
+    fn wait_for_generation(loader: &WatchingLoader, generation: u64) -> u64 {
+        let start = Instant::now();
+        while loader.generation() < generation && start.elapsed() < Duration::from_secs(10) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        loader.generation()
+    }
+
+    struct Failing;
+
+    impl Loader for Failing {
+        fn load(&self, name: &str) -> Result<Option<String>, String> {
+            if name == "broken" { Err("Disk on fire".to_string()) } else { Ok(None) }
+        }
+    }
+
+    #[test]
+    fn try_loaders_in_order() {
+        let mut first: MemoryLoader = vec![("a", "first a")].into_iter().collect();
+        first.insert("a", "newer a");
+        assert_eq!(first.generation(), 1);
+
+        let loader = CompositeLoader::new()
+            .with(first)
+            .with(Failing)
+            .with(MemoryLoader::new().template("a", "second a").template("b", "second b").template("broken", "never"));
+        assert_eq!(loader.load("a"), Ok(Some("newer a".to_string())));
+        assert_eq!(loader.load("b"), Ok(Some("second b".to_string())));
+        assert_eq!(loader.load("c"), Ok(None));
+        assert_eq!(loader.load("broken"), Err("Disk on fire".to_string()));
+        assert_eq!(loader.generation(), 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn load_files() {
+        let directory = directory("load-files");
+        fs::write(directory.join("emails").join("welcome.txt"), "Welcome {name}").unwrap();
+        fs::write(directory.join("secret"), "hunter2").unwrap();
+
+        let loader = FileLoader::new(directory.join("emails")).extension("txt");
+        assert_eq!(loader.load("welcome"), Ok(Some("Welcome {name}".to_string())));
+        assert_eq!(loader.load("missing"), Ok(None));
+        assert_eq!(loader.generation(), 0);
+        for name in ["../secret", "/etc/passwd", "a/../../secret", "./welcome", ""] {
+            assert_eq!(loader.load(name), Err(format!("{} is not a template name", name)));
+        }
+        assert!(FileLoader::new(&directory).load("emails").is_err());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn reload_changed_files() {
+        let directory = directory("reload-changed-files");
+        fs::write(directory.join("emails").join("welcome"), "Welcome").unwrap();
+        fs::write(directory.join("other"), "Other").unwrap();
+
+        let loader = WatchingLoader::new(&directory).unwrap();
+        assert_eq!(loader.load("emails/welcome"), Ok(Some("Welcome".to_string())));
+        assert_eq!(loader.load("missing"), Ok(None));
+        assert!(loader.load("../other").is_err());
+
+        // Files that weren't loaded don't count
+        fs::write(directory.join("other"), "Changed").unwrap();
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(loader.generation(), 0);
+
+        fs::write(directory.join("emails").join("welcome"), "Welcome back").unwrap();
+        let generation = wait_for_generation(&loader, 1);
+        assert!(generation > 0);
+        assert_eq!(loader.load("emails/welcome"), Ok(Some("Welcome back".to_string())));
+
+        // A missing template is noticed when it's created
+        let generation = loader.generation();
+        fs::write(directory.join("missing"), "Found").unwrap();
+        assert!(wait_for_generation(&loader, generation + 1) > generation);
+        assert_eq!(loader.load("missing"), Ok(Some("Found".to_string())));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn watch_existing_directories_only() {
+        let missing = std::env::temp_dir().join(format!("edo-test-missing-{}", std::process::id()));
+        assert!(WatchingLoader::new(&missing).unwrap_err().starts_with("Can't watch"));
+    }
+}
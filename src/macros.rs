@@ -0,0 +1,76 @@
+/// Render a template in one expression
+///
+/// Each `name = value` pair registers a producer. Closures become handlers and anything else becomes a static replacement using its `ToString` implementation. The template is rendered with a `()` context.
+///
+/// # Panics
+/// If the template fails to parse.
+///
+/// # Examples
+/// ```
+/// # #[macro_use] extern crate edo;
+/// # fn main() {
+/// let output = edo_format!(
+///     "Hi {name}, you have {count} {plural(message, messages)}",
+///     name = "Gio",
+///     count = 2,
+///     plural = |args, _| Ok(args[1].to_string()),
+/// );
+/// assert_eq!(output, "Hi Gio, you have 2 messages");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! edo_format {
+    (@register $edo:ident,) => {};
+    (@register $edo:ident, $name:ident = |$($param:pat),*| $body:expr $(, $($rest:tt)*)?) => {
+        $edo.register_handler(stringify!($name), |$($param),*| $body);
+        $crate::edo_format!(@register $edo, $($($rest)*)?);
+    };
+    (@register $edo:ident, $name:ident = $value:expr $(, $($rest:tt)*)?) => {
+        $edo.register_static(stringify!($name), $crate::__private::ToString::to_string(&$value));
+        $crate::edo_format!(@register $edo, $($($rest)*)?);
+    };
+    ($template:expr $(, $($producers:tt)*)?) => {{
+        let mut edo: $crate::Edo<()> = $crate::Edo::new($template)
+            .expect("edo_format! was given an invalid template");
+        $crate::edo_format!(@register edo, $($($producers)*)?);
+        edo.render(&())
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    #[test]
+    fn format_without_producers() {
+        assert_eq!(edo_format!("Hello {name}"), "Hello ");
+        assert_eq!(edo_format!("Hello",), "Hello");
+    }
+
+    #[test]
+    fn format_with_statics() {
+        let name = "Gio";
+        assert_eq!(
+            edo_format!("{name} is {age}", name = name, age = 30),
+            "Gio is 30"
+        );
+    }
+
+    #[test]
+    fn format_with_handlers() {
+        assert_eq!(
+            edo_format!(
+                "{greet(Gio)}{bang}",
+                greet = |args, _| Ok(format!("Hello {}", args[0])),
+                bang = |_, _| Ok("!".to_string())
+            ),
+            "Hello Gio!"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn format_invalid_template() {
+        edo_format!("Hello {name");
+    }
+}
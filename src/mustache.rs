@@ -0,0 +1,231 @@
+//! Mustache templates, see [`Edo::new_mustache`](../struct.Edo.html#method.new_mustache)
+//!
+//! Existing mustache templates are read into placeholders, so they get edo's producers without being rewritten:
+//!
+//! | Tag | Renders like |
+//! |---|---|
+//! | `{{name}}` | `{name}`, escaped for HTML |
+//! | `{{{name}}}`, `{{&name}}` | `{!name}`, not escaped |
+//! | `{{#name}}...{{/name}}` | the text between once for each item of a list, or once if the value is not empty or `false` |
+//! | `{{^name}}...{{/name}}` | the text between if the value is empty, `false` or an empty list |
+//! | `{{.}}` | the item a section is rendered for |
+//! | `{{! comment}}` | nothing |
+//! | `{{> name}}` | `{!name}`, a partial registered with [`register_template`](../struct.Edo.html#method.register_template) |
+//!
+//! The items of a list come from [`register_static_list`](../struct.Edo.html#method.register_static_list) or a [list handler](../struct.Edo.html#method.register_list_handler) called without arguments. A section has the producers of the template, it isn't rendered with the item as its context. Section, comment and partial tags alone on their line remove the line, as mustache does. Changing the delimiters with `{{=<% %>=}}` isn't supported.
+//!
+//! # Examples
+//! ```
+//! # use edo::Edo;
+//! let mut template = Edo::new_mustache("{{#admin}}<b>{{name}}</b>{{/admin}}\n{{#tags}}<i>{{.}}</i>{{/tags}}").unwrap();
+//! template.register_static("admin", "true");
+//! template.register_static("name", "Tom & Jerry");
+//! template.register_static_list("tags", vec!["cat", "mouse"]);
+//! assert_eq!(template.render(&()), "<b>Tom &amp; Jerry</b>\n<i>cat</i><i>mouse</i>");
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use engine::Compiled;
+use error::EdoError;
+use parse::{Expression, Span};
+use ValueProducer;
+
+// A mustache template read into placeholders, with the names of its sections added after the source in `text`
+pub(crate) struct Mustache {
+    text: String,
+    pieces: Vec<Piece>,
+    sections: Vec<Section>,
+}
+
+enum Piece {
+    Literal(Range<usize>),
+    Placeholder {
+        name: Range<usize>,
+        raw: bool,
+        span: Span,
+    },
+}
+
+// A section, rendered by a producer whose name is unique to it
+struct Section {
+    name: Range<usize>,
+    value: Range<usize>,
+    inverted: bool,
+    pieces: Vec<Piece>,
+}
+
+impl Mustache {
+    // The expressions of the template, borrowing from `self.text`
+    pub(crate) fn expressions(&self) -> Vec<Expression<'_>> {
+        expressions(&self.pieces, &self.text)
+    }
+
+    // The producers that render the sections, each with its own copy of the text it renders
+    pub(crate) fn producers<'a, C: ?Sized>(&self) -> Vec<(&str, ValueProducer<'a, C>)> {
+        self.sections.iter().map(|section| (&self.text[section.name.clone()], ValueProducer::Section {
+            value: self.text[section.value.clone()].to_string(),
+            inverted: section.inverted,
+            body: Compiled::copy(&expressions(&section.pieces, &self.text)),
+        })).collect()
+    }
+}
+
+fn expressions<'t>(pieces: &[Piece], text: &'t str) -> Vec<Expression<'t>> {
+    pieces.iter().map(|piece| match *piece {
+        Piece::Literal(ref range) => Expression::Literal(&text[range.clone()]),
+        Piece::Placeholder { ref name, raw, span } => Expression::Function {
+            name: &text[name.clone()],
+            arguments: vec![],
+            argument_spans: vec![],
+            has_parentheses: false,
+            case: None,
+            filters: vec![],
+            format: None,
+            raw,
+            span,
+        },
+    }).collect()
+}
+
+// A section that hasn't been closed yet
+struct Open {
+    value: Range<usize>,
+    inverted: bool,
+    span: Span,
+    pieces: Vec<Piece>,
+}
+
+// Read a mustache template
+pub(crate) fn parse(source: &str) -> Result<Mustache, EdoError> {
+    let mut read = Mustache { text: source.into(), pieces: vec![], sections: vec![] };
+    let mut open: Vec<Open> = vec![];
+    let mut offset = 0;
+    while let Some(found) = source[offset..].find("{{") {
+        let start = offset + found;
+        let triple = source[start + 2..].starts_with('{');
+        let closing = if triple { "}}}" } else { "}}" };
+        let opening = if triple { 3 } else { 2 };
+        let end = match source[start + opening..].find(closing) {
+            Some(length) => start + opening + length + closing.len(),
+            None => {
+                let line_end = source[start..].find('\n').map_or(source.len(), |length| start + length);
+                return Err(EdoError::UnterminatedPlaceholder { span: Span { start, end: line_end } });
+            },
+        };
+        let span = Span { start, end };
+        let inner = start + opening..end - closing.len();
+        let sigil = if triple { None } else { source[inner.clone()].trim_start().chars().next().filter(|c| "!#^/&>=".contains(*c)) };
+        let name = match sigil {
+            Some(sigil) => trimmed(source, source[inner.clone()].find(sigil).map_or(inner.clone(), |at| inner.start + at + 1..inner.end)),
+            None => trimmed(source, inner),
+        };
+        if sigil != Some('!') && (name.is_empty() || sigil == Some('=')) {
+            return Err(EdoError::UnsupportedTag { span });
+        }
+
+        // Tags that don't output anything take their whole line with them when they are alone on it
+        let line_start = source[..start].rfind('\n').map_or(0, |index| index + 1);
+        let after = &source[end..];
+        let line_end = after.find('\n').map_or(source.len(), |index| end + index + 1);
+        let standalone = matches!(sigil, Some('!' | '#' | '^' | '/' | '>'))
+            && line_start >= offset
+            && source[line_start..start].chars().all(|c| c == ' ' || c == '\t')
+            && source[end..line_end].trim_end_matches(['\n', '\r']).chars().all(|c| c == ' ' || c == '\t');
+        let (text_end, next) = if standalone { (line_start, line_end) } else { (start, end) };
+
+        let pieces = open.last_mut().map_or(&mut read.pieces, |section| &mut section.pieces);
+        if text_end > offset {
+            pieces.push(Piece::Literal(offset..text_end));
+        }
+        match sigil {
+            Some('!') => {},
+            Some('#') | Some('^') => open.push(Open { value: name, inverted: sigil == Some('^'), span, pieces: vec![] }),
+            Some('/') => {
+                let section = match open.pop() {
+                    Some(section) if source[section.value.clone()] == source[name.clone()] => section,
+                    _ => return Err(EdoError::UnmatchedSection { span }),
+                };
+                let generated = format!("{}{}@{}", if section.inverted { '^' } else { '#' }, &source[section.value.clone()], section.span.start);
+                let name = read.text.len()..read.text.len() + generated.len();
+                read.text.push_str(&generated);
+                let pieces = open.last_mut().map_or(&mut read.pieces, |parent| &mut parent.pieces);
+                pieces.push(Piece::Placeholder { name: name.clone(), raw: true, span: Span { start: section.span.start, end } });
+                read.sections.push(Section { name, value: section.value, inverted: section.inverted, pieces: section.pieces });
+            },
+            Some('&') | Some('>') => pieces.push(Piece::Placeholder { name, raw: true, span }),
+            _ => pieces.push(Piece::Placeholder { name, raw: triple, span }),
+        }
+        offset = next;
+    }
+    if let Some(section) = open.pop() {
+        return Err(EdoError::UnmatchedSection { span: section.span });
+    }
+    if offset < source.len() {
+        read.pieces.push(Piece::Literal(offset..source.len()));
+    }
+    Ok(read)
+}
+
+// `range` of `source` without the whitespace around it
+fn trimmed(source: &str, range: Range<usize>) -> Range<usize> {
+    let text = &source[range.clone()];
+    let start = range.start + (text.len() - text.trim_start().len());
+    start..start + text.trim().len()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use error::EdoError;
+    use parse::Span;
+    use Edo;
+
+    #[test]
+    fn render_mustache() {
+        let mut edo = match Edo::new_mustache("<ul>\n  {{#items}}\n  <li>{{.}}{{! the item }}</li>\n  {{/items}}\n</ul>\n{{^items}}none{{/items}}{{{html}}}{{&html}}{{ html }}{{#empty}}x{{/empty}}{{^no}}{{> footer}}{{/no}}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_list_handler("items", |_, _| Ok(vec!["a".to_string(), "<b>".to_string()]));
+        edo.register_static("html", "<i>");
+        edo.register_static("empty", "");
+        edo.register_static("no", "false");
+        edo.register_template("footer", "|{html}").unwrap();
+        assert_eq!(edo.render(&()), "<ul>\n  <li>a</li>\n  <li>&lt;b&gt;</li>\n</ul>\n<i><i>&lt;i&gt;|&lt;i&gt;");
+
+        edo.register_static_list("items", Vec::<&str>::new());
+        assert!(edo.render(&()).starts_with("<ul>\n</ul>\nnone"));
+    }
+
+    #[test]
+    fn nest_sections() {
+        let mut edo: Edo<()> = Edo::new_mustache("{{#a}}[{{#b}}{{.}}{{/b}}{{.}}]{{/a}}").unwrap();
+        edo.register_static_list("a", vec!["1", "2"]);
+        edo.register_static("b", "x");
+        assert_eq!(edo.render(&()), "[x1][x2]");
+    }
+
+    #[test]
+    fn keep_sections_after_set_template() {
+        let mut edo: Edo<()> = Edo::new_mustache("{{#a}}x{{/a}}").unwrap();
+        edo.register_static("a", "true");
+        edo.set_template("{#a@0}-{#a@0}").unwrap();
+        assert_eq!(edo.render(&()), "x-x");
+    }
+
+    #[test]
+    fn reject_bad_mustache() {
+        let error = |source: &str| Edo::<()>::new_mustache(source).err();
+        assert_eq!(error("{{#a}}x"), Some(EdoError::UnmatchedSection { span: Span { start: 0, end: 6 } }));
+        assert_eq!(error("{{#a}}{{/b}}"), Some(EdoError::UnmatchedSection { span: Span { start: 6, end: 12 } }));
+        assert_eq!(error("x{{/a}}"), Some(EdoError::UnmatchedSection { span: Span { start: 1, end: 7 } }));
+        assert_eq!(error("{{=<% %>=}}"), Some(EdoError::UnsupportedTag { span: Span { start: 0, end: 11 } }));
+        assert_eq!(error("{{ }}"), Some(EdoError::UnsupportedTag { span: Span { start: 0, end: 5 } }));
+        assert_eq!(error("a {{name\nb"), Some(EdoError::UnterminatedPlaceholder { span: Span { start: 2, end: 8 } }));
+    }
+}
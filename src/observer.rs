@@ -0,0 +1,75 @@
+//! Hooks into the render lifecycle
+//!
+//! Register an [`Observer`](trait.Observer.html) with [`Edo::register_observer`](../struct.Edo.html#method.register_observer) to forward renders to whatever logging or metrics library you use, without edo depending on it.
+
+use error::RenderError;
+use parse::Span;
+use report::PlaceholderOutcome;
+
+/// Callbacks made while a template renders
+///
+/// Every method does nothing by default, so only implement the ones you need. They take `&self` because a render only borrows the template, use a `Cell` or `RefCell` to keep state. Observers are also called for the expressions of nested templates.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use std::cell::RefCell;
+/// use edo::observer::Observer;
+/// use edo::parse::Span;
+///
+/// #[derive(Default)]
+/// struct Log(RefCell<Vec<String>>);
+///
+/// impl Observer for Log {
+///     fn on_placeholder_start(&self, name: &str, _: &[&str], span: Span) {
+///         self.0.borrow_mut().push(format!("{} at {}", name, span.start));
+///     }
+/// }
+///
+/// let log = Log::default();
+/// let mut template = Edo::new("Hello {name}").unwrap();
+/// template.register_static("name", "World!");
+/// template.register_observer(&log);
+/// template.render("");
+/// assert_eq!(*log.0.borrow(), vec!["name at 6".to_string()]);
+/// ```
+pub trait Observer {
+    /// A render is starting, this isn't called for nested templates
+    fn on_render_start(&self) {}
+
+    /// A literal is about to be copied to the output
+    fn on_literal(&self, _text: &str) {}
+
+    /// A placeholder is about to be rendered
+    fn on_placeholder_start(&self, _name: &str, _arguments: &[&str], _span: Span) {}
+
+    /// A placeholder has been rendered, whether or not it succeeded
+    fn on_placeholder_end(&self, _outcome: &PlaceholderOutcome) {}
+
+    /// A warning or error was found, or the render was aborted
+    ///
+    /// Problems with [`Severity::Ignore`](../report/enum.Severity.html#variant.Ignore) aren't passed on. With [`RenderOptions::fail_fast`](../options/struct.RenderOptions.html#method.fail_fast) the error that aborts the render is only passed on once.
+    fn on_error(&self, _error: &RenderError) {}
+}
+
+impl<O: Observer + ?Sized> Observer for &O {
+    fn on_render_start(&self) {
+        (**self).on_render_start()
+    }
+
+    fn on_literal(&self, text: &str) {
+        (**self).on_literal(text)
+    }
+
+    fn on_placeholder_start(&self, name: &str, arguments: &[&str], span: Span) {
+        (**self).on_placeholder_start(name, arguments, span)
+    }
+
+    fn on_placeholder_end(&self, outcome: &PlaceholderOutcome) {
+        (**self).on_placeholder_end(outcome)
+    }
+
+    fn on_error(&self, error: &RenderError) {
+        (**self).on_error(error)
+    }
+}
@@ -0,0 +1,421 @@
+//! Options controlling how templates are rendered
+
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+
+#[cfg(feature = "locale")]
+use catalog::LocaleChain;
+use report::Severity;
+
+/// Limits and switches for rendering, set with [`Edo::set_options`](../struct.Edo.html#method.set_options)
+///
+/// # Examples
+/// ```
+/// use edo::options::RenderOptions;
+///
+/// let options = RenderOptions::new().max_output(64 * 1024);
+/// assert_eq!(options.get_max_output(), Some(64 * 1024));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderOptions {
+    max_output: Option<usize>,
+    max_depth: usize,
+    allowed: Option<BTreeSet<String>>,
+    missing_producers: Severity,
+    handler_failures: Severity,
+    fail_fast: bool,
+    trim_standalone: bool,
+    indent_continuations: bool,
+    isolate_bidi: bool,
+    pseudo_localize: bool,
+    line_prefix: Option<String>,
+    list_separator: String,
+    #[cfg(feature = "locale")]
+    locale: Option<String>,
+    #[cfg(feature = "locale")]
+    locale_chain: LocaleChain,
+    #[cfg(feature = "normalize")]
+    normalize_output: bool,
+    #[cfg(feature = "normalize")]
+    normalize_literals: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        RenderOptions {
+            max_output: None,
+            max_depth: 32,
+            allowed: None,
+            missing_producers: Severity::Ignore,
+            handler_failures: Severity::Error,
+            fail_fast: false,
+            trim_standalone: false,
+            indent_continuations: false,
+            isolate_bidi: false,
+            pseudo_localize: false,
+            line_prefix: None,
+            list_separator: ", ".into(),
+            #[cfg(feature = "locale")]
+            locale: None,
+            #[cfg(feature = "locale")]
+            locale_chain: LocaleChain::new(),
+            #[cfg(feature = "normalize")]
+            normalize_output: false,
+            #[cfg(feature = "normalize")]
+            normalize_literals: false,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// The default options, with no output limit and a nesting limit of 32
+    pub fn new() -> RenderOptions {
+        RenderOptions::default()
+    }
+
+    /// Abort the render once the output would be longer than `bytes`
+    pub fn max_output(mut self, bytes: usize) -> RenderOptions {
+        self.max_output = Some(bytes);
+        self
+    }
+
+    /// The output limit in bytes, if there is one
+    pub fn get_max_output(&self) -> Option<usize> {
+        self.max_output
+    }
+
+    /// Abort the render when templates registered with [`Edo::register_template`](../struct.Edo.html#method.register_template) are nested more than `depth` deep
+    ///
+    /// Placeholder arguments, like the `{name}` in `{shout({name})}`, count as a level too.
+    pub fn max_depth(mut self, depth: usize) -> RenderOptions {
+        self.max_depth = depth;
+        self
+    }
+
+    /// The nesting limit
+    pub fn get_max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Only allow placeholders with these names, for rendering untrusted templates against a shared set of producers
+    ///
+    /// Any other placeholder is reported as an error and left empty, even if a producer or fallback could resolve it. This also applies inside nested templates.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::options::RenderOptions;
+    ///
+    /// let mut template = Edo::new("{name} {secret}").unwrap();
+    /// template.register_static("name", "Gio");
+    /// template.register_static("secret", "hunter2");
+    /// template.set_options(RenderOptions::new().allow_only(vec!["name"]));
+    /// let (output, errors) = template.render_with_errors("");
+    /// assert_eq!(output, "Gio ");
+    /// assert_eq!(errors[0].to_string(), "secret is not allowed");
+    /// ```
+    pub fn allow_only<I, S>(mut self, names: I) -> RenderOptions where
+        I: IntoIterator<Item = S>,
+        S: Into<String> {
+        self.allowed = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Whether a placeholder may be rendered
+    pub fn is_allowed(&self, name: &str) -> bool {
+        self.allowed.as_ref().is_none_or(|allowed| allowed.contains(name))
+    }
+
+    /// Report placeholders that no producer or fallback resolves as [`RenderError::MissingProducer`](../error/enum.RenderError.html#variant.MissingProducer)
+    ///
+    /// They are still left empty. Without this they are skipped silently. This is the same as `missing_producers(Severity::Error)`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::error::RenderError;
+    /// use edo::options::RenderOptions;
+    /// use edo::parse::Span;
+    ///
+    /// let mut template: Edo<()> = Edo::new("Hello {name}").unwrap();
+    /// template.set_options(RenderOptions::new().strict(true));
+    /// let (output, errors) = template.render_with_errors(&());
+    /// assert_eq!(output, "Hello ");
+    /// assert_eq!(errors, vec![RenderError::MissingProducer {
+    ///     name: "name".to_string(),
+    ///     span: Span { start: 6, end: 12 },
+    /// }]);
+    /// ```
+    pub fn strict(self, strict: bool) -> RenderOptions {
+        self.missing_producers(if strict { Severity::Error } else { Severity::Ignore })
+    }
+
+    /// Whether missing producers are reported as errors
+    pub fn get_strict(&self) -> bool {
+        self.missing_producers == Severity::Error
+    }
+
+    /// How placeholders that no producer or fallback resolves are reported, ignored by default
+    pub fn missing_producers(mut self, severity: Severity) -> RenderOptions {
+        self.missing_producers = severity;
+        self
+    }
+
+    /// How missing producers are reported
+    pub fn get_missing_producers(&self) -> Severity {
+        self.missing_producers
+    }
+
+    /// How handlers returning `Err` are reported, as errors by default
+    pub fn handler_failures(mut self, severity: Severity) -> RenderOptions {
+        self.handler_failures = severity;
+        self
+    }
+
+    /// How handler failures are reported
+    pub fn get_handler_failures(&self) -> Severity {
+        self.handler_failures
+    }
+
+    /// Abort the render on the first error instead of leaving the placeholder empty and carrying on
+    ///
+    /// Use this when partial output is worse than none, such as a generated shell command. Warnings don't abort the render.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::options::RenderOptions;
+    ///
+    /// let mut template = Edo::new("rm -rf /srv/{dir}").unwrap();
+    /// template.register_handler("dir", |_, _| Err("No directory".to_string()));
+    /// template.set_options(RenderOptions::new().fail_fast(true));
+    /// assert_eq!(template.render(""), "");
+    /// assert_eq!(template.try_render("").unwrap_err().to_string(), "No directory");
+    /// ```
+    pub fn fail_fast(mut self, fail_fast: bool) -> RenderOptions {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Whether the first error aborts the render
+    pub fn get_fail_fast(&self) -> bool {
+        self.fail_fast
+    }
+
+    /// Remove the whole line of a placeholder that is alone on it and renders to nothing, like mustache's standalone tags
+    ///
+    /// A placeholder is alone on its line when there is only spaces and tabs between it and the line break before it, or the start of its template, and the one after it, or the end.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::options::RenderOptions;
+    ///
+    /// let mut template = Edo::new("items:\n  {optional}\n  {item}\n").unwrap();
+    /// template.register_static("optional", "");
+    /// template.register_static("item", "- a");
+    /// template.set_options(RenderOptions::new().trim_standalone(true));
+    /// assert_eq!(template.render(""), "items:\n  - a\n");
+    /// ```
+    pub fn trim_standalone(mut self, trim: bool) -> RenderOptions {
+        self.trim_standalone = trim;
+        self
+    }
+
+    /// Whether empty placeholders alone on their line remove it
+    pub fn get_trim_standalone(&self) -> bool {
+        self.trim_standalone
+    }
+
+    /// Indent every line after the first of a multi-line value as deep as the line the placeholder is on
+    ///
+    /// For output where indentation matters, like YAML or source code. The indentation is the spaces and tabs the line starts with, and empty lines are left empty.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::options::RenderOptions;
+    ///
+    /// let mut template = Edo::new("steps:\n  - {step}\n").unwrap();
+    /// template.register_static("step", "run: make\n  env: ci");
+    /// template.set_options(RenderOptions::new().indent_continuations(true));
+    /// assert_eq!(template.render(""), "steps:\n  - run: make\n    env: ci\n");
+    /// ```
+    pub fn indent_continuations(mut self, indent: bool) -> RenderOptions {
+        self.indent_continuations = indent;
+        self
+    }
+
+    /// Whether multi-line values are indented to match their line
+    pub fn get_indent_continuations(&self) -> bool {
+        self.indent_continuations
+    }
+
+    /// Wrap the value of every placeholder in the Unicode bidi isolates FSI (`U+2068`) and PDI (`U+2069`)
+    ///
+    /// Text written right to left, like an Arabic or Hebrew user name, then can't reorder the text around it. Empty values, nested templates and values passed as arguments are left as they are.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::options::RenderOptions;
+    ///
+    /// let mut template = Edo::new("{name} liked your post").unwrap();
+    /// template.register_static("name", "\u{5e9}\u{5e8}\u{5d4}");
+    /// template.set_options(RenderOptions::new().isolate_bidi(true));
+    /// assert_eq!(template.render(""), "\u{2068}\u{5e9}\u{5e8}\u{5d4}\u{2069} liked your post");
+    /// ```
+    pub fn isolate_bidi(mut self, isolate: bool) -> RenderOptions {
+        self.isolate_bidi = isolate;
+        self
+    }
+
+    /// Whether placeholder values are wrapped in bidi isolates
+    pub fn get_isolate_bidi(&self) -> bool {
+        self.isolate_bidi
+    }
+
+    /// Render the literal text of templates pseudo-localized, for testing a UI before it is translated
+    ///
+    /// Letters get accents, and each literal is put in brackets and padded with `~` to about 130% of its length, as translations are often longer. Text cut off by the UI is missing its `]`, and text without accents doesn't come from a template. Placeholder values, HTML tags and entities are left as they are, and so is the whitespace around a literal. Literals are pseudo-localized after a [catalog](../struct.Edo.html#method.set_catalog) translates them.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::options::RenderOptions;
+    ///
+    /// let mut template = Edo::new("<p>Hello {name}</p>").unwrap();
+    /// template.register_static("name", "Gio");
+    /// template.set_options(RenderOptions::new().pseudo_localize(true));
+    /// assert_eq!(template.render(""), "[<p>Ĥéļļö~~] Gio[</p>]");
+    /// ```
+    pub fn pseudo_localize(mut self, pseudo: bool) -> RenderOptions {
+        self.pseudo_localize = pseudo;
+        self
+    }
+
+    /// Whether literals are pseudo-localized
+    pub fn get_pseudo_localize(&self) -> bool {
+        self.pseudo_localize
+    }
+
+    /// Start every line of the output with `prefix`, such as `"// "` to comment it out
+    ///
+    /// The prefix is added to the rendered text, so lines from placeholder values get it too. A line break at the very end doesn't start a new line. Byte templates ignore it.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::options::RenderOptions;
+    ///
+    /// let mut template = Edo::new("[server]\nport = {port}\n").unwrap();
+    /// template.register_static("port", "80\n# set by the installer");
+    /// template.set_options(RenderOptions::new().line_prefix("# "));
+    /// assert_eq!(template.render(""), "# [server]\n# port = 80\n# # set by the installer\n");
+    /// ```
+    pub fn line_prefix<S: Into<String>>(mut self, prefix: S) -> RenderOptions {
+        self.line_prefix = Some(prefix.into());
+        self
+    }
+
+    /// The prefix of every output line, if there is one
+    pub fn get_line_prefix(&self) -> Option<&str> {
+        self.line_prefix.as_deref()
+    }
+
+    /// Join the items of list values with `separator` when the placeholder doesn't give one, `", "` by default
+    ///
+    /// See [`Edo::register_list_handler`](../struct.Edo.html#method.register_list_handler).
+    pub fn list_separator<S: Into<String>>(mut self, separator: S) -> RenderOptions {
+        self.list_separator = separator.into();
+        self
+    }
+
+    /// The separator list values are joined with
+    pub fn get_list_separator(&self) -> &str {
+        &self.list_separator
+    }
+
+    /// The locale that builtins format numbers and dates for, like `de` or `pt-BR`
+    ///
+    /// Needs the `locale` feature. [`Edo::render_in`](../struct.Edo.html#method.render_in) sets it for a single render.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::builtins;
+    /// use edo::options::RenderOptions;
+    ///
+    /// let mut template = Edo::new("{num(1234.5, 2)}").unwrap();
+    /// template.register_builtins(builtins::numbers());
+    /// template.set_options(RenderOptions::new().locale("de"));
+    /// assert_eq!(template.render(""), "1.234,50");
+    /// ```
+    #[cfg(feature = "locale")]
+    pub fn locale<S: Into<String>>(mut self, locale: S) -> RenderOptions {
+        self.locale = Some(locale.into());
+        self
+    }
+
+    /// The locale, if there is one
+    #[cfg(feature = "locale")]
+    pub fn get_locale(&self) -> Option<&str> {
+        self.locale.as_deref()
+    }
+
+    /// The locales to look in when the locale of the render has no translation, see [`LocaleChain`](../catalog/struct.LocaleChain.html)
+    ///
+    /// Needs the `locale` feature. By default a locale only falls back to itself without its last subtag, so `pt-BR` falls back to `pt`.
+    #[cfg(feature = "locale")]
+    pub fn locale_chain(mut self, chain: LocaleChain) -> RenderOptions {
+        self.locale_chain = chain;
+        self
+    }
+
+    /// The chain of fallback locales
+    #[cfg(feature = "locale")]
+    pub fn get_locale_chain(&self) -> &LocaleChain {
+        &self.locale_chain
+    }
+
+    /// NFC normalize the value of every placeholder before it is filtered and escaped
+    ///
+    /// Needs the `normalize` feature. Output rendered on different systems then compares equal even when producers return different forms of the same characters.
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::options::RenderOptions;
+    ///
+    /// let mut template = Edo::new("{name}").unwrap();
+    /// template.register_static("name", "Jose\u{301}");
+    /// template.set_options(RenderOptions::new().normalize_output(true));
+    /// assert_eq!(template.render(""), "Jos\u{e9}");
+    /// ```
+    #[cfg(feature = "normalize")]
+    pub fn normalize_output(mut self, normalize: bool) -> RenderOptions {
+        self.normalize_output = normalize;
+        self
+    }
+
+    /// Whether placeholder values are NFC normalized
+    #[cfg(feature = "normalize")]
+    pub fn get_normalize_output(&self) -> bool {
+        self.normalize_output
+    }
+
+    /// NFC normalize the literal text of the template too
+    ///
+    /// Needs the `normalize` feature. Literals that are already normalized are copied as they are.
+    #[cfg(feature = "normalize")]
+    pub fn normalize_literals(mut self, normalize: bool) -> RenderOptions {
+        self.normalize_literals = normalize;
+        self
+    }
+
+    /// Whether literals are NFC normalized
+    #[cfg(feature = "normalize")]
+    pub fn get_normalize_literals(&self) -> bool {
+        self.normalize_literals
+    }
+}
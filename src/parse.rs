@@ -1,163 +1,975 @@
-use std::str;
+//! The template syntax tree and parser
 
-use nom::{alphanumeric, IResult};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::str;
+
+use nom::branch::alt;
+use nom::bytes::complete::{is_not, tag, take_till, take_while1};
+use nom::character::complete::{char, multispace0, one_of};
+use nom::combinator::{consumed, map, map_res, opt, recognize, value, verify};
+use nom::error::{Error, ErrorKind};
+use nom::multi::{many0, separated_list1};
+use nom::sequence::{delimited, pair, preceded, tuple};
+use nom::IResult;
 
 use error::EdoError;
+use format::Format;
 
-#[derive(Debug, PartialEq)]
+/// Where a placeholder appears in its template, as byte offsets covering the braces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    /// The offset of the opening `{`
+    pub start: usize,
+    /// The offset just past the closing `}`
+    pub end: usize,
+}
+
+/// A single piece of a parsed template
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expression<'a> {
+    /// A placeholder such as `{name}`, `{name(arg1, arg2)}`, `{name^}`, `{name|filter}` or `{name:>12}`
     Function {
+        /// The name of the handler to call
         name: &'a str,
+        /// The arguments passed to the handler, as they are written, see [`unescape`](fn.unescape.html)
         arguments: Vec<&'a str>,
+        /// Where each argument appears in the template, without the whitespace around it
+        argument_spans: Vec<Span>,
+        /// Whether the name is followed by an argument list, so `{name()}` has one and `{name}` doesn't
+        has_parentheses: bool,
+        /// The change of case written after the name or arguments, applied before the filters
+        case: Option<Case>,
+        /// The filters the output is passed through, in order
+        filters: Vec<&'a str>,
+        /// The format spec after the `:`, as it is written, see [`Format`](../format/struct.Format.html)
+        format: Option<&'a str>,
+        /// Whether the output skips escaping, written as `{!name}` or `{name|raw}`
+        raw: bool,
+        /// Where the placeholder appears in the template
+        span: Span,
     },
+    /// Text that is copied to the output untouched
     Literal(&'a str),
 }
 
+impl<'a> Expression<'a> {
+    /// Whether both are the same literal or placeholder, wherever they appear
+    ///
+    /// Unlike `==`, this leaves out the spans, so the `{name}` in `{name}` and ` {name}` are the same, and so are `{name}` and `{ name }`.
+    pub fn same_structure(&self, other: &Expression) -> bool {
+        match (self, other) {
+            (&Expression::Literal(text), &Expression::Literal(other)) => text == other,
+            (&Expression::Function { name, ref arguments, has_parentheses, case, ref filters, format, raw, .. },
+             &Expression::Function { name: other_name, arguments: ref other_arguments, has_parentheses: other_has_parentheses, case: other_case, filters: ref other_filters, format: other_format, raw: other_raw, .. }) =>
+                (name, has_parentheses, case, format, raw) == (other_name, other_has_parentheses, other_case, other_format, other_raw)
+                    && arguments == other_arguments
+                    && filters == other_filters,
+            _ => false,
+        }
+    }
+
+    // Move the spans of a placeholder parsed on its own to where it starts in its template
+    pub(crate) fn move_to(&mut self, offset: usize) {
+        if let Expression::Function { ref mut span, ref mut argument_spans, .. } = *self {
+            for span in core::iter::once(span).chain(argument_spans) {
+                *span = Span { start: offset + span.start, end: offset + span.end };
+            }
+        }
+    }
+}
+
+/// A change of case, written `{name^}` or `{name!upper}`, `{name!lower}` and `{name!title}`
+///
+/// Unlike filters, these don't need to be registered.
+///
+/// # Examples
+/// ```
+/// use edo::parse::Case;
+///
+/// assert_eq!(Case::Title.apply("hello wORLD"), "Hello World");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    /// Every letter upper case
+    Upper,
+    /// Every letter lower case
+    Lower,
+    /// The first letter of every word upper case and the rest lower case
+    Title,
+}
+
+impl Case {
+    /// Change the case of `value`
+    pub fn apply(&self, value: &str) -> String {
+        match *self {
+            Case::Upper => value.to_uppercase(),
+            Case::Lower => value.to_lowercase(),
+            Case::Title => {
+                let mut output = String::with_capacity(value.len());
+                let mut start = true;
+                for c in value.chars() {
+                    if start {
+                        output.extend(c.to_uppercase());
+                    } else {
+                        output.extend(c.to_lowercase());
+                    }
+                    start = c.is_whitespace();
+                }
+                output
+            },
+        }
+    }
+}
+
+const CASES: [(&str, Case); 4] = [("^", Case::Upper), ("!upper", Case::Upper), ("!lower", Case::Lower), ("!title", Case::Title)];
+
+/// A single piece of a parsed byte template, see [`parse_bytes`](fn.parse_bytes.html)
+#[derive(Debug, PartialEq)]
+pub enum ByteExpression<'a> {
+    /// A placeholder such as `{name}` or `{name(arg1, arg2)}`
+    Function {
+        /// The name of the handler to call
+        name: &'a str,
+        /// The arguments passed to the handler
+        arguments: Vec<&'a str>,
+        /// Whether the name is followed by an argument list
+        has_parentheses: bool,
+        /// Where the placeholder appears in the template
+        span: Span,
+    },
+    /// Bytes that are copied to the output untouched
+    Literal(&'a [u8]),
+}
+
+// The characters a backslash escapes in an argument
+const ESCAPED: &str = ",(){}\\";
+
+// Parse a backslash, along with the character it escapes if there is one
+fn escape(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    recognize(pair(char('\\'), opt(one_of(ESCAPED))))(input)
+}
+
+/// How deeply parentheses and placeholder arguments can nest inside a placeholder, deeper ones are [`EdoError::NestedTooDeep`](../error/enum.EdoError.html#variant.NestedTooDeep)
+pub const MAX_NESTING: usize = 64;
+
+// Abort the whole parse, `input` is the `(` or `{` where the nesting goes past `MAX_NESTING`
+// A failure isn't backtracked over by `alt` and `many0`, so the nesting isn't reported as some other error
+fn too_deep(input: &[u8]) -> nom::Err<Error<&[u8]>> {
+    nom::Err::Failure(Error::new(input, ErrorKind::TooLarge))
+}
+
+// Parse a group of balanced parentheses inside an argument, like the `(3 + x)` in `{calc(2 * (3 + x))}`
+// `depth` is how many groups and placeholders it is inside of, counting itself
+fn group(input: &[u8], depth: usize) -> IResult<&[u8], &[u8]> {
+    if depth > MAX_NESTING && input.starts_with(b"(") {
+        return Err(too_deep(&input[..1]));
+    }
+    recognize(delimited(
+        char('('),
+        many0(alt((
+            is_not("(){}\\"),
+            escape,
+            |input| group(input, depth + 1)
+        ))),
+        char(')')
+    ))(input)
+}
+
+// Parse any amount of whitespace, which unlike `multispace0` includes Unicode whitespace like U+00A0
+fn whitespace(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    let mut length = 0;
+    loop {
+        let rest = &input[length..];
+        let prefix = &rest[..rest.len().min(4)];
+        let valid = match str::from_utf8(prefix) {
+            Ok(valid) => valid,
+            Err(error) => str::from_utf8(&prefix[..error.valid_up_to()]).unwrap_or_default(),
+        };
+        match valid.chars().next() {
+            Some(c) if c.is_whitespace() => length += c.len_utf8(),
+            _ => return Ok((rest, &input[..length])),
+        }
+    }
+}
+
+// Parse a single argument, which can't contain unescaped commas, braces or unbalanced parentheses, or is a whole placeholder
+// Whitespace around the argument, including newlines and Unicode whitespace, is dropped, so it can be empty
+// Escapes are kept until the argument is used, see `unescape`
+// `depth` is that of the placeholder the argument is passed to
+fn argument(input: &[u8], depth: usize) -> IResult<&[u8], &str> {
+    map(
+        map_res(
+            alt((
+                delimited(whitespace, recognize(|input| placeholder(input, depth + 1)), whitespace),
+                recognize(many0(alt((
+                    is_not(",(){}\\"),
+                    escape,
+                    |input| group(input, depth + 1)
+                )))),
+            )),
+            str::from_utf8
+        ),
+        str::trim
+    )(input)
+}
+
+/// An argument as its handler sees it, with `\,`, `\(`, `\)`, `\{`, `\}` and `\\` replaced by the character after the backslash
+///
+/// Arguments in the syntax tree are kept as they are written. A placeholder argument, see [`reference`](fn.reference.html), isn't unescaped, it's rendered. Other backslashes are left alone, so `{regex(\d+)}` gets `\d+`.
+///
+/// # Examples
+/// ```
+/// use edo::parse::unescape;
+///
+/// assert_eq!(unescape(r"a\,b \(c\) \d \\"), r"a,b (c) \d \");
+/// ```
+pub fn unescape(argument: &str) -> Cow<'_, str> {
+    if !argument.contains('\\') {
+        return Cow::Borrowed(argument);
+    }
+    let mut unescaped = String::with_capacity(argument.len());
+    let mut chars = argument.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(&next) if c == '\\' && ESCAPED.contains(next) => {
+                unescaped.push(next);
+                chars.next();
+            },
+            _ => unescaped.push(c),
+        }
+    }
+    Cow::Owned(unescaped)
+}
+
+/// The placeholder an argument such as the `{name}` in `{shout({name})}` refers to, if it is one
+///
+/// Its value is passed to the handler in place of the argument, rendered before the handler is called. The arguments of a placeholder are rendered in order.
+///
+/// # Examples
+/// ```
+/// use edo::parse::{reference, Expression};
+///
+/// match reference("{name|upper}") {
+///     Some(Expression::Function { name, filters, .. }) => assert_eq!((name, filters), ("name", vec!["upper"])),
+///     _ => panic!("not a placeholder"),
+/// }
+/// assert!(reference("name").is_none());
+/// ```
+pub fn reference(argument: &str) -> Option<Expression<'_>> {
+    match function(argument.as_bytes()) {
+        Ok((&[], expression)) => Some(expression),
+        _ => None,
+    }
+}
+
 // Parse a list of arguments
-// TODO: allow non alphanumeric values inside arguments
-// TODO: allow trailing commas, allow leading and trailing whitespace
-named!(arguments<&[u8], Vec<&str> >, delimited!(
-    char!('('),
-    separated_list!(
-        terminated!(
-            char!(','),
-            many0!(char!(' '))
+// An empty last argument is dropped, so `()` has no arguments, `(a,)` has one and `(,)` has one empty argument
+fn arguments(input: &[u8]) -> IResult<&[u8], Vec<&str>> {
+    nested_arguments(input, 0)
+}
+
+fn nested_arguments(input: &[u8], depth: usize) -> IResult<&[u8], Vec<&str>> {
+    map(
+        delimited(
+            char('('),
+            separated_list1(
+                char(','),
+                |input| argument(input, depth)
+            ),
+            char(')')
         ),
-        map_res!(
-            alphanumeric,
+        |mut arguments| {
+            if arguments.last().is_some_and(|last: &&str| last.is_empty()) {
+                arguments.pop();
+            }
+            arguments
+        }
+    )(input)
+}
+
+// Parse a list of filters, each starting with a `|`
+fn filters(input: &[u8]) -> IResult<&[u8], Vec<&str>> {
+    many0(preceded(
+        tuple((multispace0, char('|'), multispace0)),
+        map_res(
+            take_while1(|c: u8| c.is_ascii_alphanumeric() || c == b'_'),
             str::from_utf8
         )
-    ),
-    char!(')')
-));
+    ))(input)
+}
+
+// Parse a change of case, like the `^` in `{name(a)^}`
+fn case(input: &[u8]) -> IResult<&[u8], Case> {
+    alt((
+        value(Case::Upper, char('^')),
+        preceded(char('!'), alt((
+            value(Case::Upper, tag("upper")),
+            value(Case::Lower, tag("lower")),
+            value(Case::Title, tag("title")),
+        ))),
+    ))(input)
+}
+
+// Parse a format spec, starting with a `:`
+// A spec never ends in whitespace, so whitespace before the closing brace is dropped
+fn format(input: &[u8]) -> IResult<&[u8], &str> {
+    preceded(
+        char(':'),
+        verify(
+            map(
+                map_res(take_till(|c| c == b'}'), str::from_utf8),
+                |spec: &str| spec.trim_end_matches(|c: char| c.is_ascii_whitespace())
+            ),
+            |spec: &str| Format::parse(spec).is_some()
+        )
+    )(input)
+}
 
 // Parse a function
-named!(function<&[u8], Expression>, chain!(
-    tag!("{") ~
-    // Parse until the function ends or the arguments start
-    name: map_res!(
-        alt!(
-            take_until!("(") |
-            take_until!("}")
-        ),
-        str::from_utf8
-    ) ~
-    // Optionally parse a list of arguments
-    args: arguments? ~
-    tag!("}") ,
-    || { Expression::Function {
-        name: name,
-        arguments: args.unwrap_or(vec![]),
-    }}
-));
+// Whitespace and newlines are allowed around the name, the arguments and the filters, so long placeholders can be wrapped
+// The span starts at zero, `expressions` moves it to its place in the template
+fn function(input: &[u8]) -> IResult<&[u8], Expression<'_>> {
+    placeholder(input, 0).map_err(|error| match error {
+        // Widen the failure to run from the start of the placeholder
+        nom::Err::Failure(error) if error.code == ErrorKind::TooLarge => {
+            let end = error.input.as_ptr() as usize + error.input.len() - input.as_ptr() as usize;
+            too_deep(&input[..end])
+        },
+        error => error,
+    })
+}
+
+// `depth` is how many groups and placeholders it is an argument inside of
+fn placeholder(input: &[u8], depth: usize) -> IResult<&[u8], Expression<'_>> {
+    if depth > MAX_NESTING && input.starts_with(b"{") {
+        return Err(too_deep(&input[..1]));
+    }
+    map(
+        consumed(tuple((
+            tag("{"),
+            // A leading `!` marks the output as raw
+            map(opt(char('!')), |bang| bang.is_some()),
+            // Parse until the function ends or the arguments or filters start
+            map(
+                map_res(
+                    take_till(|c| c == b'(' || c == b'|' || c == b'}'),
+                    str::from_utf8
+                ),
+                |name: &str| name.trim_matches(|c: char| c.is_ascii_whitespace())
+            ),
+            // Optionally parse a list of arguments
+            opt(|input| nested_arguments(input, depth)),
+            opt(preceded(multispace0, case)),
+            filters,
+            opt(preceded(multispace0, format)),
+            preceded(multispace0, tag("}")),
+        ))),
+        |(source, (_, bang, name, args, case, mut filters, format, _))| {
+            // A spec and a change of case right after the name were read as part of it
+            let (name, format) = match format {
+                None if args.is_none() && filters.is_empty() => split_format(name),
+                _ => (name, format),
+            };
+            let (name, case) = match case {
+                None if args.is_none() => split_case(name),
+                _ => (name, case),
+            };
+            // `{name|raw}` is another way of writing `{!name}`
+            let length = filters.len();
+            filters.retain(|filter| *filter != "raw");
+            let has_parentheses = args.is_some();
+            let arguments = args.unwrap_or_default();
+            let argument_spans = arguments.iter().map(|argument: &&str| {
+                let start = argument.as_ptr() as usize - source.as_ptr() as usize;
+                Span { start, end: start + argument.len() }
+            }).collect();
+            Expression::Function {
+                name,
+                arguments,
+                argument_spans,
+                has_parentheses,
+                case,
+                raw: bang || filters.len() != length,
+                filters,
+                format,
+                span: Span { start: 0, end: source.len() },
+            }
+        }
+    )(input)
+}
+
+// Split a name like `amount:>12` at the first `:` followed by a format spec
+// Names can contain a `:` that isn't, so `{ color: {f}` in CSS is still a placeholder
+fn split_format(name: &str) -> (&str, Option<&str>) {
+    for (index, _) in name.match_indices(':') {
+        let spec = name[index + 1..].trim_end_matches(|c: char| c.is_ascii_whitespace());
+        if Format::parse(spec).is_some() {
+            return (name[..index].trim_end_matches(|c: char| c.is_ascii_whitespace()), Some(spec));
+        }
+    }
+    (name, None)
+}
+
+// Split a change of case off the end of a name, like `name^` or `name!title`
+fn split_case(name: &str) -> (&str, Option<Case>) {
+    for &(suffix, case) in &CASES {
+        if let Some(name) = name.strip_suffix(suffix) {
+            return (name.trim_end_matches(|c: char| c.is_ascii_whitespace()), Some(case));
+        }
+    }
+    (name, None)
+}
 
 // Parse a literal
-named!(literal<&[u8], Expression>, map!(
-    map_res!(
-        is_not!("{"),
-        str::from_utf8
-    ),
-    Expression::Literal
-));
+fn literal(input: &[u8]) -> IResult<&[u8], Expression<'_>> {
+    map(
+        map_res(
+            is_not("{"),
+            str::from_utf8
+        ),
+        Expression::Literal
+    )(input)
+}
 
 // Parse multiple functions and text literals
-named!(pub expressions<&[u8], Vec<Expression> >, many0!(alt!(
-    function |
-    literal
-)));
+fn expressions(input: &[u8]) -> IResult<&[u8], Vec<Expression<'_>>> {
+    map(
+        many0(alt((
+            function,
+            literal
+        ))),
+        |mut expressions| {
+            let mut offset = 0;
+            for expression in &mut expressions {
+                match *expression {
+                    Expression::Literal(text) => offset += text.len(),
+                    Expression::Function { span, .. } => {
+                        expression.move_to(offset);
+                        offset += span.end;
+                    },
+                }
+            }
+            expressions
+        }
+    )(input)
+}
+
+// Parse a function in a byte template, which can't be filtered or raw
+fn byte_function(input: &[u8]) -> IResult<&[u8], ByteExpression<'_>> {
+    map_res(
+        function,
+        |expression| match expression {
+            // Placeholder arguments aren't supported either
+            Expression::Function { name, arguments, has_parentheses, ref filters, case: None, format: None, raw: false, span, .. } if filters.is_empty() && !arguments.iter().any(|argument| argument.starts_with('{')) =>
+                Ok(ByteExpression::Function { name, arguments, has_parentheses, span }),
+            _ => Err(()),
+        }
+    )(input)
+}
+
+// Parse multiple functions and byte literals
+fn byte_expressions(input: &[u8]) -> IResult<&[u8], Vec<ByteExpression<'_>>> {
+    map(
+        many0(alt((
+            byte_function,
+            map(is_not("{"), ByteExpression::Literal)
+        ))),
+        |mut expressions| {
+            let mut offset = 0;
+            for expression in &mut expressions {
+                match *expression {
+                    ByteExpression::Literal(bytes) => offset += bytes.len(),
+                    ByteExpression::Function { ref mut span, .. } => {
+                        *span = Span { start: offset, end: offset + span.end };
+                        offset = span.end;
+                    },
+                }
+            }
+            expressions
+        }
+    )(input)
+}
+
+/// Parse a byte template into a vector of expressions
+///
+/// Literals can be any bytes, but placeholder names and arguments must be UTF-8. Filters and raw placeholders are not supported.
+pub fn parse_bytes(input: &[u8]) -> Result<Vec<ByteExpression<'_>>, EdoError> {
+    match byte_expressions(input) {
+        Ok((rest, _)) if !rest.is_empty() =>
+            Err(placeholder_error(rest, input.len() - rest.len())),
+        Ok((_, expressions)) => Ok(expressions),
+        Err(nom::Err::Failure(error)) => Err(nesting_error(error, input)),
+        Err(_) =>
+            Err(placeholder_error(input, 0)),
+    }
+}
+
+/// The delimiters placeholders are written with
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::parse::Syntax;
+///
+/// let mut template = Edo::new_with_syntax(r#"{"user": "{{ name!upper }}"}"#, Syntax::DoubleBrace).unwrap();
+/// template.register_static("name", "gio");
+/// assert_eq!(template.render(""), r#"{"user": "GIO"}"#);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Syntax {
+    /// `{name(arg)}`, the default
+    #[default]
+    Brace,
+    /// `{{ name(arg) }}`, for JSON, JavaScript and CSS, whose own braces are then literal text
+    ///
+    /// Inside the double braces a placeholder is written as it is between single braces, with optional whitespace, so arguments that are placeholders are still `{{ shout({name}) }}`. A `{` that doesn't start `{{` is literal text.
+    DoubleBrace,
+}
+
+/// Parse a template written with `syntax` into a vector of expressions
+///
+/// Spans cover all of a placeholder's braces.
+///
+/// # Examples
+/// ```
+/// use edo::parse::{parse_with_syntax, Expression, Span, Syntax};
+///
+/// let expressions = parse_with_syntax("{ {{ a }} }", Syntax::DoubleBrace).unwrap();
+/// assert_eq!(expressions[0], Expression::Literal("{ "));
+/// match expressions[1] {
+///     Expression::Function { name, span, .. } => assert_eq!((name, span), ("a", Span { start: 2, end: 9 })),
+///     _ => panic!("not a placeholder"),
+/// }
+/// ```
+pub fn parse_with_syntax(input: &str, syntax: Syntax) -> Result<Vec<Expression<'_>>, EdoError> {
+    match syntax {
+        Syntax::Brace => parse(input),
+        Syntax::DoubleBrace => double_brace_expressions(input),
+    }
+}
+
+// Parse a template with double brace placeholders, the inner braces of each are parsed as a single brace placeholder
+fn double_brace_expressions(input: &str) -> Result<Vec<Expression<'_>>, EdoError> {
+    let mut expressions = vec![];
+    let mut offset = 0;
+    while let Some(found) = input[offset..].find("{{") {
+        let start = offset + found;
+        if start > offset {
+            expressions.push(Expression::Literal(&input[offset..start]));
+        }
+        let inner = &input.as_bytes()[start + 1..];
+        let rest = match function(inner) {
+            Ok((rest, mut expression)) if rest.starts_with(b"}") => {
+                expression.move_to(start + 1);
+                if let Expression::Function { ref mut span, .. } = expression {
+                    *span = Span { start, end: span.end + 1 };
+                }
+                expressions.push(expression);
+                rest
+            },
+            Ok(_) => {
+                let end = input[start..].find('\n').map_or(input.len(), |length| start + length);
+                return Err(EdoError::UnterminatedPlaceholder { span: Span { start, end } });
+            },
+            Err(nom::Err::Failure(error)) => {
+                let error = nesting_error(error, input.as_bytes());
+                return Err(EdoError::NestedTooDeep { span: Span { start, ..error.span().unwrap_or_default() } });
+            },
+            Err(_) => return Err(placeholder_error(inner, start + 1)),
+        };
+        offset = input.len() - rest.len() + 1;
+    }
+    if offset < input.len() {
+        expressions.push(Expression::Literal(&input[offset..]));
+    }
+    Ok(expressions)
+}
 
 /// Parse a template into a vector of expressions
-pub fn parse<'a>(input: &'a str) -> Result<Vec<Expression>, EdoError> {
+pub fn parse(input: &str) -> Result<Vec<Expression<'_>>, EdoError> {
     match expressions(input.as_bytes()) {
-        IResult::Done(_, expressions) => Ok(expressions),
-        IResult::Error(_) =>
-            Err(EdoError::ParsingError),
-        IResult::Incomplete(_) =>
-            Err(EdoError::ParsingError),
+        Ok((rest, _)) if !rest.is_empty() =>
+            Err(placeholder_error(rest, input.len() - rest.len())),
+        Ok((_, expressions)) => Ok(expressions),
+        Err(nom::Err::Failure(error)) => Err(nesting_error(error, input.as_bytes())),
+        Err(_) =>
+            Err(placeholder_error(input.as_bytes(), 0)),
+    }
+}
+
+// The error for a parse aborted by `too_deep`, whose input runs from the start of the placeholder to where it nests too deep in `template`
+fn nesting_error(error: Error<&[u8]>, template: &[u8]) -> EdoError {
+    let start = error.input.as_ptr() as usize - template.as_ptr() as usize;
+    EdoError::NestedTooDeep { span: Span { start, end: start + error.input.len() } }
+}
+
+// Work out why the placeholder at the start of `rest` didn't parse, `offset` is where it starts in the template
+fn placeholder_error(rest: &[u8], offset: usize) -> EdoError {
+    let end = match rest.iter().position(|&c| c == b'}') {
+        Some(index) => index + 1,
+        None => {
+            let end = rest.iter().position(|&c| c == b'\n').unwrap_or(rest.len());
+            return EdoError::UnterminatedPlaceholder { span: Span { start: offset, end: offset + end } };
+        },
+    };
+    let span = Span { start: offset, end: offset + end };
+
+    // A placeholder that parses on its own was rejected by `byte_function`
+    if function(rest).is_ok() {
+        return EdoError::UnsupportedInBytes { span };
+    }
+
+    // Step over the brace, the raw marker and the name
+    let inside = &rest[1..end - 1];
+    let inside = inside.strip_prefix(b"!").unwrap_or(inside);
+    let name_length = inside.iter().position(|&c| c == b'(' || c == b'|').unwrap_or(inside.len());
+    if let Err(error) = str::from_utf8(&inside[..name_length]) {
+        return EdoError::InvalidUtf8 { span, error };
+    }
+    let mut remaining = &inside[name_length..];
+    if remaining.starts_with(b"(") {
+        match arguments(remaining) {
+            Ok((after, _)) => remaining = after,
+            Err(_) => return EdoError::BadArguments { span },
+        }
+    }
+    match filters(remaining) {
+        Ok((after, _)) if after.trim_ascii_start().starts_with(b":") => EdoError::BadFormat { span },
+        Ok((after, ref parsed)) if parsed.is_empty() && !after.trim_ascii_start().starts_with(b"|") => EdoError::BadArguments { span },
+        _ => EdoError::BadFilter { span },
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use nom::IResult;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
 
+    use error::EdoError;
     use super::{
+        ByteExpression,
+        Case,
         Expression,
+        MAX_NESTING,
+        Span,
         arguments,
+        filters,
         function,
         literal,
         expressions,
-        parse
+        parse,
+        parse_bytes,
+        parse_with_syntax,
+        Syntax
     };
 
     #[test]
     fn parse_arguments() {
         assert_eq!(
             arguments(b"()"),
-            IResult::Done(
+            Ok((
                 &b""[..],
                 vec![]
-            )
+            ))
         );
 
         assert_eq!(
             arguments(b"(test)"),
-            IResult::Done(
+            Ok((
                 &b""[..],
                 vec!["test"]
-            )
+            ))
         );
 
         assert_eq!(
             arguments(b"(test,test2)"),
-            IResult::Done(
+            Ok((
                 &b""[..],
                 vec!["test", "test2"]
-            )
+            ))
+        );
+
+        assert_eq!(
+            arguments(b"( %Y-%m-%d , 2 * (3 + x))"),
+            Ok((
+                &b""[..],
+                vec!["%Y-%m-%d", "2 * (3 + x)"]
+            ))
+        );
+
+        assert_eq!(
+            arguments(b"(MY_VAR,test2)"),
+            Ok((
+                &b""[..],
+                vec!["MY_VAR", "test2"]
+            ))
         );
 
         assert_eq!(
             arguments(b"(test, test2)"),
-            IResult::Done(
+            Ok((
                 &b""[..],
                 vec!["test", "test2"]
-            )
+            ))
         );
+
+        assert_eq!(
+            arguments(br"(a\,b, \(x\), \d+, \{\}\\)"),
+            Ok((
+                &b""[..],
+                vec![r"a\,b", r"\(x\)", r"\d+", r"\{\}\\"]
+            ))
+        );
+        assert_eq!(arguments(b"( {a}, {b(c, {d})|e} )"), Ok((&b""[..], vec!["{a}", "{b(c, {d})|e}"])));
+        assert!(arguments(b"({a}b)").is_err());
+
+        // Arguments can be empty, and an empty last one is dropped
+        assert_eq!(arguments(b"(,)"), Ok((&b""[..], vec![""])));
+        assert_eq!(arguments(b"(a,)"), Ok((&b""[..], vec!["a"])));
+        assert_eq!(arguments(b"(a, ,b,,)"), Ok((&b""[..], vec!["a", "", "b", ""])));
+
+        // A backslash before the closing parenthesis escapes it
+        assert!(arguments(br"(a\)").is_err());
+        assert_eq!(arguments(br"(a\))"), Ok((&b""[..], vec![r"a\)"])));
+
+        // There is no limit on how many arguments there are
+        let many: Vec<String> = (0..1000).map(|index| index.to_string()).collect();
+        let input = format!("({})", many.join(", "));
+        assert_eq!(arguments(input.as_bytes()), Ok((&b""[..], many.iter().map(String::as_str).collect())));
+    }
+
+    #[test]
+    fn compare_structure() {
+        let (a, b) = (parse("x{ f(a, b) |upper }").unwrap(), parse("x{f(a,b)|upper}").unwrap());
+        assert_ne!(a, b);
+        assert!(a.iter().zip(&b).all(|(a, b)| a.same_structure(b)));
+        let c = parse("x{f(a, b)|upper:>3}").unwrap();
+        assert!(!a[1].same_structure(&c[1]));
+        assert!(!a[0].same_structure(&a[1]));
     }
 
     #[test]
     fn parse_function() {
         assert_eq!(
             function(b"{test}"),
-            IResult::Done(
+            Ok((
                 &b""[..],
                 Expression::Function {
                     name: "test",
                     arguments: vec![],
+                    argument_spans: vec![],
+                    has_parentheses: false,
+                    case: None,
+                    filters: vec![],
+                    format: None,
+                    raw: false,
+                    span: Span { start: 0, end: 6 },
                 }
-            )
+            ))
         );
 
         assert_eq!(
             function(b"{test()}"),
-            IResult::Done(
+            Ok((
                 &b""[..],
                 Expression::Function {
                     name: "test",
                     arguments: vec![],
+                    argument_spans: vec![],
+                    has_parentheses: true,
+                    case: None,
+                    filters: vec![],
+                    format: None,
+                    raw: false,
+                    span: Span { start: 0, end: 8 },
                 }
-            )
+            ))
         );
 
         assert_eq!(
             function(b"{test(1, 2, 3)}"),
-            IResult::Done(
+            Ok((
                 &b""[..],
                 Expression::Function {
                     name: "test",
                     arguments: vec!["1", "2", "3"],
+                    argument_spans: vec![Span { start: 6, end: 7 }, Span { start: 9, end: 10 }, Span { start: 12, end: 13 }],
+                    has_parentheses: true,
+                    case: None,
+                    filters: vec![],
+                    format: None,
+                    raw: false,
+                    span: Span { start: 0, end: 15 },
+                }
+            ))
+        );
+
+        // Placeholders can be wrapped across lines
+        let wrapped = b"{\n  link (\n    /docs,\n    {title}\n  )\n  | upper\n}";
+        assert_eq!(
+            function(wrapped),
+            Ok((
+                &b""[..],
+                Expression::Function {
+                    name: "link",
+                    arguments: vec!["/docs", "{title}"],
+                    argument_spans: vec![Span { start: 15, end: 20 }, Span { start: 26, end: 33 }],
+                    has_parentheses: true,
+                    case: None,
+                    filters: vec!["upper"],
+                    format: None,
+                    raw: false,
+                    span: Span { start: 0, end: wrapped.len() },
+                }
+            ))
+        );
+        assert_eq!(arguments(b"(\n)"), Ok((&b""[..], vec![])));
+    }
+
+    #[test]
+    fn parse_arguments_trims_all_whitespace() {
+        assert_eq!(
+            arguments("(\t a b\t,\n c \r\n, \u{a0}d\u{a0}\u{3000}, \u{a0}{e}\u{a0})".as_bytes()),
+            Ok((&b""[..], vec!["a b", "c", "d", "{e}"]))
+        );
+        assert_eq!(arguments("(\u{a0},\u{2003})".as_bytes()), Ok((&b""[..], vec![""])));
+
+        let template = "{f(\u{a0}a\t)}";
+        match parse(template) {
+            Ok(ref expressions) => match expressions[..] {
+                [Expression::Function { ref argument_spans, .. }] => assert_eq!(argument_spans[..], [Span { start: 5, end: 6 }]),
+                _ => panic!("{:?}", expressions),
+            },
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    #[test]
+    fn parse_filters() {
+        assert_eq!(
+            filters(b"|json|shell_quote}"),
+            Ok((
+                &b"}"[..],
+                vec!["json", "shell_quote"]
+            ))
+        );
+
+        assert_eq!(
+            function(b"{test(a)|urlencode}"),
+            Ok((
+                &b""[..],
+                Expression::Function {
+                    name: "test",
+                    arguments: vec!["a"],
+                    argument_spans: vec![Span { start: 6, end: 7 }],
+                    has_parentheses: true,
+                    case: None,
+                    filters: vec!["urlencode"],
+                    format: None,
+                    raw: false,
+                    span: Span { start: 0, end: 19 },
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_format() {
+        assert_eq!(
+            function(b"{amount(x)|upper:*>12 }"),
+            Ok((
+                &b""[..],
+                Expression::Function {
+                    name: "amount",
+                    arguments: vec!["x"],
+                    argument_spans: vec![Span { start: 8, end: 9 }],
+                    has_parentheses: true,
+                    case: None,
+                    filters: vec!["upper"],
+                    format: Some("*>12"),
+                    raw: false,
+                    span: Span { start: 0, end: 23 },
+                }
+            ))
+        );
+        assert!(function(b"{amount(x):12x}").is_err());
+        assert_eq!(parse("{a} {amount|x:12x}"), Err(EdoError::BadFormat { span: Span { start: 4, end: 18 } }));
+        assert_eq!(parse("{x|trim:20000000000}"), Err(EdoError::BadFormat { span: Span { start: 0, end: 20 } }));
+        assert_eq!(parse("{x|trim:.99999999999}"), Err(EdoError::BadFormat { span: Span { start: 0, end: 21 } }));
+        match (function(b"{ns:key::>3}"), function(b"{a:12x}")) {
+            (Ok((_, Expression::Function { name, format, .. })), Ok((_, Expression::Function { name: other, format: None, .. }))) =>
+                assert_eq!((name, format, other), ("ns:key", Some(":>3"), "a:12x")),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(parse("{a|x:}"), Ok(vec![Expression::Function {
+            name: "a",
+            arguments: vec![],
+            argument_spans: vec![],
+            has_parentheses: false,
+            case: None,
+            filters: vec!["x"],
+            format: Some(""),
+            raw: false,
+            span: Span { start: 0, end: 6 },
+        }]));
+    }
+
+    #[test]
+    fn parse_case() {
+        let case = |input: &str| match function(input.as_bytes()) {
+            Ok((_, Expression::Function { name, case, ref filters, format, .. })) => (name.to_string(), case, filters.len(), format.is_some()),
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(case("{name^}"), ("name".to_string(), Some(Case::Upper), 0, false));
+        assert_eq!(case("{name !lower|trim}"), ("name".to_string(), Some(Case::Lower), 1, false));
+        assert_eq!(case("{name!title:>9}"), ("name".to_string(), Some(Case::Title), 0, true));
+        assert_eq!(case("{f(a) ^ |trim}"), ("f".to_string(), Some(Case::Upper), 1, false));
+        assert_eq!(case("{name!other}"), ("name!other".to_string(), None, 0, false));
+        assert!(function(b"{f(a)!other}").is_err());
+        assert_eq!(Case::Title.apply("ünï  cOde x"), "Ünï  Code X");
+    }
+
+    #[test]
+    fn parse_raw_function() {
+        assert_eq!(
+            function(b"{!test(a)}"),
+            Ok((
+                &b""[..],
+                Expression::Function {
+                    name: "test",
+                    arguments: vec!["a"],
+                    argument_spans: vec![Span { start: 7, end: 8 }],
+                    has_parentheses: true,
+                    case: None,
+                    filters: vec![],
+                    format: None,
+                    raw: true,
+                    span: Span { start: 0, end: 10 },
                 }
-            )
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_raw_filter() {
+        assert_eq!(
+            function(b"{test|raw|json}"),
+            Ok((
+                &b""[..],
+                Expression::Function {
+                    name: "test",
+                    arguments: vec![],
+                    argument_spans: vec![],
+                    has_parentheses: false,
+                    case: None,
+                    filters: vec!["json"],
+                    format: None,
+                    raw: true,
+                    span: Span { start: 0, end: 15 },
+                }
+            ))
         );
     }
 
@@ -165,10 +977,10 @@ mod tests {
     fn parse_literal() {
         assert_eq!(
             literal(b"testing"),
-            IResult::Done(
+            Ok((
                 &b""[..],
                 Expression::Literal("testing")
-            )
+            ))
         );
     }
 
@@ -176,36 +988,105 @@ mod tests {
     fn parse_multiple_expressions() {
         assert_eq!(
             expressions(b"{test}literal{test2}haha"),
-            IResult::Done(
+            Ok((
                 &b""[..],
                 vec![
                     Expression::Function {
                         name: "test",
                         arguments: vec![],
+                        argument_spans: vec![],
+                        has_parentheses: false,
+                        case: None,
+                        filters: vec![],
+                        format: None,
+                        raw: false,
+                        span: Span { start: 0, end: 6 },
                     },
                     Expression::Literal("literal"),
                     Expression::Function {
                         name: "test2",
                         arguments: vec![],
+                        argument_spans: vec![],
+                        has_parentheses: false,
+                        case: None,
+                        filters: vec![],
+                        format: None,
+                        raw: false,
+                        span: Span { start: 13, end: 20 },
                     },
                     Expression::Literal("haha"),
                 ]
-            )
+            ))
         );
-        
+
         assert_eq!(
             expressions(b"haha{test}"),
-            IResult::Done(
+            Ok((
                 &b""[..],
                 vec![
                     Expression::Literal("haha"),
                     Expression::Function {
                         name: "test",
                         arguments: vec![],
+                        argument_spans: vec![],
+                        has_parentheses: false,
+                        case: None,
+                        filters: vec![],
+                        format: None,
+                        raw: false,
+                        span: Span { start: 4, end: 10 },
+                    },
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_name_stops_at_its_own_placeholder() {
+        // A name ends at the first `(`, `|` or `}`, not at one in a later placeholder or literal
+        assert_eq!(
+            expressions(b"{test}literal{test2(a)}"),
+            Ok((
+                &b""[..],
+                vec![
+                    Expression::Function {
+                        name: "test",
+                        arguments: vec![],
+                        argument_spans: vec![],
+                        has_parentheses: false,
+                        case: None,
+                        filters: vec![],
+                        format: None,
+                        raw: false,
+                        span: Span { start: 0, end: 6 },
+                    },
+                    Expression::Literal("literal"),
+                    Expression::Function {
+                        name: "test2",
+                        arguments: vec!["a"],
+                        argument_spans: vec![Span { start: 20, end: 21 }],
+                        has_parentheses: true,
+                        case: None,
+                        filters: vec![],
+                        format: None,
+                        raw: false,
+                        span: Span { start: 13, end: 23 },
                     },
                 ]
-            )
+            ))
         );
+
+        let names = |template| match parse(template) {
+            Ok(expressions) => expressions.iter().map(|expression| match *expression {
+                Expression::Function { name, ref arguments, .. } => format!("{{{}{:?}}}", name, arguments),
+                Expression::Literal(text) => text.to_string(),
+            }).collect::<Vec<_>>(),
+            Err(error) => panic!("{}", error),
+        };
+        assert_eq!(names("{a} (b) {c}"), vec!["{a[]}", " (b) ", "{c[]}"]);
+        assert_eq!(names("{a}|b {c(d)}"), vec!["{a[]}", "|b ", "{c[\"d\"]}"]);
+        assert_eq!(names("{a}{b|c}"), vec!["{a[]}", "{b[]}"]);
+        assert_eq!(names("{a}: {b}"), vec!["{a[]}", ": ", "{b[]}"]);
     }
 
     #[test]
@@ -217,8 +1098,122 @@ mod tests {
                 Expression::Function {
                     name: "test",
                     arguments: vec!["a", "b", "c"],
+                    argument_spans: vec![Span { start: 10, end: 11 }, Span { start: 13, end: 14 }, Span { start: 16, end: 17 }],
+                    has_parentheses: true,
+                    case: None,
+                    filters: vec![],
+                    format: None,
+                    raw: false,
+                    span: Span { start: 4, end: 19 },
                 },
             ])
         );
     }
+
+    #[test]
+    fn parse_rejects_leftover_input() {
+        assert_eq!(parse("Hello {name"), Err(EdoError::UnterminatedPlaceholder { span: Span { start: 6, end: 11 } }));
+        assert_eq!(parse("{a}\n{b(x\n{c"), Err(EdoError::UnterminatedPlaceholder { span: Span { start: 4, end: 8 } }));
+        assert_eq!(parse("Hello {name(a}"), Err(EdoError::BadArguments { span: Span { start: 6, end: 14 } }));
+        assert_eq!(parse("{a} {name(a{b)} {c}"), Err(EdoError::BadArguments { span: Span { start: 4, end: 15 } }));
+        assert_eq!(parse("{name((a)}"), Err(EdoError::BadArguments { span: Span { start: 0, end: 10 } }));
+        assert_eq!(parse("{name(a)b}"), Err(EdoError::BadArguments { span: Span { start: 0, end: 10 } }));
+        assert_eq!(parse("{name|}"), Err(EdoError::BadFilter { span: Span { start: 0, end: 7 } }));
+        assert_eq!(parse("{name|a-b}"), Err(EdoError::BadFilter { span: Span { start: 0, end: 10 } }));
+    }
+
+    #[test]
+    fn parse_baseline_edge_cases() {
+        let shapes = |template| match parse(template) {
+            Ok(expressions) => expressions.iter().map(|expression| match *expression {
+                Expression::Function { name, ref arguments, has_parentheses, .. } => format!("{{{}{}{:?}}}", name, if has_parentheses { "()" } else { "" }, arguments),
+                Expression::Literal(text) => text.to_string(),
+            }).collect::<Vec<_>>(),
+            Err(error) => panic!("{}", error),
+        };
+
+        // Escapes are kept verbatim in arguments and mean nothing outside of a placeholder
+        assert_eq!(shapes(r"{f(a\,b)}"), vec![r#"{f()["a\\,b"]}"#]);
+        assert_eq!(shapes(r"{f(\))}"), vec![r#"{f()["\\)"]}"#]);
+        assert_eq!(shapes(r"a\{b}"), vec![r"a\", "{b[]}"]);
+        assert_eq!(shapes("}"), vec!["}"]);
+
+        // Empty argument lists still count as parentheses, a lone comma is one empty argument
+        assert_eq!(shapes("{f()}"), vec!["{f()[]}"]);
+        assert_eq!(shapes("{f( )}"), vec!["{f()[]}"]);
+        assert_eq!(shapes("{f(,)}"), vec!["{f()[\"\"]}"]);
+        assert_eq!(shapes("{f}"), vec!["{f[]}"]);
+        assert_eq!(shapes("{}"), vec!["{[]}"]);
+        assert_eq!(shapes("{ }"), vec!["{[]}"]);
+
+        // Unterminated placeholders span from the opening brace to the end of the template
+        for &(template, start) in &[("{", 0), ("x{", 1), ("{f(", 0), ("{f(a,", 0), ("{f()", 0), ("{f|", 0), ("{f:", 0), ("{f(a)|upper", 0)] {
+            assert_eq!(parse(template), Err(EdoError::UnterminatedPlaceholder { span: Span { start, end: template.len() } }), "{}", template);
+        }
+    }
+
+    #[test]
+    fn parse_rejects_deep_nesting() {
+        let nested = |depth: usize| format!("{{a}} {{calc({}x{})}}", "(".repeat(depth), ")".repeat(depth));
+        assert!(parse(&nested(MAX_NESTING)).is_ok());
+        assert_eq!(parse(&nested(MAX_NESTING + 1)), Err(EdoError::NestedTooDeep { span: Span { start: 4, end: 10 + MAX_NESTING + 1 } }));
+        assert_eq!(parse(&nested(5000)), Err(EdoError::NestedTooDeep { span: Span { start: 4, end: 10 + MAX_NESTING + 1 } }));
+        assert_eq!(parse_bytes(nested(5000).as_bytes()).unwrap_err().code(), "E0009");
+
+        // Placeholder arguments count towards the same limit
+        let references = format!("{}x{}", "{f(".repeat(2000), ")}".repeat(2000));
+        assert_eq!(parse(&references), Err(EdoError::NestedTooDeep { span: Span { start: 0, end: 3 * (MAX_NESTING + 1) + 1 } }));
+        assert!(parse(&format!("{}(x){}", "{f(".repeat(MAX_NESTING / 2), ")}".repeat(MAX_NESTING / 2))).is_ok());
+        assert_eq!(
+            parse_with_syntax(&format!("x {{{{ calc({}) }}}}", "(".repeat(5000)), Syntax::DoubleBrace),
+            Err(EdoError::NestedTooDeep { span: Span { start: 2, end: 10 + MAX_NESTING + 1 } })
+        );
+    }
+
+    #[test]
+    fn parse_byte_template() {
+        assert_eq!(
+            parse_bytes(b"\xff\x00{test(a)}\xfe"),
+            Ok(vec![
+                ByteExpression::Literal(b"\xff\x00"),
+                ByteExpression::Function {
+                    name: "test",
+                    arguments: vec!["a"],
+                    has_parentheses: true,
+                    span: Span { start: 2, end: 11 },
+                },
+                ByteExpression::Literal(b"\xfe"),
+            ])
+        );
+        assert_eq!(parse_bytes(b"{test|json}"), Err(EdoError::UnsupportedInBytes { span: Span { start: 0, end: 11 } }));
+        assert_eq!(parse_bytes(b"ab{!test}"), Err(EdoError::UnsupportedInBytes { span: Span { start: 2, end: 9 } }));
+        match parse_bytes(b"{\xff}") {
+            Err(EdoError::InvalidUtf8 { span, .. }) => assert_eq!(span, Span { start: 0, end: 3 }),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_double_braces() {
+        let parsed = parse_with_syntax("{\"a\": {{ pad(2, {x}) }}, {{!b|upper:>3}}}", Syntax::DoubleBrace).unwrap();
+        assert_eq!(parsed.len(), 5);
+        assert_eq!(parsed[0], Expression::Literal("{\"a\": "));
+        match parsed[1] {
+            Expression::Function { name, ref arguments, ref argument_spans, span, .. } => {
+                assert_eq!((name, arguments.clone(), span), ("pad", vec!["2", "{x}"], Span { start: 6, end: 23 }));
+                assert_eq!(argument_spans[0], Span { start: 13, end: 14 });
+            },
+            ref other => panic!("{:?}", other),
+        }
+        match parsed[3] {
+            Expression::Function { name, raw, format, span, .. } => assert_eq!((name, raw, format, span), ("b", true, Some(">3"), Span { start: 25, end: 40 })),
+            ref other => panic!("{:?}", other),
+        }
+        assert_eq!(parsed[4], Expression::Literal("}"));
+        assert_eq!(parse_with_syntax("{a}", Syntax::DoubleBrace), Ok(vec![Expression::Literal("{a}")]));
+        assert_eq!(parse_with_syntax("{a}", Syntax::Brace), parse("{a}"));
+
+        assert_eq!(parse_with_syntax("x {{ a }\ny", Syntax::DoubleBrace), Err(EdoError::UnterminatedPlaceholder { span: Span { start: 2, end: 8 } }));
+        assert_eq!(parse_with_syntax("{{ a(b }}", Syntax::DoubleBrace), Err(EdoError::BadArguments { span: Span { start: 1, end: 8 } }));
+    }
 }
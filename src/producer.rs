@@ -0,0 +1,133 @@
+//! Producers that can be made before the template they are registered on, see [`Edo::with_producers`](../struct.Edo.html#method.with_producers)
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use args::{Args, Arity};
+use error::EdoError;
+use parse::{self, Syntax};
+use ValueProducer;
+
+/// Something that produces the value of a placeholder, like the ones the `register_` methods of [`Edo`](../struct.Edo.html) register
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::producer::Producer;
+///
+/// let mut template = Edo::with_producers("{greeting} {name(Gio)}{tags}", vec![
+///     ("greeting", Producer::value("Hello")),
+///     ("name", Producer::handler(|args, _| Ok(args[0].to_string()))),
+///     ("tags", Producer::list(vec!["!", "?"])),
+/// ]).unwrap();
+/// assert_eq!(template.render(&()), "Hello Gio!, ?");
+/// ```
+pub struct Producer<'a, C: ?Sized>(pub(crate) ValueProducer<'a, C>);
+
+impl<'a, C: ?Sized> Producer<'a, C> {
+    /// A handler, see [`Edo::register_handler`](../struct.Edo.html#method.register_handler)
+    pub fn handler<F>(handler: F) -> Producer<'a, C> where
+        F: 'static + Fn(Args, &C) -> Result<String, String> {
+        Producer(ValueProducer::Handler(Box::new(handler), None))
+    }
+
+    /// A handler that takes `arity` arguments, see [`Edo::register_handler_with_arity`](../struct.Edo.html#method.register_handler_with_arity)
+    pub fn handler_with_arity<A, F>(arity: A, handler: F) -> Producer<'a, C> where
+        A: Into<Arity>,
+        F: 'static + Fn(Args, &C) -> Result<String, String> {
+        Producer(ValueProducer::Handler(Box::new(handler), Some(arity.into())))
+    }
+
+    /// A handler that is also called with the locale of the render, see [`Edo::register_localized_handler`](../struct.Edo.html#method.register_localized_handler)
+    #[cfg(feature = "locale")]
+    pub fn localized_handler<F>(handler: F) -> Producer<'a, C> where
+        F: 'static + Fn(Args, &C, Option<&str>) -> Result<String, String> {
+        Producer(ValueProducer::Localized(Box::new(handler)))
+    }
+
+    /// A handler that returns a list, see [`Edo::register_list_handler`](../struct.Edo.html#method.register_list_handler)
+    pub fn list_handler<F>(handler: F) -> Producer<'a, C> where
+        F: 'static + Fn(Args, &C) -> Result<Vec<String>, String> {
+        Producer(ValueProducer::ListHandler(Box::new(handler)))
+    }
+
+    /// A static replacement, see [`Edo::register_static`](../struct.Edo.html#method.register_static)
+    pub fn value<S: Into<String>>(value: S) -> Producer<'a, C> {
+        Producer(ValueProducer::Static(value.into()))
+    }
+
+    /// A static list, see [`Edo::register_static_list`](../struct.Edo.html#method.register_static_list)
+    pub fn list<I>(items: I) -> Producer<'a, C> where
+        I: IntoIterator,
+        I::Item: Into<String> {
+        Producer(ValueProducer::StaticList(items.into_iter().map(Into::into).collect()))
+    }
+
+    /// A nested template, see [`Edo::register_template`](../struct.Edo.html#method.register_template)
+    ///
+    /// It is written with single braces, use [`template_with_syntax`](#method.template_with_syntax) for the templates of another [`Syntax`](../parse/enum.Syntax.html).
+    pub fn template(template: &'a str) -> Result<Producer<'a, C>, EdoError> {
+        Producer::template_with_syntax(template, Syntax::Brace)
+    }
+
+    /// A nested template whose placeholders are written with `syntax`, like the templates [`Edo::register_template`](../struct.Edo.html#method.register_template) registers on a template made with [`Edo::new_with_syntax`](../struct.Edo.html#method.new_with_syntax)
+    ///
+    /// # Examples
+    /// ```
+    /// # use edo::Edo;
+    /// use edo::parse::Syntax;
+    /// use edo::producer::Producer;
+    ///
+    /// let mut template = Edo::new_with_syntax("{ \"user\": {{ user }} }", Syntax::DoubleBrace).unwrap();
+    /// template.register_producer("user", Producer::template_with_syntax("{ \"name\": \"{{ name }}\" }", Syntax::DoubleBrace).unwrap());
+    /// template.register_static("name", "Gio");
+    /// assert_eq!(template.render(&()), "{ \"user\": { \"name\": \"Gio\" } }");
+    /// ```
+    pub fn template_with_syntax(template: &'a str, syntax: Syntax) -> Result<Producer<'a, C>, EdoError> {
+        Ok(Producer(ValueProducer::Template(parse::parse_with_syntax(template, syntax)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use error::{RegisterError, RenderError};
+    use parse::{Span, Syntax};
+    use super::Producer;
+    use Edo;
+
+    #[test]
+    fn register_producers() {
+        let mut edo = Edo::with_producers("{a} {b} {c(1)} {d(x)} {e}", vec![
+            ("a", Producer::template("{b}!").unwrap()),
+            ("b", Producer::value("x")),
+            ("c", Producer::handler_with_arity(0, |_, _| Ok("never".to_string()))),
+            ("d", Producer::list_handler(|args, context: &str| Ok(vec![args[0].to_string(), context.to_string()]))),
+        ]).unwrap();
+        edo.register_producer("e", Producer::list(vec!["y", "z"]));
+        let (output, errors) = edo.render_with_errors("ctx");
+        assert_eq!(output, "x! x  x, ctx y, z");
+        assert_eq!(errors, vec![RenderError::WrongArity { name: "c".to_string(), expected: 0.into(), found: 1, span: Span { start: 11, end: 12 } }]);
+
+        assert!(edo.register_static("b", "y").is_some());
+        assert!(edo.register_handler("f", |_, _| Ok("f".to_string())).is_none());
+        assert!(edo.register_template("f", "{b}").unwrap().is_some());
+        assert_eq!(edo.try_register_producer("b", Producer::value("z")), Err(RegisterError::Duplicate { name: "b".to_string() }));
+        assert_eq!(edo.try_register_producer("g", Producer::value("z")), Ok(()));
+        edo.set_template("{b}{f}{g}").unwrap();
+        assert_eq!(edo.render(""), "yyz");
+
+        assert!(Producer::<()>::template("{").is_err());
+        assert!(Edo::<()>::with_producers("{", vec![]).is_err());
+    }
+    #[test]
+    fn register_producers_with_syntax() {
+        let mut edo = Edo::new_with_syntax("{{ a }}", Syntax::DoubleBrace).unwrap();
+        edo.register_producer("a", Producer::template_with_syntax("{ {{ b }} }", Syntax::DoubleBrace).unwrap());
+        edo.register_static("b", "x");
+        assert_eq!(edo.render(&()), "{ x }");
+        assert!(Producer::<()>::template_with_syntax("{{", Syntax::DoubleBrace).is_err());
+    }
+}
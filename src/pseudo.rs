@@ -0,0 +1,70 @@
+// Pseudo-localization, see `RenderOptions::pseudo_localize`
+
+use alloc::string::String;
+
+const PLAIN: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const ACCENTED: &str = "ÅƁÇĐÉƑĜĤÎĴĶĻṀÑÖÞǪŔŠŦÛṼŴẊÝŽáƀçðéƒĝĥîĵķļṁñöþǫŕšŧûṽŵẋýž";
+
+// An accented look-alike of an ASCII letter
+fn accent(character: char) -> char {
+    PLAIN.find(character)
+        .and_then(|index| ACCENTED.chars().nth(index))
+        .unwrap_or(character)
+}
+
+// `text` with accented letters, padded by about 30% and put in brackets, keeping the whitespace around it
+//
+// Markup like `<a href="...">` and entities like `&amp;` are copied as they are, so HTML templates still work.
+pub(crate) fn localize(text: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.into();
+    }
+    let start = text.len() - text.trim_start().len();
+    let mut output = String::with_capacity(text.len() * 2);
+    output.push_str(&text[..start]);
+    output.push('[');
+
+    let mut letters: usize = 0;
+    let mut rest = trimmed;
+    while let Some(character) = rest.chars().next() {
+        let markup = match character {
+            '<' => rest.find('>').map(|end| end + 1),
+            '&' => rest.find(';').filter(|&end| end > 1 && rest[1..end].chars().all(|c| c.is_ascii_alphanumeric() || c == '#')).map(|end| end + 1),
+            _ => None,
+        };
+        match markup {
+            Some(length) => {
+                output.push_str(&rest[..length]);
+                rest = &rest[length..];
+            },
+            None => {
+                if !character.is_whitespace() {
+                    letters += 1;
+                }
+                output.push(accent(character));
+                rest = &rest[character.len_utf8()..];
+            },
+        }
+    }
+
+    output.extend(core::iter::repeat_n('~', (letters * 3).div_ceil(10)));
+    output.push(']');
+    output.push_str(&text[start + trimmed.len()..]);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::localize;
+
+    #[test]
+    fn pseudo_localize() {
+        assert_eq!(localize("Hello, world"), "[Ĥéļļö, ŵöŕļð~~~~]");
+        assert_eq!(localize("\n  Save "), "\n  [Šáṽé~~] ");
+        assert_eq!(localize("Ünïcode 123"), "[Üñïçöðé 123~~~]");
+        assert_eq!(localize("<b>Tom &amp; Jerry</b> & co;"), "[<b>Ŧöṁ &amp; Ĵéŕŕý</b> & çö;~~~~]");
+        assert_eq!(localize(" \n"), " \n");
+        assert_eq!(localize(""), "");
+    }
+}
@@ -0,0 +1,185 @@
+//! The result of a render
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use error::RenderError;
+use parse::Span;
+
+/// How a problem found while rendering is reported, set per kind of problem on [`RenderOptions`](../options/struct.RenderOptions.html)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Not reported at all
+    Ignore,
+    /// Reported in [`RenderReport::warnings`](struct.RenderReport.html#structfield.warnings)
+    Warning,
+    /// Reported in [`RenderReport::errors`](struct.RenderReport.html#structfield.errors)
+    Error,
+}
+
+/// How a placeholder got its value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// A handler returned the value
+    Handler,
+    /// The value was registered with `register_static`
+    Static,
+    /// A nested template was rendered
+    Template,
+    /// The value was looked up in the context, see [`Edo::use_context_lookup`](../struct.Edo.html#method.use_context_lookup)
+    Context,
+    /// The fallback returned the value
+    Fallback,
+    /// A handler returned an error, so the placeholder was left empty
+    Failed,
+    /// Nothing resolved the placeholder or it isn't allowed, so it was left empty
+    Skipped,
+}
+
+/// What happened to a single placeholder during a render
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaceholderOutcome {
+    /// The name of the placeholder
+    pub name: String,
+    /// Where the placeholder appears, in the template it was parsed from
+    pub span: Span,
+    /// How the placeholder got its value
+    pub resolution: Resolution,
+    /// How long producing, filtering and escaping the value took
+    ///
+    /// This is `None` without the `std` feature and on `wasm32`, where there is no clock to read.
+    pub duration: Option<Duration>,
+}
+
+/// The output of a render, with the problems found along the way
+///
+/// Problems never stop the render, the placeholder is left empty instead. Errors that abort a render are returned as `Err` by [`Edo::try_render`](../struct.Edo.html#method.try_render).
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::options::RenderOptions;
+/// use edo::report::Severity;
+///
+/// let mut template = Edo::new("{greeting} {name}").unwrap();
+/// template.register_handler("greeting", |_, _| Err("No greeting".to_string()));
+/// template.set_options(RenderOptions::new().missing_producers(Severity::Warning));
+/// let report = template.try_render("").unwrap();
+/// assert_eq!(report.output, " ");
+/// assert_eq!(report.warnings[0].to_string(), "Nothing produces name");
+/// assert_eq!(report.errors[0].to_string(), "No greeting");
+///
+/// use edo::report::Resolution;
+/// assert_eq!(report.placeholders[0].name, "greeting");
+/// assert_eq!(report.placeholders[0].resolution, Resolution::Failed);
+/// assert_eq!(report.placeholders[1].resolution, Resolution::Skipped);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RenderReport<O = String> {
+    /// The rendered template
+    pub output: O,
+    /// Problems reported with [`Severity::Warning`](enum.Severity.html#variant.Warning)
+    pub warnings: Vec<RenderError>,
+    /// Problems reported with [`Severity::Error`](enum.Severity.html#variant.Error)
+    pub errors: Vec<RenderError>,
+    /// Every placeholder that was rendered, in the order they finished
+    ///
+    /// Placeholders inside a nested template come just before the placeholder that included it.
+    pub placeholders: Vec<PlaceholderOutcome>,
+}
+
+impl<O> RenderReport<O> {
+    /// Whether the render finished without errors, warnings are allowed
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Call counts and durations for each placeholder name in this render
+    ///
+    /// See [`Edo::metrics`](../struct.Edo.html#method.metrics) for totals across renders.
+    pub fn metrics(&self) -> BTreeMap<String, PlaceholderMetrics> {
+        let mut metrics = BTreeMap::new();
+        add_metrics(&mut metrics, &self.placeholders);
+        metrics
+    }
+
+    // Record a problem according to its severity
+    pub(crate) fn report(&mut self, severity: Severity, error: RenderError) {
+        match severity {
+            Severity::Ignore => {},
+            Severity::Warning => self.warnings.push(error),
+            Severity::Error => self.errors.push(error),
+        }
+    }
+}
+
+/// How often a placeholder was rendered and how long it took
+///
+/// Durations stay at zero without a clock, see [`PlaceholderOutcome::duration`](struct.PlaceholderOutcome.html#structfield.duration). A nested template's durations include the placeholders inside it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PlaceholderMetrics {
+    /// How many times the placeholder was rendered
+    pub calls: u64,
+    /// How many of those renders had a failing handler
+    pub failures: u64,
+    /// The time spent on all of them
+    pub total_duration: Duration,
+    /// The time spent on the slowest one
+    pub max_duration: Duration,
+}
+
+// Add the outcomes of a render to per name metrics
+pub(crate) fn add_metrics(metrics: &mut BTreeMap<String, PlaceholderMetrics>, outcomes: &[PlaceholderOutcome]) {
+    for outcome in outcomes {
+        let entry = metrics.entry(outcome.name.clone()).or_default();
+        entry.calls += 1;
+        if outcome.resolution == Resolution::Failed {
+            entry.failures += 1;
+        }
+        let duration = outcome.duration.unwrap_or_default();
+        entry.total_duration += duration;
+        entry.max_duration = entry.max_duration.max(duration);
+    }
+}
+
+/// One step of a traced render, see [`Edo::render_traced`](../struct.Edo.html#method.render_traced)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEvent {
+    /// The registered template the expression is in, `None` for the template being rendered
+    pub template: Option<String>,
+    /// The position of the expression in its template
+    pub index: usize,
+    /// What happened to the placeholder, `None` for literals
+    pub placeholder: Option<PlaceholderOutcome>,
+    /// The arguments the placeholder was called with
+    pub arguments: Vec<String>,
+    /// How many bytes the expression added to the output
+    pub output_length: usize,
+}
+
+// Times a placeholder when there is a clock available
+pub(crate) struct Timer {
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    started: ::std::time::Instant,
+}
+
+impl Timer {
+    pub(crate) fn start() -> Timer {
+        Timer {
+            #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+            started: ::std::time::Instant::now(),
+        }
+    }
+
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    pub(crate) fn elapsed(&self) -> Option<Duration> {
+        Some(self.started.elapsed())
+    }
+
+    #[cfg(not(all(feature = "std", not(target_arch = "wasm32"))))]
+    pub(crate) fn elapsed(&self) -> Option<Duration> {
+        None
+    }
+}
@@ -0,0 +1,504 @@
+//! Turning serializable values into static replacements
+//!
+//! See [`Edo::register_serialize`](../struct.Edo.html#method.register_serialize), or [`bind_fields`](fn.bind_fields.html) for values that change with every render.
+
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::ser::{self, Impossible, Serialize};
+
+use context::Lookup;
+
+/// An error flattening a value into static replacements
+#[derive(Debug, PartialEq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// `std::error::Error` with `std`, serde's stand-in without it
+impl ser::StdError for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+/// Flatten a value into `(path, value)` pairs
+///
+/// Struct fields and map entries are joined to their parent path with a `.`, sequence elements use their index and enum variants are named like serde's externally tagged representation. `None` and unit values become empty strings.
+pub(crate) fn flatten<T: Serialize + ?Sized>(prefix: &str, value: &T) -> Result<Vec<(String, String)>, Error> {
+    let mut output = vec![];
+    value.serialize(Flatten { path: prefix.to_string(), output: &mut output })?;
+    Ok(output)
+}
+
+/// The fields of a serializable value as a render context, without deriving [`Lookup`](../context/trait.Lookup.html) for it
+///
+/// Fields are named like [`Edo::register_serialize`](../struct.Edo.html#method.register_serialize) names them with an empty prefix, but they are looked up in the context of each render, see [`Edo::use_context_lookup`](../struct.Edo.html#method.use_context_lookup).
+///
+/// # Examples
+/// ```
+/// # extern crate edo;
+/// # extern crate serde;
+/// # use serde::Serialize;
+/// # use edo::Edo;
+/// use edo::ser::{bind_fields, Fields};
+///
+/// #[derive(Serialize)]
+/// struct Order {
+///     id: u32,
+///     customer: &'static str,
+/// }
+///
+/// # fn main() {
+/// let mut template: Edo<Fields> = Edo::new("Order {id} for {customer}").unwrap();
+/// template.use_context_lookup();
+/// let order = bind_fields(&Order { id: 7, customer: "Gio" }).unwrap();
+/// assert_eq!(template.render(&order), "Order 7 for Gio");
+/// # }
+/// ```
+pub fn bind_fields<T: Serialize + ?Sized>(value: &T) -> Result<Fields, Error> {
+    Ok(Fields { values: flatten("", value)?.into_iter().collect() })
+}
+
+/// The fields of a value, made by [`bind_fields`](fn.bind_fields.html)
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Fields {
+    values: BTreeMap<String, String>,
+}
+
+impl Lookup for Fields {
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        self.values.get(name).map(|value| Cow::Borrowed(value.as_str()))
+    }
+}
+
+fn join(path: &str, segment: &str) -> String {
+    if path.is_empty() {
+        segment.to_string()
+    } else {
+        format!("{}.{}", path, segment)
+    }
+}
+
+struct Flatten<'o> {
+    path: String,
+    output: &'o mut Vec<(String, String)>,
+}
+
+impl<'o> Flatten<'o> {
+    fn leaf<T: fmt::Display>(self, value: T) -> Result<(), Error> {
+        if self.path.is_empty() {
+            return Err(Error(format!("a value without a name can't be registered, use a prefix or a struct (got {})", value)));
+        }
+        self.output.push((self.path, value.to_string()));
+        Ok(())
+    }
+
+    fn compound(self, segment: Option<&str>) -> Compound<'o> {
+        Compound {
+            path: match segment {
+                Some(segment) => join(&self.path, segment),
+                None => self.path,
+            },
+            output: self.output,
+            index: 0,
+            key: None,
+        }
+    }
+}
+
+macro_rules! serialize_display {
+    ($($method:ident($ty:ty);)*) => {
+        $(
+            fn $method(self, value: $ty) -> Result<(), Error> {
+                self.leaf(value)
+            }
+        )*
+    };
+}
+
+impl<'o> ser::Serializer for Flatten<'o> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'o>;
+    type SerializeTuple = Compound<'o>;
+    type SerializeTupleStruct = Compound<'o>;
+    type SerializeTupleVariant = Compound<'o>;
+    type SerializeMap = Compound<'o>;
+    type SerializeStruct = Compound<'o>;
+    type SerializeStructVariant = Compound<'o>;
+
+    serialize_display! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_i128(i128);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_u128(u128);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_str(&str);
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<(), Error> {
+        Err(Error(format!("{} is a byte array, which can't be registered as text", self.path)))
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.leaf("")
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.leaf("")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.leaf("")
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<(), Error> {
+        self.leaf(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32, variant: &'static str, value: &T) -> Result<(), Error> {
+        value.serialize(Flatten { path: join(&self.path, variant), output: self.output })
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'o>, Error> {
+        Ok(self.compound(None))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'o>, Error> {
+        Ok(self.compound(None))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'o>, Error> {
+        Ok(self.compound(None))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, variant: &'static str, _len: usize) -> Result<Compound<'o>, Error> {
+        Ok(self.compound(Some(variant)))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'o>, Error> {
+        Ok(self.compound(None))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'o>, Error> {
+        Ok(self.compound(None))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str, _len: usize) -> Result<Compound<'o>, Error> {
+        Ok(self.compound(Some(variant)))
+    }
+}
+
+/// Collects the children of a sequence, map or struct
+struct Compound<'o> {
+    path: String,
+    output: &'o mut Vec<(String, String)>,
+    index: usize,
+    key: Option<String>,
+}
+
+impl<'o> Compound<'o> {
+    fn child<T: Serialize + ?Sized>(&mut self, segment: &str, value: &T) -> Result<(), Error> {
+        value.serialize(Flatten { path: join(&self.path, segment), output: &mut *self.output })
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let index = self.index.to_string();
+        self.index += 1;
+        self.child(&index, value)
+    }
+}
+
+impl<'o> ser::SerializeSeq for Compound<'o> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'o> ser::SerializeTuple for Compound<'o> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'o> ser::SerializeTupleStruct for Compound<'o> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'o> ser::SerializeTupleVariant for Compound<'o> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'o> ser::SerializeMap for Compound<'o> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(key.serialize(MapKey)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().expect("serialize_value called before serialize_key");
+        self.child(&key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'o> ser::SerializeStruct for Compound<'o> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.child(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'o> ser::SerializeStructVariant for Compound<'o> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.child(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Turns map keys into path segments
+struct MapKey;
+
+macro_rules! key_display {
+    ($($method:ident($ty:ty);)*) => {
+        $(
+            fn $method(self, value: $ty) -> Result<String, Error> {
+                Ok(value.to_string())
+            }
+        )*
+    };
+}
+
+macro_rules! key_unsupported {
+    ($($method:ident($($arg:ident: $ty:ty),*) -> $ok:ty;)*) => {
+        $(
+            fn $method(self, $($arg: $ty),*) -> Result<$ok, Error> {
+                Err(Error("map keys must be strings, numbers or booleans".to_string()))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for MapKey {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    key_display! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_i128(i128);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_u128(u128);
+        serialize_char(char);
+        serialize_str(&str);
+    }
+
+    key_unsupported! {
+        serialize_f32(_value: f32) -> String;
+        serialize_f64(_value: f64) -> String;
+        serialize_bytes(_value: &[u8]) -> String;
+        serialize_none() -> String;
+        serialize_unit() -> String;
+        serialize_unit_struct(_name: &'static str) -> String;
+        serialize_seq(_len: Option<usize>) -> Impossible<String, Error>;
+        serialize_tuple(_len: usize) -> Impossible<String, Error>;
+        serialize_tuple_struct(_name: &'static str, _len: usize) -> Impossible<String, Error>;
+        serialize_tuple_variant(_name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Impossible<String, Error>;
+        serialize_map(_len: Option<usize>) -> Impossible<String, Error>;
+        serialize_struct(_name: &'static str, _len: usize) -> Impossible<String, Error>;
+        serialize_struct_variant(_name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Impossible<String, Error>;
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<String, Error> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32, _variant: &'static str, _value: &T) -> Result<String, Error> {
+        Err(Error("map keys must be strings, numbers or booleans".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::BTreeMap;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+
+    use serde::Serialize;
+
+    use context::Lookup;
+    use super::{bind_fields, flatten, Error};
+
+    #[derive(Serialize)]
+    enum Role {
+        Admin,
+        Guest(u32),
+    }
+
+    #[derive(Serialize)]
+    struct Address {
+        city: &'static str,
+    }
+
+    #[derive(Serialize)]
+    struct User {
+        name: &'static str,
+        age: u8,
+        nickname: Option<&'static str>,
+        address: Address,
+        tags: Vec<&'static str>,
+        scores: BTreeMap<&'static str, f32>,
+        role: Role,
+        guest: Role,
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|&(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn flatten_struct() {
+        let mut scores = BTreeMap::new();
+        scores.insert("math", 9.5);
+        let user = User {
+            name: "Gio",
+            age: 30,
+            nickname: None,
+            address: Address { city: "Portland" },
+            tags: vec!["a", "b"],
+            scores,
+            role: Role::Admin,
+            guest: Role::Guest(7),
+        };
+        assert_eq!(flatten("user", &user), Ok(pairs(&[
+            ("user.name", "Gio"),
+            ("user.age", "30"),
+            ("user.nickname", ""),
+            ("user.address.city", "Portland"),
+            ("user.tags.0", "a"),
+            ("user.tags.1", "b"),
+            ("user.scores.math", "9.5"),
+            ("user.role", "Admin"),
+            ("user.guest.Guest", "7"),
+        ])));
+    }
+
+    #[test]
+    fn flatten_without_prefix() {
+        assert_eq!(flatten("", &Address { city: "Portland" }), Ok(pairs(&[("city", "Portland")])));
+        assert_eq!(flatten("count", &3), Ok(pairs(&[("count", "3")])));
+        assert!(flatten("", &3).is_err());
+    }
+
+    #[test]
+    fn bind_struct_fields() {
+        let fields = bind_fields(&Address { city: "Portland" }).unwrap();
+        assert_eq!((fields.get("city").as_deref(), fields.get("town")), (Some("Portland"), None));
+        assert_eq!(bind_fields(&3), Err(Error("a value without a name can't be registered, use a prefix or a struct (got 3)".to_string())));
+    }
+
+    #[test]
+    fn flatten_unsupported_key() {
+        let mut map = BTreeMap::new();
+        map.insert(vec![1], "one");
+        assert_eq!(flatten("map", &map), Err(Error("map keys must be strings, numbers or booleans".to_string())));
+    }
+}
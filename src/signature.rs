@@ -0,0 +1,95 @@
+//! The placeholders a template uses
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+
+use parse::{self, Expression};
+
+/// The names of the placeholders in a template, with the numbers of arguments each is called with
+///
+/// Placeholders used as arguments, like the `{name}` in `{shout({name})}`, are included.
+///
+/// Comparing signatures checks that a changed or uploaded template only uses placeholders the application can produce, before it is ever rendered.
+///
+/// # Examples
+/// ```
+/// # use edo::Edo;
+/// use edo::signature::Signature;
+///
+/// let known = Signature::new().with("name", 0).with("date", 0).with("date", 1);
+///
+/// let uploaded: Edo<()> = Edo::new("Hi {name}, it's {date(%A)}").unwrap();
+/// assert!(uploaded.signature().is_compatible_with(&known));
+///
+/// let uploaded: Edo<()> = Edo::new("Hi {name(formal)}").unwrap();
+/// assert!(!uploaded.signature().is_compatible_with(&known));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Signature {
+    placeholders: BTreeMap<String, BTreeSet<usize>>,
+}
+
+impl Signature {
+    /// A signature without placeholders
+    pub fn new() -> Signature {
+        Signature::default()
+    }
+
+    /// Add a placeholder called with `arity` arguments
+    pub fn with(mut self, name: &str, arity: usize) -> Signature {
+        self.placeholders.entry(name.to_string()).or_default().insert(arity);
+        self
+    }
+
+    pub(crate) fn of(template: &[Expression]) -> Signature {
+        let mut signature = Signature::new();
+        for expression in template {
+            signature.add(expression);
+        }
+        signature
+    }
+
+    // Add a placeholder and the placeholders in its arguments
+    fn add(&mut self, expression: &Expression) {
+        if let Expression::Function { name, ref arguments, .. } = *expression {
+            self.placeholders.entry(name.to_string()).or_default().insert(arguments.len());
+            for reference in arguments.iter().filter_map(|argument| parse::reference(argument)) {
+                self.add(&reference);
+            }
+        }
+    }
+
+    /// The placeholder names, with the numbers of arguments each is called with
+    pub fn placeholders(&self) -> &BTreeMap<String, BTreeSet<usize>> {
+        &self.placeholders
+    }
+
+    /// Whether every placeholder of this signature is in `other` with the same number of arguments
+    ///
+    /// `other` can have more placeholders, such as the ones an application can produce.
+    pub fn is_compatible_with(&self, other: &Signature) -> bool {
+        self.placeholders.iter().all(|(name, arities)| other.placeholders.get(name).is_some_and(|known| arities.is_subset(known)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use parse;
+    use super::Signature;
+
+    #[test]
+    fn compare_signatures() {
+        let template = parse::parse("{a} {b(1, 2)} {a|upper} {b()} {c(x, {a(y)})}").unwrap();
+        let signature = Signature::of(&template);
+        assert_eq!(signature, Signature::new().with("a", 0).with("a", 1).with("b", 2).with("b", 0).with("c", 2));
+        assert_eq!(signature.placeholders()["b"].iter().cloned().collect::<Vec<_>>(), vec![0, 2]);
+
+        assert!(signature.is_compatible_with(&signature));
+        assert!(Signature::new().is_compatible_with(&signature));
+        assert!(Signature::new().with("a", 0).is_compatible_with(&signature));
+        assert!(!signature.is_compatible_with(&Signature::new().with("a", 0)));
+        assert!(!Signature::new().with("c", 1).is_compatible_with(&signature));
+    }
+}
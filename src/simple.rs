@@ -0,0 +1,122 @@
+//! Templates rendered without a context
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut};
+
+use args::{Args, Arity};
+use error::{EdoError, RenderError};
+use report::RenderReport;
+use Edo;
+
+/// A template that is rendered without a context, so handlers only get their arguments
+///
+/// Everything not about the context, like [`register_static`](../struct.Edo.html#method.register_static) or [`set_options`](../struct.Edo.html#method.set_options), is used through the [`Edo`](../struct.Edo.html) it wraps.
+///
+/// # Examples
+/// ```
+/// use edo::simple::SimpleEdo;
+///
+/// let mut template = SimpleEdo::new("Hello {name}, {shout(hi)}").unwrap();
+/// template.register_static("name", "World");
+/// template.register_handler("shout", |args| Ok(args[0].to_uppercase()));
+/// assert_eq!(template.render(), "Hello World, HI");
+/// ```
+pub struct SimpleEdo<'a> {
+    edo: Edo<'a, ()>,
+}
+
+impl<'a> SimpleEdo<'a> {
+    /// Creates a new template, see [`Edo::new`](../struct.Edo.html#method.new)
+    pub fn new(template_string: &'a str) -> Result<SimpleEdo<'a>, EdoError> {
+        Ok(SimpleEdo { edo: Edo::new(template_string)? })
+    }
+
+    /// Register a handler that is called with the placeholder's arguments
+    pub fn register_handler<F>(&mut self, name: &str, handler: F) where
+        F: 'static + Fn(Args) -> Result<String, String> {
+        self.edo.register_handler(name, move |args, ()| handler(args));
+    }
+
+    /// Register a handler that takes `arity` arguments, see [`Edo::register_handler_with_arity`](../struct.Edo.html#method.register_handler_with_arity)
+    pub fn register_handler_with_arity<A, F>(&mut self, name: &str, arity: A, handler: F) where
+        A: Into<Arity>,
+        F: 'static + Fn(Args) -> Result<String, String> {
+        self.edo.register_handler_with_arity(name, arity, move |args, ()| handler(args));
+    }
+
+    /// Register a handler that returns a list, see [`Edo::register_list_handler`](../struct.Edo.html#method.register_list_handler)
+    pub fn register_list_handler<F>(&mut self, name: &str, handler: F) where
+        F: 'static + Fn(Args) -> Result<Vec<String>, String> {
+        self.edo.register_list_handler(name, move |args, ()| handler(args));
+    }
+
+    /// Render the template into a string
+    pub fn render(&mut self) -> String {
+        self.edo.render(&())
+    }
+
+    /// Render the template and recieve a vector of errors, see [`Edo::render_with_errors`](../struct.Edo.html#method.render_with_errors)
+    pub fn render_with_errors(&mut self) -> (String, Vec<RenderError>) {
+        self.edo.render_with_errors(&())
+    }
+
+    /// Render the template into a report, see [`Edo::try_render`](../struct.Edo.html#method.try_render)
+    pub fn try_render(&mut self) -> Result<RenderReport, RenderError> {
+        self.edo.try_render(&())
+    }
+
+    /// The template this wraps
+    pub fn into_inner(self) -> Edo<'a, ()> {
+        self.edo
+    }
+}
+
+impl<'a> From<Edo<'a, ()>> for SimpleEdo<'a> {
+    fn from(edo: Edo<'a, ()>) -> SimpleEdo<'a> {
+        SimpleEdo { edo }
+    }
+}
+
+impl<'a> Deref for SimpleEdo<'a> {
+    type Target = Edo<'a, ()>;
+
+    fn deref(&self) -> &Edo<'a, ()> {
+        &self.edo
+    }
+}
+
+impl<'a> DerefMut for SimpleEdo<'a> {
+    fn deref_mut(&mut self) -> &mut Edo<'a, ()> {
+        &mut self.edo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::SimpleEdo;
+    use Edo;
+
+    #[test]
+    fn render_without_context() {
+        let mut edo = match SimpleEdo::new("{a} {b(x)} {c} {d(1, 2)}") {
+            Ok(edo) => edo,
+            Err(err) => panic!("{}", err),
+        };
+        edo.register_static("a", "1");
+        edo.register_handler("b", |args| Ok(args[0].to_string()));
+        edo.register_list_handler("c", |_| Ok(vec!["y".to_string(), "z".to_string()]));
+        edo.register_handler_with_arity("d", 1, |_| Ok("never".to_string()));
+        assert_eq!(edo.render(), "1 x y, z ");
+        assert_eq!(edo.render_with_errors().1.len(), 1);
+        assert_eq!(edo.try_render().map(|report| report.output), Ok("1 x y, z ".to_string()));
+
+        let mut edo = SimpleEdo::from(edo.into_inner());
+        edo.set_template("{a}").unwrap();
+        assert_eq!(edo.render(), "1");
+        let mut plain: Edo<()> = edo.into_inner();
+        assert_eq!(plain.render(&()), "1");
+    }
+}
@@ -0,0 +1,139 @@
+//! Parsed templates that are rendered with the producers of an [`Edo`](../struct.Edo.html)
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use catalog::{self, Message};
+use engine::Compiled;
+use error::{EdoError, RenderError};
+use escape::{self, HtmlContext};
+use parse::Expression;
+use report::RenderReport;
+use Edo;
+
+/// A parsed template that can't be changed, rendered with whichever producers are passed in
+///
+/// Cloning a template shares the parsed form instead of parsing it again. Any [`Edo`](../struct.Edo.html) holds the producers, filters, escaping and options it is rendered with, usually an empty [`Edo::default`](../struct.Edo.html#impl-Default). Only a shared borrow of it is needed, and renders aren't added to its [`metrics`](../struct.Edo.html#method.metrics).
+///
+/// Templates can be sent to and shared between threads, each rendering with its own producers.
+///
+/// # Examples
+/// ```
+/// use edo::Edo;
+/// use edo::template::Template;
+///
+/// let template = Template::new("Hello {name}").unwrap();
+/// let mut english: Edo<()> = Edo::default();
+/// english.register_static("name", "World");
+/// let mut spanish: Edo<()> = Edo::default();
+/// spanish.register_static("name", "Mundo");
+///
+/// assert_eq!(template.render(&english, &()), "Hello World");
+/// assert_eq!(template.clone().render(&spanish, &()), "Hello Mundo");
+/// ```
+#[derive(Clone)]
+pub struct Template {
+    shared: Arc<Shared>,
+}
+
+// What clones of a template share
+struct Shared {
+    compiled: Compiled,
+    // Found once, for bindings that escape for the HTML context of each placeholder
+    html_contexts: Vec<HtmlContext>,
+}
+
+impl Template {
+    /// Parse a copy of `template_string`
+    pub fn new(template_string: &str) -> Result<Template, EdoError> {
+        let compiled = Compiled::new(template_string.to_string())?;
+        let html_contexts = escape::html_contexts(&compiled.expressions());
+        Ok(Template { shared: Arc::new(Shared { compiled, html_contexts }) })
+    }
+
+    /// The text the template was parsed from
+    pub fn source(&self) -> &str {
+        self.shared.compiled.source()
+    }
+
+    /// The parsed template, see [`Edo::segments`](../struct.Edo.html#method.segments)
+    pub fn expressions(&self) -> Vec<Expression<'_>> {
+        self.shared.compiled.expressions()
+    }
+
+    /// The literal text of the template, see [`Edo::extract_messages`](../struct.Edo.html#method.extract_messages)
+    pub fn extract_messages(&self) -> Vec<Message<'_>> {
+        catalog::messages(&self.shared.compiled.expressions())
+    }
+
+    /// Render the template with the producers and settings of `bindings`, see [`Edo::render`](../struct.Edo.html#method.render)
+    pub fn render<C: ?Sized>(&self, bindings: &Edo<C>, context: &C) -> String {
+        self.render_with_errors(bindings, context).0
+    }
+
+    /// Render the template and recieve a vector of errors, see [`Edo::render_with_errors`](../struct.Edo.html#method.render_with_errors)
+    pub fn render_with_errors<C: ?Sized>(&self, bindings: &Edo<C>, context: &C) -> (String, Vec<RenderError>) {
+        match self.try_render(bindings, context) {
+            Ok(report) => (report.output, report.errors),
+            Err(error) => ("".to_string(), vec![error]),
+        }
+    }
+
+    /// Render the template into a report, see [`Edo::try_render`](../struct.Edo.html#method.try_render)
+    pub fn try_render<C: ?Sized>(&self, bindings: &Edo<C>, context: &C) -> Result<RenderReport, RenderError> {
+        bindings.render_parsed(&self.shared.compiled.expressions(), &self.shared.html_contexts, context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use error::{EdoError, RenderError};
+    use escape::Escaping;
+    use options::RenderOptions;
+    use parse::Span;
+    use super::Template;
+    use Edo;
+
+    #[test]
+    fn render_with_bindings() {
+        let template = Template::new("<{a}> {b(x)}").unwrap();
+        assert_eq!(template.source(), "<{a}> {b(x)}");
+        assert_eq!(template.expressions().len(), 4);
+        assert_eq!(template.extract_messages().len(), 2);
+
+        let mut bindings = Edo::default();
+        bindings.register_static("a", "<1>");
+        bindings.register_handler("b", |args, context: &str| Ok(format!("{}{}", args[0], context)));
+        assert_eq!(template.render(&bindings, "!"), "<<1>> x!");
+
+        let shared = template.clone();
+        bindings.set_escaping(Escaping::Html);
+        assert_eq!(shared.render(&bindings, "?"), "<&lt;1&gt;> x?");
+
+        assert_eq!(shared.render_with_errors(&Edo::default(), ""), ("<> ".into(), vec![]));
+
+        bindings.set_options(RenderOptions::new().max_output(2));
+        assert_eq!(shared.try_render(&bindings, ""), Err(RenderError::OutputTooLarge { limit: 2 }));
+        assert_eq!(Template::new("{a").err(), Some(EdoError::UnterminatedPlaceholder { span: Span { start: 0, end: 2 } }));
+    }
+
+    #[test]
+    fn render_like_edo() {
+        let source = "<a href=\"{url}\">{url}</a>\n{url}";
+        let options = RenderOptions::new().line_prefix("> ");
+        let mut edo = Edo::new(source).unwrap();
+        edo.register_static("url", "a\"b c");
+        edo.set_escaping(Escaping::ContextualHtml);
+        edo.set_options(options);
+        let expected = edo.render(&());
+        assert_eq!(expected, "> <a href=\"a&#x22;b&#x20;c\">a&quot;b c</a>\n> a&quot;b c");
+        assert_eq!(Template::new(source).unwrap().render(&edo, &()), expected);
+    }
+
+    #[test]
+    fn share_between_threads() {
+        fn shared<T: Send + Sync>() {}
+        shared::<Template>();
+    }
+}
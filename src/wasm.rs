@@ -0,0 +1,71 @@
+//! JavaScript bindings for WebAssembly builds
+//!
+//! Build with the `wasm` feature for `wasm32-unknown-unknown` and run `wasm-bindgen` over the output to get an `Edo` class with the same template semantics as the Rust crate.
+//!
+//! ```js
+//! const template = new Edo("Hello {name}");
+//! template.registerStatic("name", "World!");
+//! template.render(); // "Hello World!"
+//! ```
+
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+use wasm_bindgen::prelude::*;
+
+use parse;
+use Edo;
+
+/// A template exposed to JavaScript as `Edo`
+#[wasm_bindgen(js_name = Edo)]
+pub struct Template {
+    source: String,
+    statics: Vec<(String, String)>,
+}
+
+#[wasm_bindgen(js_class = Edo)]
+impl Template {
+    /// Parse a new template, throwing if it is invalid
+    #[wasm_bindgen(constructor)]
+    pub fn new(source: String) -> Result<Template, JsValue> {
+        parse::parse(&source).map_err(|error| JsValue::from_str(&error.to_string()))?;
+        Ok(Template {
+            source,
+            statics: vec![],
+        })
+    }
+
+    /// Register a static replacement
+    #[wasm_bindgen(js_name = registerStatic)]
+    pub fn register_static(&mut self, name: String, value: String) {
+        self.statics.push((name, value));
+    }
+
+    /// Render the template into a string
+    pub fn render(&self) -> String {
+        // JavaScript owns the source, so the template is parsed again for every render
+        let mut edo: Edo<()> = Edo::new(&self.source).expect("template was validated when it was created");
+        for (name, value) in &self.statics {
+            edo.register_static(name, value.as_str());
+        }
+        edo.render(&())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::string::ToString;
+
+    use super::Template;
+
+    #[test]
+    fn render_statics() {
+        let mut template = match Template::new("Hello {name}{missing}".to_string()) {
+            Ok(template) => template,
+            Err(_) => panic!("failed to parse template"),
+        };
+        template.register_static("name".to_string(), "World".to_string());
+        template.register_static("name".to_string(), "World!".to_string());
+        assert_eq!(template.render(), "Hello World!");
+    }
+}